@@ -0,0 +1,113 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wasm_thing::decode_bytes;
+
+/// `wat2wasm tests/fixtures/add.wat`, the smallest realistic module.
+const ADD_WASM: &[u8] = include_bytes!("../tests/fixtures/add.wasm");
+
+/// Append `value` as an unsigned LEB128.
+fn write_varuint(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    write_varuint(out, name.len() as u32);
+    out.extend_from_slice(name.as_bytes());
+}
+
+fn write_section(out: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    out.push(id);
+    write_varuint(out, contents.len() as u32);
+    out.extend_from_slice(contents);
+}
+
+fn header() -> Vec<u8> {
+    vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00]
+}
+
+/// A module importing `count` functions of type `(param i32)` from `env`.
+fn import_heavy_module(count: u32) -> Vec<u8> {
+    let mut module = header();
+    write_section(&mut module, 1, &[0x01, 0x60, 0x01, 0x7f, 0x00]);
+
+    let mut imports = Vec::new();
+    write_varuint(&mut imports, count);
+    for index in 0..count {
+        write_name(&mut imports, "env");
+        write_name(&mut imports, &format!("imported_function_{index}"));
+        imports.extend_from_slice(&[0x00, 0x00]);
+    }
+    write_section(&mut module, 2, &imports);
+
+    module
+}
+
+/// A module defining `count` functions of type `(result i32)`, each summing 64 constants,
+/// with every tenth function exported.
+fn code_heavy_module(count: u32) -> Vec<u8> {
+    let mut module = header();
+    write_section(&mut module, 1, &[0x01, 0x60, 0x00, 0x01, 0x7f]);
+
+    let mut functions = Vec::new();
+    write_varuint(&mut functions, count);
+    // Every function uses type 0.
+    functions.resize(functions.len() + count as usize, 0x00);
+    write_section(&mut module, 3, &functions);
+
+    let exported: Vec<u32> = (0..count).step_by(10).collect();
+    let mut exports = Vec::new();
+    write_varuint(&mut exports, exported.len() as u32);
+    for index in exported {
+        write_name(&mut exports, &format!("function_{index}"));
+        exports.push(0x00);
+        write_varuint(&mut exports, index);
+    }
+    write_section(&mut module, 7, &exports);
+
+    // No locals, `i32.const 0`, then 63 × (`i32.const n`, `i32.add`), then `end`.
+    let mut body = vec![0x00, 0x41, 0x00];
+    for n in 1..64 {
+        body.extend_from_slice(&[0x41, n, 0x6a]);
+    }
+    body.push(0x0b);
+
+    let mut code = Vec::new();
+    write_varuint(&mut code, count);
+    for _ in 0..count {
+        write_varuint(&mut code, body.len() as u32);
+        code.extend_from_slice(&body);
+    }
+    write_section(&mut module, 10, &code);
+
+    module
+}
+
+fn bench_decode_bytes(c: &mut Criterion) {
+    let modules = [
+        ("add", ADD_WASM.to_vec()),
+        ("imports_1000", import_heavy_module(1000)),
+        ("code_100", code_heavy_module(100)),
+        ("code_10000", code_heavy_module(10_000)),
+    ];
+
+    let mut group = c.benchmark_group("decode_bytes");
+    for (name, bytes) in &modules {
+        assert!(decode_bytes(bytes).is_ok(), "{name} does not decode");
+
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), bytes, |b, bytes| {
+            b.iter(|| decode_bytes(black_box(bytes)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode_bytes);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+//! Print the sections, types and imports of a module.
+//!
+//! ```text
+//! cargo run --example wasmdump -- tests/fixtures/imports.wasm
+//! ```
+
+use std::process::ExitCode;
+use wasm_thing::types::WasmImportDescriptor;
+use wasm_thing::DecodeError;
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: wasmdump <file.wasm>");
+        return ExitCode::FAILURE;
+    };
+
+    let module = match wasm_thing::decode_file(&path) {
+        Ok(module) => module,
+        // Errors reading the file already name it.
+        Err(error @ DecodeError::File { .. }) => {
+            eprintln!("wasmdump: {error}");
+            return ExitCode::FAILURE;
+        }
+        Err(error) => {
+            eprintln!("wasmdump: cannot decode {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("{path}: version {}", module.version().version());
+
+    println!("\nSections:");
+    for section in module.sections() {
+        println!(
+            "  {:<10} offset {:#08x}, {} bytes",
+            section.section_type().name(),
+            section.offset(),
+            section.length()
+        );
+    }
+
+    println!("\nTypes:");
+    for (index, function_type) in module.types().iter().enumerate() {
+        println!("  {index}: {function_type}");
+    }
+
+    println!("\nImports:");
+    for import in module.imports() {
+        let kind = match import.descriptor() {
+            WasmImportDescriptor::Function(type_index) => format!("func (type {type_index})"),
+            WasmImportDescriptor::Table(table_type) => format!("table ({})", table_type.limits()),
+            WasmImportDescriptor::Memory(memory_type) => {
+                format!("memory ({})", memory_type.limits())
+            }
+            WasmImportDescriptor::Global(global_type) => format!("global {global_type}"),
+            descriptor => format!("{:?}", descriptor.kind()),
+        };
+        println!("  {}.{}: {kind}", import.module_name(), import.field_name());
+    }
+
+    ExitCode::SUCCESS
+}
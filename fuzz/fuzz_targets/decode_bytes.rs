@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Decoding arbitrary input may fail, but it must never panic: every malformed
+// binary has to come back as an `Err`.
+fuzz_target!(|data: &[u8]| {
+    let _ = wasm_thing::decode_bytes(data);
+});
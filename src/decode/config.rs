@@ -0,0 +1,235 @@
+use crate::types::SectionType;
+
+/// Options controlling how strictly a binary is decoded, and how large a binary may be.
+///
+/// The defaults follow the specification; relaxing them is meant for tooling that
+/// deliberately inspects malformed files. The default limits mirror those of web engines, so
+/// anything an engine would load decodes, while absurd counts and sizes fail with
+/// `DecodeError::LimitExceeded` before anything is allocated for them.
+#[derive(Clone, Copy, Debug)]
+pub struct DecoderConfig {
+    pub(crate) check_section_order: bool,
+    pub(crate) check_version: bool,
+    pub(crate) lossy_names: bool,
+    pub(crate) skip_magic: bool,
+    pub(crate) strict_leb128: bool,
+    pub(crate) allow_gc: bool,
+    pub(crate) allow_shared_tables: bool,
+    pub(crate) allow_table64: bool,
+    pub(crate) allow_shared_memories: bool,
+    pub(crate) allow_memory64: bool,
+    pub(crate) reject_unknown_sections: bool,
+    pub(crate) max_module_size: u32,
+    pub(crate) max_section_size: u32,
+    pub(crate) max_vector_len: u32,
+    pub(crate) max_types: u32,
+    pub(crate) max_imports: u32,
+    pub(crate) max_functions: u32,
+    pub(crate) max_globals: u32,
+    pub(crate) max_exports: u32,
+    pub(crate) max_data_segments: u32,
+    pub(crate) max_body_size: u32,
+    pub(crate) max_name_length: u32,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        Self {
+            check_section_order: true,
+            check_version: true,
+            lossy_names: false,
+            skip_magic: false,
+            strict_leb128: true,
+            allow_gc: false,
+            allow_shared_tables: false,
+            allow_table64: false,
+            allow_shared_memories: false,
+            allow_memory64: false,
+            reject_unknown_sections: false,
+            max_module_size: 1 << 30,
+            max_section_size: 1 << 30,
+            max_vector_len: 100_000,
+            max_types: 1_000_000,
+            max_imports: 100_000,
+            max_functions: 1_000_000,
+            max_globals: 1_000_000,
+            max_exports: 100_000,
+            max_data_segments: 100_000,
+            max_body_size: 10 << 20,
+            max_name_length: 100_000,
+        }
+    }
+}
+
+impl DecoderConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require non-custom sections to appear in the order given by the specification.
+    /// Enabled by default.
+    pub fn check_section_order(mut self, enabled: bool) -> Self {
+        self.check_section_order = enabled;
+        self
+    }
+
+    /// Require the binary format version to be 1, the only version defined so far.
+    /// Enabled by default; disable it to experiment with other versions.
+    pub fn check_version(mut self, enabled: bool) -> Self {
+        self.check_version = enabled;
+        self
+    }
+
+    /// Replace invalid UTF-8 in import, export and custom section names with U+FFFD instead
+    /// of failing with `DecodeError::InvalidName`. Disabled by default; some tools emit
+    /// names that are raw bytes.
+    pub fn lossy_names(mut self, enabled: bool) -> Self {
+        self.lossy_names = enabled;
+        self
+    }
+
+    /// Decode input that starts at the version field, without the magic bytes, e.g. a module
+    /// taken out of a container that replaces its header. Everything after the magic is still
+    /// checked, and offsets in errors are relative to the start of the input. Disabled by
+    /// default.
+    pub fn skip_magic(mut self, enabled: bool) -> Self {
+        self.skip_magic = enabled;
+        self
+    }
+
+    /// Reject unsigned integers encoded with a redundant final zero byte, e.g. `0x80 0x00` for
+    /// 0, with `DecodeError::NonCanonicalLeb128`. Enabled by default; disable it for toolchains
+    /// that pad integers to a fixed width, which then only produces a warning.
+    pub fn strict_leb128(mut self, enabled: bool) -> Self {
+        self.strict_leb128 = enabled;
+        self
+    }
+
+    /// Decode the typed references of the function references and GC proposals, such as
+    /// `(ref null $t)`, wherever a value type is expected. Disabled by default, as the rest of
+    /// those proposals is not supported yet; element types of tables are still limited to
+    /// `funcref` and `externref`.
+    pub fn allow_gc(mut self, enabled: bool) -> Self {
+        self.allow_gc = enabled;
+        self
+    }
+
+    /// Decode tables flagged as shared, from the shared-everything threads proposal. Disabled
+    /// by default, which rejects them with `DecodeError::LimitsFlags`.
+    pub fn allow_shared_tables(mut self, enabled: bool) -> Self {
+        self.allow_shared_tables = enabled;
+        self
+    }
+
+    /// Decode tables with 64-bit indices, from the memory64 proposal, whose limits are 64-bit
+    /// too. Disabled by default, which rejects them with `DecodeError::LimitsFlags`.
+    pub fn allow_table64(mut self, enabled: bool) -> Self {
+        self.allow_table64 = enabled;
+        self
+    }
+
+    /// Decode memories flagged as shared, from the threads proposal. Disabled by default,
+    /// which rejects them with `DecodeError::MemoryLimitsFlags`.
+    pub fn allow_shared_memories(mut self, enabled: bool) -> Self {
+        self.allow_shared_memories = enabled;
+        self
+    }
+
+    /// Decode memories with 64-bit addresses, from the memory64 proposal, whose limits are
+    /// 64-bit too. Disabled by default, which rejects them with
+    /// `DecodeError::MemoryLimitsFlags`.
+    pub fn allow_memory64(mut self, enabled: bool) -> Self {
+        self.allow_memory64 = enabled;
+        self
+    }
+
+    /// Treat a section with an unknown id as the end of the module, failing with
+    /// `DecodeError::TrailingBytes`, as the specification requires. Disabled by default, which
+    /// keeps such sections undecoded as `SectionType::Unknown`, so that modules using newer
+    /// proposals still decode and encode back unchanged.
+    pub fn reject_unknown_sections(mut self, enabled: bool) -> Self {
+        self.reject_unknown_sections = enabled;
+        self
+    }
+
+    /// The largest module, in bytes. Defaults to 1 GiB.
+    pub fn max_module_size(mut self, limit: u32) -> Self {
+        self.max_module_size = limit;
+        self
+    }
+
+    /// The largest section, in bytes, checked against the size in its header before anything
+    /// of it is decoded. Defaults to 1 GiB.
+    pub fn max_section_size(mut self, limit: u32) -> Self {
+        self.max_section_size = limit;
+        self
+    }
+
+    /// The most items in a vector within a section entry, such as the parameters of a function
+    /// type, the locals of a body or the targets of a `br_table`. Defaults to 100,000.
+    pub fn max_vector_len(mut self, limit: u32) -> Self {
+        self.max_vector_len = limit;
+        self
+    }
+
+    /// The most entries in the type section. Defaults to 1,000,000.
+    pub fn max_types(mut self, limit: u32) -> Self {
+        self.max_types = limit;
+        self
+    }
+
+    /// The most entries in the import section. Defaults to 100,000.
+    pub fn max_imports(mut self, limit: u32) -> Self {
+        self.max_imports = limit;
+        self
+    }
+
+    /// The most entries in the function and code sections. Defaults to 1,000,000.
+    pub fn max_functions(mut self, limit: u32) -> Self {
+        self.max_functions = limit;
+        self
+    }
+
+    /// The most entries in the global section. Defaults to 1,000,000.
+    pub fn max_globals(mut self, limit: u32) -> Self {
+        self.max_globals = limit;
+        self
+    }
+
+    /// The most entries in the export section. Defaults to 100,000.
+    pub fn max_exports(mut self, limit: u32) -> Self {
+        self.max_exports = limit;
+        self
+    }
+
+    /// The most segments in the data section. Defaults to 100,000.
+    pub fn max_data_segments(mut self, limit: u32) -> Self {
+        self.max_data_segments = limit;
+        self
+    }
+
+    /// The largest function body, in bytes. Defaults to 10 MiB.
+    pub fn max_body_size(mut self, limit: u32) -> Self {
+        self.max_body_size = limit;
+        self
+    }
+
+    /// The longest import, export or custom section name, in bytes. Defaults to 100,000.
+    pub fn max_name_length(mut self, limit: u32) -> Self {
+        self.max_name_length = limit;
+        self
+    }
+
+    /// What the entry count of a section is limited by, and to how many entries.
+    pub(crate) fn item_limit(&self, section_type: SectionType) -> Option<(&'static str, u32)> {
+        match section_type {
+            SectionType::Type => Some(("types", self.max_types)),
+            SectionType::Import => Some(("imports", self.max_imports)),
+            SectionType::Function | SectionType::Code => Some(("functions", self.max_functions)),
+            SectionType::Global => Some(("globals", self.max_globals)),
+            SectionType::Export => Some(("exports", self.max_exports)),
+            SectionType::Data => Some(("data segments", self.max_data_segments)),
+            _ => None,
+        }
+    }
+}
@@ -1,19 +1,26 @@
-use crate::decode::{DecodeError, FUNCTION_MAGIC_BYTES, HEADER_MAGIC_BYTES};
+use crate::decode::Result;
+use crate::decode::{
+    DecodeError, DecodeWarning, DecoderConfig, WarningKind, FUNCTION_MAGIC_BYTES,
+    HEADER_MAGIC_BYTES,
+};
 use crate::types::{
-    GlobalType, ImportSection, MemoryType, Mutability, TableType, TypeSection, VarUInt,
-    WasmElementType, WasmFunctionType, WasmImportDescriptor, WasmImportEntry, WasmLimits,
-    WasmSection, WasmValueType,
+    CodeSection, CustomSection, DataMode, DataSection, DataSegment, ElementItems, ElementMode,
+    ElementSection, ElementSegment, ExportSection, ExternalKind, FunctionBody, FunctionSection,
+    Global, GlobalSection, GlobalType, HeapType, ImportSection, MemorySection, MemoryType,
+    Mutability, RefType, SectionType, TableSection, TableType, Tag, TagSection, TypeSection,
+    VarUInt, WasmElementType, WasmExportDescriptor, WasmExportEntry, WasmFunctionType,
+    WasmImportDescriptor, WasmImportEntry, WasmLimits, WasmLocals, WasmSection, WasmValueType,
+    WasmVersion,
 };
-use anyhow::Result;
-use std::io::{Cursor, Read};
+use std::borrow::Cow;
+use std::io::Cursor;
 
 macro_rules! decode_dummy_section {
     ($name:ident, $section:ident, $docs:expr) => {
         doc_comment! {
             concat!("Decode a ", $docs, "section. This function will read from the provided reader and create a `WasmSection` variant corresponding section that contains no data."),
-            fn $name(&mut self, size: u32) -> Result<WasmSection> {
-                let mut custom_section = vec![0; size as usize];
-                self.reader.read_exact(&mut custom_section)?;
+            fn $name(&mut self, size: u32) -> Result<WasmSection<'a>> {
+                self.read_slice(size)?;
 
                 Ok(WasmSection::$section(()))
             }
@@ -21,88 +28,689 @@ macro_rules! decode_dummy_section {
     };
 }
 
-macro_rules! read_bytes_const {
-    ($reader:expr, $size:expr) => {{
-        let mut buf = [0; $size];
-        $reader.read_exact(&mut buf)?;
-        buf
-    }};
+/// Names what was being read when the input ran out.
+pub(crate) trait EofContext<T> {
+    /// Fill in the context of an `UnexpectedEof` that does not have one yet, so the innermost
+    /// (most specific) context wins.
+    fn eof_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T> EofContext<T> for Result<T> {
+    fn eof_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|mut error| {
+            if let Some(unset) = unset_eof_context(&mut error) {
+                *unset = context().into();
+            }
+            error
+        })
+    }
+}
+
+/// The empty context of an `UnexpectedEof`, looking through any `InSection` around it.
+fn unset_eof_context(error: &mut DecodeError) -> Option<&mut String> {
+    match error {
+        DecodeError::UnexpectedEof { context, .. } if context.is_empty() => Some(context),
+        DecodeError::InSection { source, .. } => unset_eof_context(source),
+        _ => None,
+    }
+}
+
+/// The id and declared size that precede the contents of every section.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SectionHeader {
+    pub(crate) id: SectionType,
+    pub(crate) size: u32,
+}
+
+/// The fewest bytes an entry of `section_type` takes, for the sections whose counts are checked
+/// against their size.
+fn min_item_size(section_type: SectionType) -> Option<u64> {
+    match section_type {
+        // The 0x60 marker and two empty vectors.
+        SectionType::Type => Some(3),
+        _ => None,
+    }
+}
+
+/// Wrap `source` in `DecodeError::InSection`.
+fn in_section(
+    section_type: SectionType,
+    item_index: Option<u32>,
+    source: DecodeError,
+) -> DecodeError {
+    DecodeError::InSection {
+        section_id: section_type.id(),
+        item_index,
+        source: Box::new(source),
+    }
 }
 
-macro_rules! read_bytes {
-    ($reader:expr, $size:expr) => {{
-        let mut buf = vec![0; $size as usize];
-        $reader.read_exact(&mut buf)?;
-        buf
-    }};
+/// Fail with `LimitExceeded` if `found`, read at `offset`, is above `limit`.
+pub(crate) fn check_limit(
+    what: &'static str,
+    limit: u32,
+    found: impl Into<u64>,
+    offset: u64,
+) -> Result<()> {
+    let found = found.into();
+    if found > limit as u64 {
+        return Err(DecodeError::LimitExceeded {
+            what,
+            limit: limit as u64,
+            found,
+            offset,
+        });
+    }
+
+    Ok(())
 }
 
+/// Upper bound on the capacity reserved for a vector before any of its items are decoded.
+const MAX_PREALLOCATED_ITEMS: usize = 1024;
+
 pub(crate) struct Decoder<'a> {
     reader: Cursor<&'a [u8]>,
+    /// Offset of the start of `reader` in the whole module, for error messages.
+    base_offset: u64,
+    /// Position in `reader` where the enclosing section ends. Length fields are checked against
+    /// it, so that a length running past its section is not mistaken for a truncated input.
+    limit: u64,
+    config: DecoderConfig,
+    /// Collected here, then handed to the enclosing decoder when a section or body finishes.
+    pub(crate) warnings: Vec<DecodeWarning>,
 }
 
 impl<'a> Decoder<'a> {
     pub(crate) fn new(reader: Cursor<&'a [u8]>) -> Self {
-        Self { reader }
+        Self::with_config(reader, DecoderConfig::default())
+    }
+
+    pub(crate) fn with_config(reader: Cursor<&'a [u8]>, config: DecoderConfig) -> Self {
+        let limit = reader.get_ref().len() as u64;
+
+        Self {
+            reader,
+            base_offset: 0,
+            limit,
+            config,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Create a decoder over the input starting at the current position, for decoding the
+    /// contents of a section of `size` bytes. Only length fields are limited to the declared
+    /// size; other reads may run past it, so that `finish_section` can tell exactly how many
+    /// bytes the contents took.
+    fn section_decoder(&self, size: u32) -> Decoder<'a> {
+        let bytes: &'a [u8] = self.reader.get_ref();
+        let position = (self.reader.position() as usize).min(bytes.len());
+
+        Decoder {
+            reader: Cursor::new(&bytes[position..]),
+            base_offset: self.base_offset + position as u64,
+            limit: size as u64,
+            config: self.config,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Take the next `length` bytes of the input and return a decoder limited to them, e.g. for
+    /// a function body that declares its own size.
+    pub(crate) fn slice_decoder(&mut self, length: u32) -> Result<Decoder<'a>> {
+        let base_offset = self.offset();
+        let bytes = self.read_slice(length)?;
+
+        Ok(Decoder {
+            reader: Cursor::new(bytes),
+            base_offset,
+            limit: length as u64,
+            config: self.config,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Check that a section's contents consumed exactly its declared size, then skip past it.
+    fn finish_section(
+        &mut self,
+        mut section_decoder: Decoder<'a>,
+        section_type: SectionType,
+        size: u32,
+    ) -> Result<()> {
+        let consumed = section_decoder.position();
+        if consumed != size as u64 {
+            return Err(DecodeError::SectionSize {
+                section_id: section_type.id(),
+                declared: size,
+                consumed,
+                offset: section_decoder.base_offset,
+            });
+        }
+
+        self.reader.set_position(self.reader.position() + consumed);
+        self.warnings.append(&mut section_decoder.warnings);
+        Ok(())
+    }
+
+    fn warn(&mut self, kind: WarningKind, offset: u64) {
+        self.warnings.push(DecodeWarning { kind, offset });
+    }
+
+    /// Whether every byte of the input has been consumed.
+    pub(crate) fn is_end(&self) -> bool {
+        self.reader.position() >= self.reader.get_ref().len() as u64
+    }
+
+    /// The number of bytes after the current position that belong to the enclosing section,
+    /// or to the whole input outside of a section.
+    pub(crate) fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.reader.position())
+    }
+
+    /// Fail with `LengthOutOfBounds` unless `length` bytes fit in the rest of the enclosing
+    /// section. `field` names what the length belongs to.
+    fn check_length(&self, field: &'static str, length: u32) -> Result<()> {
+        let remaining = self.remaining();
+        if length as u64 > remaining {
+            return Err(DecodeError::LengthOutOfBounds {
+                field,
+                length,
+                remaining,
+                offset: self.offset(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn position(&self) -> u64 {
+        self.reader.position()
+    }
+
+    /// An `UnexpectedEof` at the end of this decoder's input, `needed` bytes short.
+    pub(crate) fn unexpected_eof(&self, needed: u64) -> DecodeError {
+        DecodeError::UnexpectedEof {
+            offset: self.base_offset + self.reader.get_ref().len() as u64,
+            needed,
+            context: String::new(),
+        }
+    }
+
+    /// The current position within the whole module, rather than within this decoder's input.
+    pub(crate) fn offset(&self) -> u64 {
+        self.base_offset + self.reader.position()
+    }
+
+    pub(crate) fn set_position(&mut self, position: u64) {
+        self.reader.set_position(position)
+    }
+
+    /// Decode a vector: a varuint count followed by that many items.
+    /// The count is untrusted, so only a bounded capacity is reserved up front; longer vectors
+    /// grow as their items actually decode.
+    pub(crate) fn decode_vec<T>(
+        &mut self,
+        mut decode_item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        self.decode_indexed_vec(|decoder, _| decode_item(decoder))
+    }
+
+    /// Like `decode_vec`, but also passes the index of each item, for error reporting.
+    pub(crate) fn decode_indexed_vec<T>(
+        &mut self,
+        mut decode_item: impl FnMut(&mut Self, u32) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let offset = self.offset();
+        let count = self.decode_varuint()?;
+        let capacity = count.to_usize_checked()?.min(MAX_PREALLOCATED_ITEMS);
+        let count: u32 = count.into();
+        check_limit("vector length", self.config.max_vector_len, count, offset)?;
+        let mut items = Vec::with_capacity(capacity);
+
+        for index in 0..count {
+            items.push(decode_item(self, index)?);
+        }
+
+        Ok(items)
     }
 
-    fn decode_varuint(&mut self) -> Result<VarUInt> {
+    /// Decode the entries of a section like `decode_indexed_vec`, wrapping any error in
+    /// `DecodeError::InSection` so it says which section and entry it happened in.
+    fn decode_section_items<T>(
+        &mut self,
+        section_type: SectionType,
+        mut decode_item: impl FnMut(&mut Self, u32) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut item_index = None;
+
+        let mut decode_items = |decoder: &mut Self| {
+            let count = decoder.decode_section_count(section_type)?;
+            let capacity = VarUInt::from(count).to_usize_checked()?;
+            let mut items = Vec::with_capacity(capacity.min(MAX_PREALLOCATED_ITEMS));
+
+            for index in 0..count {
+                item_index = Some(index);
+                items.push(decode_item(decoder, index)?);
+            }
+
+            Ok(items)
+        };
+
+        decode_items(self).map_err(|source| in_section(section_type, item_index, source))
+    }
+
+    /// Read the number of entries in a section, failing with `LimitExceeded` if the
+    /// `DecoderConfig` allows fewer, and with `MalformedCount` if the rest of the section is too
+    /// short to hold them.
+    fn decode_section_count(&mut self, section_type: SectionType) -> Result<u32> {
+        let offset = self.offset();
+        let count: u32 = self.decode_varuint()?.into();
+
+        if let Some((what, limit)) = self.config.item_limit(section_type) {
+            check_limit(what, limit, count, offset)?;
+        }
+
+        let remaining = self.remaining();
+        if let Some(min_size) = min_item_size(section_type) {
+            if count as u64 * min_size > remaining {
+                return Err(DecodeError::MalformedCount {
+                    declared: count,
+                    remaining,
+                    offset,
+                });
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Borrow the next `length` bytes of the input without copying them.
+    pub(crate) fn read_slice(&mut self, length: u32) -> Result<&'a [u8]> {
+        let remaining = (self.reader.get_ref().len() as u64).saturating_sub(self.reader.position());
+        if length as u64 > remaining {
+            return Err(self.unexpected_eof(length as u64 - remaining));
+        }
+
+        let bytes: &'a [u8] = self.reader.get_ref();
+        let start = self.reader.position() as usize;
+        let slice = &bytes[start..start + VarUInt::from(length).to_usize_checked()?];
+
+        self.reader.set_position((start + slice.len()) as u64);
+        Ok(slice)
+    }
+
+    /// Read a name: a varuint length followed by that many bytes of UTF-8, borrowed from the
+    /// input. `field` says which name it is, and `section` and `item_index` whose, for errors.
+    ///
+    /// With `DecoderConfig::lossy_names`, invalid sequences are replaced by U+FFFD instead.
+    pub(crate) fn read_name(
+        &mut self,
+        section: &'static str,
+        field: &'static str,
+        item_index: u32,
+    ) -> Result<Cow<'a, str>> {
+        let length_offset = self.offset();
+        let length: u32 = self.decode_varuint().eof_context(|| field)?.into();
+        check_limit(
+            "name length",
+            self.config.max_name_length,
+            length,
+            length_offset,
+        )?;
+        self.check_length(field, length)?;
+
+        let offset = self.offset();
+        let bytes = self.read_slice(length).eof_context(|| field)?;
+
+        if self.config.lossy_names {
+            return Ok(String::from_utf8_lossy(bytes));
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(name) => Ok(Cow::Borrowed(name)),
+            Err(source) => Err(DecodeError::InvalidName {
+                section,
+                item_index,
+                offset,
+                source,
+            }),
+        }
+    }
+
+    pub(crate) fn read_byte(&mut self) -> Result<u8> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    pub(crate) fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_slice(N as u32)?);
+        Ok(array)
+    }
+
+    pub(crate) fn decode_varuint(&mut self) -> Result<VarUInt> {
+        let offset = self.offset();
         let mut value = 0;
 
         for i in 0.. {
-            let byte = read_bytes_const!(self.reader, 1)[0] as u32;
-            let shifted = (byte & 0x7f)
-                .checked_shl(i * 7)
-                .ok_or(DecodeError::Numeric {
+            // Running out after a continuation bit truncates the integer itself, rather than
+            // whatever it is part of.
+            let byte = match self.read_byte() {
+                Err(error) if i > 0 => return Err(error).eof_context(|| "varuint"),
+                byte => byte? as u32,
+            };
+            // The fifth byte holds the last four bits, and ends the integer.
+            if i == 4 && byte & 0xf0 != 0 {
+                return Err(DecodeError::Numeric {
                     current_value: value,
                     invalid_byte: byte,
-                })?;
-            value += shifted;
+                    offset,
+                });
+            }
+            value |= (byte & 0x7f) << (i * 7);
 
             if byte & 0x80 == 0 {
+                if i > 0 && byte == 0 {
+                    if self.config.strict_leb128 {
+                        return Err(DecodeError::NonCanonicalLeb128 { offset });
+                    }
+                    self.warn(WarningKind::NonMinimalLeb128, offset);
+                }
                 break;
             }
         }
 
         Ok(VarUInt::from(value))
     }
+
+    /// Like `decode_varuint`, for integers of up to 64 bits such as the limits of a 64-bit
+    /// table.
+    pub(crate) fn decode_varuint64(&mut self) -> Result<u64> {
+        let offset = self.offset();
+        let mut value = 0;
+
+        for i in 0.. {
+            let byte = match self.read_byte() {
+                Err(error) if i > 0 => return Err(error).eof_context(|| "varuint"),
+                byte => byte? as u64,
+            };
+            // The tenth byte holds the last bit.
+            if i > 9 || (i == 9 && byte > 0x01) {
+                return Err(DecodeError::Numeric {
+                    current_value: value as u32,
+                    invalid_byte: byte as u32,
+                    offset,
+                });
+            }
+            value |= (byte & 0x7f) << (i * 7);
+
+            if byte & 0x80 == 0 {
+                if i > 0 && byte == 0 {
+                    if self.config.strict_leb128 {
+                        return Err(DecodeError::NonCanonicalLeb128 { offset });
+                    }
+                    self.warn(WarningKind::NonMinimalLeb128, offset);
+                }
+                break;
+            }
+        }
+
+        Ok(value)
+    }
+
+    pub(crate) fn decode_varint32(&mut self) -> Result<i32> {
+        let offset = self.offset();
+        let value = self.decode_signed(32)?;
+
+        i32::try_from(value).map_err(|_| DecodeError::Numeric {
+            current_value: value as u32,
+            invalid_byte: (value >> 28) as u32,
+            offset,
+        })
+    }
+
+    pub(crate) fn decode_varint64(&mut self) -> Result<i64> {
+        self.decode_signed(64)
+    }
+
+    /// Decode a signed LEB128 integer of at most `bits` bits, sign-extending the result.
+    pub(crate) fn decode_signed(&mut self, bits: u32) -> Result<i64> {
+        let offset = self.offset();
+        let mut value: i64 = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = match self.read_byte() {
+                Err(error) if shift > 0 => return Err(error).eof_context(|| "varint"),
+                byte => byte?,
+            };
+            if shift >= bits {
+                return Err(DecodeError::Numeric {
+                    current_value: value as u32,
+                    invalid_byte: byte as u32,
+                    offset,
+                });
+            }
+
+            value |= ((byte & 0x7f) as i64) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                break;
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Decode a single entry of the code section: the body size, the local declarations and
+    /// the expression, which is kept as raw bytes once it has been checked to be well formed.
+    fn decode_function_body(&mut self, func_index: u32) -> Result<FunctionBody<'a>> {
+        let size_offset = self.offset();
+        let body_size: u32 = self.decode_varuint()?.into();
+        check_limit(
+            "code body size",
+            self.config.max_body_size,
+            body_size,
+            size_offset,
+        )?;
+        self.check_length("code body", body_size)?;
+        let mut body_decoder = self.slice_decoder(body_size)?;
+
+        let locals = body_decoder.decode_vec(|decoder| {
+            Ok(WasmLocals {
+                count: decoder.decode_varuint()?.into(),
+                value_type: decoder.decode_value_type()?,
+            })
+        })?;
+
+        let expression_start = body_decoder.position();
+        body_decoder.validate_expression(func_index)?;
+        body_decoder.set_position(expression_start);
+        let expression = body_decoder.read_slice(body_decoder.remaining() as u32)?;
+        self.warnings.append(&mut body_decoder.warnings);
+
+        Ok(FunctionBody {
+            locals,
+            expression: Cow::Borrowed(expression),
+        })
+    }
+
+    /// Decode a reference type, as used by tables and `ref.null`.
+    pub(crate) fn decode_element_type(&mut self) -> Result<WasmElementType> {
+        // The element type is represented by a byte. According to the WebAssembly specification,
+        // 0x70 corresponds to `funcref` in the MVP; the reference types proposal adds 0x6f.
+        let offset = self.offset();
+        let element_type_byte = self.read_byte()?;
+        match element_type_byte {
+            0x70 => Ok(WasmElementType::Funcref),
+            0x6f => Ok(WasmElementType::Externref),
+            // In future versions of WebAssembly, there might be additional element types.
+            _ => Err(DecodeError::ElementType {
+                invalid_byte: element_type_byte,
+                offset,
+            }),
+        }
+    }
+
+    /// Decode a value type encoded as a varuint, rejecting unknown type bytes.
+    pub(crate) fn decode_value_type(&mut self) -> Result<WasmValueType> {
+        let start = self.position();
+        let offset = self.offset();
+        let value = self.decode_varuint()?;
+
+        if self.config.allow_gc && matches!(u32::from(value), 0x63 | 0x64) {
+            self.set_position(start);
+            return Ok(WasmValueType::Ref(self.decode_ref_type()?));
+        }
+
+        WasmValueType::try_from(value).map_err(|value| DecodeError::ValueType { value, offset })
+    }
+
+    /// Decode a typed reference: `0x63` (`ref null`) or `0x64` (`ref`), then a heap type.
+    /// The heap type is an s33 that is either a type index, or negative for the single byte
+    /// of an abstract heap type such as `0x70` (`func`).
+    pub(crate) fn decode_ref_type(&mut self) -> Result<RefType> {
+        let offset = self.offset();
+        let nullable = match self.read_byte()? {
+            0x63 => true,
+            0x64 => false,
+            byte => {
+                return Err(DecodeError::ValueType {
+                    value: byte as u32,
+                    offset,
+                })
+            }
+        };
+
+        let offset = self.offset();
+        let value = self.decode_signed(33)?;
+        let heap_type = match u32::try_from(value) {
+            Ok(type_index) => HeapType::Concrete(type_index),
+            Err(_) => HeapType::from_abstract_byte(value as u8 & 0x7f)
+                .filter(|_| value >= -0x40)
+                .ok_or(DecodeError::HeapType { value, offset })?,
+        };
+
+        Ok(RefType::new(nullable, heap_type))
+    }
 }
 
 pub(crate) trait WasmDecoder<'a> {
     fn read_validate(&mut self) -> Result<()>;
-    fn read_version(&mut self) -> Result<u32>;
-    fn decode_type_section(&mut self, size: u32) -> Result<WasmSection>;
-    fn decode_custom_section(&mut self, size: u32) -> Result<WasmSection>;
-    fn decode_import_section(&mut self, size: u32) -> Result<WasmSection>;
-    fn decode_table_section(&mut self, size: u32) -> Result<WasmSection>;
-    fn decode_memory_section(&mut self, size: u32) -> Result<WasmSection>;
-    fn decode_global_section(&mut self, size: u32) -> Result<WasmSection>;
-    fn decode_start_section(&mut self, size: u32) -> Result<WasmSection>;
-    fn decode_element_section(&mut self, size: u32) -> Result<WasmSection>;
+    fn read_version(&mut self) -> Result<WasmVersion>;
+    fn decode_section_type(&mut self) -> Result<SectionHeader>;
+    fn decode_section(&mut self, section_type: SectionType, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_type_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_custom_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_import_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_function_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_table_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_memory_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_global_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_export_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_start_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_element_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_code_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_data_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_data_count_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
+    fn decode_tag_section(&mut self, size: u32) -> Result<WasmSection<'a>>;
 
     fn decode_table_type(&mut self) -> Result<TableType>;
     fn decode_memory_type(&mut self) -> Result<MemoryType>;
+    fn decode_limits(&mut self, flags: u8) -> Result<WasmLimits>;
     fn decode_global_type(&mut self) -> Result<GlobalType>;
 }
 
 impl<'a> WasmDecoder<'a> for Decoder<'a> {
     fn read_validate(&mut self) -> Result<()> {
-        let magic_bytes = read_bytes_const!(self.reader, 4);
+        let magic_bytes: [u8; 4] = self.read_array().eof_context(|| "magic bytes")?;
 
         if magic_bytes == HEADER_MAGIC_BYTES {
             return Ok(());
         }
 
         Err(DecodeError::Binary {
-            found: String::from_utf8(Vec::from(magic_bytes)).unwrap_or_default(),
-            expected: String::from_utf8(Vec::from(HEADER_MAGIC_BYTES)).unwrap_or_default(),
-        }
-        .into())
+            expected: HEADER_MAGIC_BYTES,
+            found: magic_bytes,
+        })
+    }
+
+    fn read_version(&mut self) -> Result<WasmVersion> {
+        let version_bytes = self.read_array().eof_context(|| "version")?;
+        Ok(u32::from_le_bytes(version_bytes).into())
+    }
+
+    /// Decode a section header.
+    /// Layout:
+    /// (1) section id (byte)
+    /// (2) section size in bytes (varuint)
+    ///
+    fn decode_section_type(&mut self) -> Result<SectionHeader> {
+        let offset = self.offset();
+        let id = self.read_byte()?;
+        let section_type = match SectionType::try_from(id) {
+            Ok(section_type) => section_type,
+            Err(id) if self.config.reject_unknown_sections => {
+                return Err(DecodeError::SectionId { id, offset })
+            }
+            Err(id) => SectionType::Unknown(id),
+        };
+
+        // A known id followed by the end of the input is a truncated module, not junk. An
+        // unknown id is junk unless followed by a size that fits in the input.
+        let size_offset = self.offset();
+        let size = self.decode_varuint().eof_context(|| "section header");
+        let size: u32 = match (section_type, size) {
+            (SectionType::Unknown(id), Err(_)) => {
+                return Err(DecodeError::SectionId { id, offset })
+            }
+            (SectionType::Unknown(id), Ok(size)) if u32::from(size) as u64 > self.remaining() => {
+                return Err(DecodeError::SectionId { id, offset })
+            }
+            (_, size) => size?.into(),
+        };
+        check_limit(
+            "section size",
+            self.config.max_section_size,
+            size,
+            size_offset,
+        )?;
+
+        Ok(SectionHeader {
+            id: section_type,
+            size,
+        })
     }
 
-    fn read_version(&mut self) -> Result<u32> {
-        let version_bytes = read_bytes_const!(self.reader, 4);
-        Ok(u32::from_le_bytes(version_bytes))
+    fn decode_section(&mut self, section_type: SectionType, size: u32) -> Result<WasmSection<'a>> {
+        // Fail before decoding anything if the section cannot possibly fit in the input.
+        let remaining = self.remaining();
+        if size as u64 > remaining {
+            return Err(self.unexpected_eof(size as u64 - remaining));
+        }
+
+        match section_type {
+            SectionType::Custom => self.decode_custom_section(size),
+            SectionType::Type => self.decode_type_section(size),
+            SectionType::Import => self.decode_import_section(size),
+            SectionType::Function => self.decode_function_section(size),
+            SectionType::Table => self.decode_table_section(size),
+            SectionType::Memory => self.decode_memory_section(size),
+            SectionType::Global => self.decode_global_section(size),
+            SectionType::Export => self.decode_export_section(size),
+            SectionType::Start => self.decode_start_section(size),
+            SectionType::Element => self.decode_element_section(size),
+            SectionType::Code => self.decode_code_section(size),
+            SectionType::Data => self.decode_data_section(size),
+            SectionType::DataCount => self.decode_data_count_section(size),
+            SectionType::Tag => self.decode_tag_section(size),
+            SectionType::Unknown(id) => Ok(WasmSection::Unknown {
+                id,
+                data: self.read_slice(size)?,
+            }),
+        }
     }
 
     /// Decode the type section of a WebAssembly binary.
@@ -115,44 +723,58 @@ impl<'a> WasmDecoder<'a> for Decoder<'a> {
     ///     - (5) returns count (varuint)
     ///         - type (varuint)
     ///
-    fn decode_type_section(&mut self, size: u32) -> Result<WasmSection> {
-        let section_bytes = read_bytes!(self.reader, size);
-
-        let mut section_decoder = Decoder::new(Cursor::new(&section_bytes));
-
-        let size: u32 = section_decoder.decode_varuint()?.into();
-        let items = (0..size)
-            .map(|_| {
-                // "...Function types are encoded by the byte 0x60
-                // followed by the respective vectors of parameter and result types."
-                let type_magic_bytes = read_bytes_const!(section_decoder.reader, 1);
-                if type_magic_bytes != FUNCTION_MAGIC_BYTES {
-                    return Err(DecodeError::TypeSectionBytes.into());
-                }
+    fn decode_type_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
 
-                let read_value_types =
-                    |count: u32,
-                     decoder: &mut Decoder|
-                     -> Result<Vec<WasmValueType>, anyhow::Error> {
-                        (0..count)
-                            .map(|_| Ok(WasmValueType::from(decoder.decode_varuint()?)))
-                            .collect()
-                    };
-
-                let param_count: u32 = section_decoder.decode_varuint()?.into();
-                let return_count: u32 = section_decoder.decode_varuint()?.into();
-
-                Ok(WasmFunctionType {
-                    params: read_value_types(param_count, &mut section_decoder)?,
-                    returns: read_value_types(return_count, &mut section_decoder)?,
-                })
-            })
-            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        let items = section_decoder.decode_section_items(SectionType::Type, |decoder, _| {
+            // "...Function types are encoded by the byte 0x60
+            // followed by the respective vectors of parameter and result types."
+            let offset = decoder.offset();
+            let type_magic_bytes = [decoder.read_byte()?];
+            if type_magic_bytes != FUNCTION_MAGIC_BYTES {
+                return Err(DecodeError::TypeSectionBytes {
+                    found: type_magic_bytes[0],
+                    offset,
+                });
+            }
+
+            let params = decoder.decode_vec(Decoder::decode_value_type)?;
+            let returns = decoder.decode_vec(Decoder::decode_value_type)?;
+
+            Ok(WasmFunctionType { params, returns })
+        })?;
+
+        self.finish_section(section_decoder, SectionType::Type, size)?;
 
         Ok(WasmSection::Type(TypeSection { items }))
     }
 
-    decode_dummy_section!(decode_custom_section, Custom, "Custom");
+    /// Decode a custom section of a WebAssembly binary. Only the name is validated; the
+    /// remaining contents are kept as raw bytes.
+    ///
+    /// Layout:
+    /// (1) name length (varuint)
+    /// (2) name (string)
+    /// (3) contents (bytes, up to the end of the section)
+    ///
+    fn decode_custom_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        // A custom section has a single name, so it is always item 0.
+        let offset = section_decoder.offset();
+        let name = section_decoder.read_name("custom", "custom section name", 0)?;
+        if section_decoder.remaining() == 0 {
+            section_decoder.warn(WarningKind::EmptyCustomSection, offset);
+        }
+        let data = section_decoder.read_slice(section_decoder.remaining() as u32)?;
+
+        self.finish_section(section_decoder, SectionType::Custom, size)?;
+
+        Ok(WasmSection::Custom(CustomSection {
+            name,
+            data: Cow::Borrowed(data),
+        }))
+    }
 
     /// Decode the import section of a WebAssembly binary.
     ///
@@ -166,123 +788,438 @@ impl<'a> WasmDecoder<'a> for Decoder<'a> {
     ///     - (7) import kind (byte)
     ///     - (8) import descriptor (based on kind)
     ///
-    fn decode_import_section(&mut self, size: u32) -> Result<WasmSection> {
-        let section_bytes = read_bytes!(self.reader, size);
-        let mut section_decoder = Decoder::new(Cursor::new(&section_bytes));
-
-        let count: u32 = section_decoder.decode_varuint()?.into();
-        let mut items = Vec::new();
-
-        for _ in 0..count {
-            let module_name_length: u32 = section_decoder.decode_varuint()?.into();
-            let module_name =
-                String::from_utf8(read_bytes!(section_decoder.reader, module_name_length))?;
-
-            let field_name_length: u32 = section_decoder.decode_varuint()?.into();
-            let field_name =
-                String::from_utf8(read_bytes!(section_decoder.reader, field_name_length))?;
-
-            let import_kind = read_bytes_const!(section_decoder.reader, 1)[0];
-
-            let import_descriptor = match import_kind {
-                0x00 => WasmImportDescriptor::Function(section_decoder.decode_varuint()?),
-                0x01 => WasmImportDescriptor::Table(section_decoder.decode_table_type()?),
-                0x02 => WasmImportDescriptor::Memory(section_decoder.decode_memory_type()?),
-                0x03 => WasmImportDescriptor::Global(section_decoder.decode_global_type()?),
-                _ => return Err(anyhow::anyhow!("Invalid import kind: {}", import_kind)),
-            };
+    fn decode_import_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
 
-            items.push(WasmImportEntry {
-                module_name,
-                field_name,
-                descriptor: import_descriptor,
-            });
-        }
+        let items =
+            section_decoder.decode_section_items(SectionType::Import, |decoder, index| {
+                let module_name = decoder.read_name("import", "import module name", index)?;
+                let field_name = decoder.read_name("import", "import field name", index)?;
+
+                let kind_offset = decoder.offset();
+                let import_kind = decoder.read_byte().eof_context(|| "import kind")?;
+
+                let import_descriptor = match ExternalKind::try_from(import_kind) {
+                    Ok(ExternalKind::Function) => {
+                        WasmImportDescriptor::Function(decoder.decode_varuint()?.into())
+                    }
+                    Ok(ExternalKind::Table) => {
+                        WasmImportDescriptor::Table(decoder.decode_table_type()?)
+                    }
+                    Ok(ExternalKind::Memory) => {
+                        WasmImportDescriptor::Memory(decoder.decode_memory_type()?)
+                    }
+                    Ok(ExternalKind::Global) => {
+                        WasmImportDescriptor::Global(decoder.decode_global_type()?)
+                    }
+                    Err(kind) => {
+                        return Err(DecodeError::ImportKind {
+                            kind,
+                            offset: kind_offset,
+                        })
+                    }
+                };
+
+                Ok(WasmImportEntry {
+                    module_name,
+                    field_name,
+                    descriptor: import_descriptor,
+                })
+            })?;
+
+        self.finish_section(section_decoder, SectionType::Import, size)?;
 
         Ok(WasmSection::Import(ImportSection { items }))
     }
 
-    fn decode_table_type(&mut self) -> Result<TableType> {
-        // The element type is represented by a byte. According to the WebAssembly specification,
-        // 0x70 corresponds to `funcref` in the MVP.
-        let element_type_byte = read_bytes_const!(self.reader, 1)[0];
-        let element_type = match element_type_byte {
-            0x70 => WasmElementType::Funcref,
-            // In future versions of WebAssembly, there might be additional element types.
-            _ => {
-                return Err(DecodeError::ElementType {
-                    invalid_byte: element_type_byte,
-                }
-                .into())
-            }
-        };
+    /// Decode the function section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) function count (varuint)
+    /// (2) type index of each function (varuint)
+    ///
+    fn decode_function_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
 
-        // The limits are represented by a byte flag that indicates whether a maximum is present,
-        // followed by the minimum (and optionally the maximum) represented as varuints.
-        let flags = read_bytes_const!(self.reader, 1)[0];
-        let min = self.decode_varuint()?.into();
-        let max = if flags & 0x01 != 0 {
-            Some(self.decode_varuint()?.into())
-        } else {
-            None
-        };
+        let items = section_decoder.decode_section_items(SectionType::Function, |decoder, _| {
+            Ok(decoder.decode_varuint()?.into())
+        })?;
 
-        Ok(TableType {
-            element_type,
-            limits: WasmLimits { min, max },
-        })
+        self.finish_section(section_decoder, SectionType::Function, size)?;
+
+        Ok(WasmSection::Function(FunctionSection { items }))
     }
 
-    fn decode_memory_type(&mut self) -> Result<MemoryType> {
-        // The limits are represented by a byte flag that indicates whether a maximum is present,
-        // followed by the minimum (and optionally the maximum) represented as varuints.
-        let flags = read_bytes_const!(self.reader, 1)[0];
-        let min = self.decode_varuint()?.into();
-        let max = if flags & 0x01 != 0 {
-            Some(self.decode_varuint()?.into())
-        } else {
-            None
-        };
+    /// Decode the export section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) export count (varuint)
+    /// (2) exports
+    ///     - (3) name length (varuint)
+    ///     - (4) name (string)
+    ///     - (5) export kind (byte)
+    ///     - (6) index into the index space of that kind (varuint)
+    ///
+    fn decode_export_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
 
-        Ok(MemoryType {
-            limits: WasmLimits { min, max },
-        })
-    }
+        let items =
+            section_decoder.decode_section_items(SectionType::Export, |decoder, index| {
+                let name = decoder.read_name("export", "export name", index)?;
 
-    fn decode_global_type(&mut self) -> Result<GlobalType> {
-        // The value type is represented by a single byte.
-        let value_type_byte = read_bytes_const!(self.reader, 1)[0];
-        let value_type = WasmValueType::from(VarUInt::from(value_type_byte as u32));
+                let kind_offset = decoder.offset();
+                let export_kind = decoder.read_byte().eof_context(|| "export kind")?;
+                let index = decoder.decode_varuint()?;
 
-        // The mutability is represented by a single byte.
-        let mutability_byte = read_bytes_const!(self.reader, 1)[0];
-        let mutability = match mutability_byte {
-            0x00 => Mutability::Immutable,
-            0x01 => Mutability::Mutable,
-            _ => {
-                return Err(DecodeError::MutabilityByte {
-                    invalid_byte: mutability_byte,
-                }
-                .into())
-            }
-        };
+                let descriptor = match ExternalKind::try_from(export_kind) {
+                    Ok(ExternalKind::Function) => WasmExportDescriptor::Function(index.into()),
+                    Ok(ExternalKind::Table) => WasmExportDescriptor::Table(index.into()),
+                    Ok(ExternalKind::Memory) => WasmExportDescriptor::Memory(index.into()),
+                    Ok(ExternalKind::Global) => WasmExportDescriptor::Global(index.into()),
+                    Err(kind) => {
+                        return Err(DecodeError::ExportKind {
+                            kind,
+                            offset: kind_offset,
+                        })
+                    }
+                };
 
-        Ok(GlobalType {
-            value_type,
-            mutability,
-        })
-    }
+                Ok(WasmExportEntry { name, descriptor })
+            })?;
 
-    decode_dummy_section!(decode_table_section, Table, "Table");
-    decode_dummy_section!(decode_memory_section, Memory, "Memory");
-    decode_dummy_section!(decode_global_section, Global, "Global");
-    decode_dummy_section!(decode_start_section, Start, "Start");
-    decode_dummy_section!(decode_element_section, Element, "Element");
-}
+        self.finish_section(section_decoder, SectionType::Export, size)?;
+
+        Ok(WasmSection::Export(ExportSection { items }))
+    }
+
+    /// Decode the code section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) body count (varuint)
+    /// (2) bodies
+    ///     - (3) body size (varuint)
+    ///     - (4) local declaration count (varuint)
+    ///         - local count (varuint)
+    ///         - local type (varuint)
+    ///     - (5) expression (bytes, up to the end of the body)
+    ///
+    fn decode_code_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        let items = section_decoder.decode_section_items(SectionType::Code, |decoder, index| {
+            decoder
+                .decode_function_body(index)
+                .eof_context(|| format!("code body {index}"))
+        })?;
+
+        self.finish_section(section_decoder, SectionType::Code, size)?;
+
+        Ok(WasmSection::Code(CodeSection { items }))
+    }
+
+    /// Decode the table section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) table count (varuint)
+    /// (2) table types (element type byte, then limits)
+    ///
+    fn decode_table_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        let items = section_decoder
+            .decode_section_items(SectionType::Table, |decoder, _| decoder.decode_table_type())?;
+
+        self.finish_section(section_decoder, SectionType::Table, size)?;
+
+        Ok(WasmSection::Table(TableSection { items }))
+    }
+
+    /// Decode the memory section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) memory count (varuint)
+    /// (2) memory types (limits)
+    ///
+    fn decode_memory_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        let items = section_decoder.decode_section_items(SectionType::Memory, |decoder, _| {
+            decoder.decode_memory_type()
+        })?;
+
+        self.finish_section(section_decoder, SectionType::Memory, size)?;
+
+        Ok(WasmSection::Memory(MemorySection { items }))
+    }
+
+    /// Decode the global section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) global count (varuint)
+    /// (2) globals
+    ///     - (3) global type (value type, mutability byte)
+    ///     - (4) initializer (constant expression, up to and including `end`)
+    ///
+    fn decode_global_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        let items = section_decoder.decode_section_items(SectionType::Global, |decoder, _| {
+            Ok(Global {
+                global_type: decoder.decode_global_type()?,
+                init: decoder.decode_const_expr()?,
+            })
+        })?;
+
+        self.finish_section(section_decoder, SectionType::Global, size)?;
+
+        Ok(WasmSection::Global(GlobalSection { items }))
+    }
+
+    /// Decode the element section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) segment count (varuint)
+    /// (2) segments
+    ///     - (3) flags (varuint): bit 0 set for passive or declarative, bit 1 set for a table
+    ///       index if active, or declarative if not, bit 2 set for constant expressions rather
+    ///       than function indices
+    ///     - (4) table index (varuint, flags 2 and 6 only)
+    ///     - (5) offset (constant expression, flags 0, 2, 4 and 6 only)
+    ///     - (6) element kind (byte, flags 1 to 3 only), always 0x00 for funcref, or element
+    ///       type (byte, flags 5 to 7 only)
+    ///     - (7) function indices (vector of varuints), or constant expressions if bit 2 is set
+    ///
+    fn decode_element_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        let items = section_decoder.decode_section_items(SectionType::Element, |decoder, _| {
+            let flags_offset = decoder.offset();
+            let flags: u32 = decoder.decode_varuint()?.into();
+            if flags > 7 {
+                return Err(DecodeError::ElementSegmentFlags {
+                    flags,
+                    offset: flags_offset,
+                });
+            }
+
+            let expressions = flags & 0b100 != 0;
+            let mode = match flags & 0b11 {
+                0 => ElementMode::Active {
+                    table_index: 0.into(),
+                    offset: decoder.decode_const_expr()?,
+                },
+                1 => ElementMode::Passive,
+                2 => ElementMode::Active {
+                    table_index: decoder.decode_varuint()?.into(),
+                    offset: decoder.decode_const_expr()?,
+                },
+                _ => ElementMode::Declarative,
+            };
+
+            // Flags 0 and 4 leave out the type, which is then funcref.
+            let element_type = match (flags & 0b11, expressions) {
+                (0, _) => WasmElementType::Funcref,
+                (_, true) => decoder.decode_element_type()?,
+                (_, false) => {
+                    let offset = decoder.offset();
+                    match decoder.read_byte()? {
+                        0x00 => WasmElementType::Funcref,
+                        kind => return Err(DecodeError::ElementKind { kind, offset }),
+                    }
+                }
+            };
+
+            let items = if expressions {
+                ElementItems::Expressions(
+                    decoder.decode_vec(|decoder| decoder.decode_const_expr())?,
+                )
+            } else {
+                ElementItems::Functions(
+                    decoder.decode_vec(|decoder| Ok(decoder.decode_varuint()?.into()))?,
+                )
+            };
+
+            Ok(ElementSegment {
+                mode,
+                element_type,
+                items,
+            })
+        })?;
+
+        self.finish_section(section_decoder, SectionType::Element, size)?;
+
+        Ok(WasmSection::Element(ElementSection { items }))
+    }
+
+    /// Decode the data section of a WebAssembly binary.
+    ///
+    /// Layout:
+    /// (1) segment count (varuint)
+    /// (2) segments
+    ///     - (3) flags (varuint): 0 active in memory 0, 1 passive, 2 active with a memory index
+    ///     - (4) memory index (varuint, flags 2 only)
+    ///     - (5) offset (constant expression, flags 0 and 2 only)
+    ///     - (6) data length (varuint), then the data
+    ///
+    fn decode_data_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        let items = section_decoder.decode_section_items(SectionType::Data, |decoder, _| {
+            let flags_offset = decoder.offset();
+            let mode = match decoder.decode_varuint()?.into() {
+                0 => DataMode::Active {
+                    memory_index: 0.into(),
+                    offset: decoder.decode_const_expr()?,
+                },
+                1 => DataMode::Passive,
+                2 => DataMode::Active {
+                    memory_index: decoder.decode_varuint()?.into(),
+                    offset: decoder.decode_const_expr()?,
+                },
+                flags => {
+                    return Err(DecodeError::DataSegmentFlags {
+                        flags,
+                        offset: flags_offset,
+                    })
+                }
+            };
+
+            let length: u32 = decoder.decode_varuint()?.into();
+            decoder.check_length("data segment", length)?;
+
+            Ok(DataSegment {
+                mode,
+                data: Cow::Borrowed(decoder.read_slice(length)?),
+            })
+        })?;
+
+        self.finish_section(section_decoder, SectionType::Data, size)?;
+
+        Ok(WasmSection::Data(DataSection { items }))
+    }
+
+    /// Decode the tag section of a WebAssembly binary (exception-handling proposal).
+    ///
+    /// Layout:
+    /// (1) tag count (varuint)
+    /// (2) tags
+    ///     - (3) attribute (byte)
+    ///     - (4) type index (varuint)
+    ///
+    fn decode_tag_section(&mut self, size: u32) -> Result<WasmSection<'a>> {
+        let mut section_decoder = self.section_decoder(size);
+
+        let items = section_decoder.decode_section_items(SectionType::Tag, |decoder, _| {
+            Ok(Tag {
+                attribute: decoder.read_byte()?,
+                type_index: decoder.decode_varuint()?.into(),
+            })
+        })?;
+
+        self.finish_section(section_decoder, SectionType::Tag, size)?;
+
+        Ok(WasmSection::Tag(TagSection { items }))
+    }
+
+    fn decode_table_type(&mut self) -> Result<TableType> {
+        let element_type = self.decode_element_type()?;
+
+        // The limits start with a byte of flags: 0x01 if a maximum follows the minimum, 0x02
+        // for a shared table, and 0x04 for a 64-bit table, whose limits are 64-bit varuints.
+        let offset = self.offset();
+        let flags = self.read_byte()?;
+        let mut allowed = 0x01;
+        if self.config.allow_shared_tables {
+            allowed |= 0x02;
+        }
+        if self.config.allow_table64 {
+            allowed |= 0x04;
+        }
+        if flags & !allowed != 0 {
+            return Err(DecodeError::LimitsFlags { flags, offset });
+        }
+
+        Ok(TableType {
+            element_type,
+            limits: self.decode_limits(flags)?,
+            shared: flags & 0x02 != 0,
+            table64: flags & 0x04 != 0,
+        })
+    }
+
+    fn decode_memory_type(&mut self) -> Result<MemoryType> {
+        // The same flags as for tables: 0x01 if a maximum follows the minimum, 0x02 for a
+        // shared memory, and 0x04 for a 64-bit memory.
+        let offset = self.offset();
+        let flags = self.read_byte()?;
+        let mut allowed = 0x01;
+        if self.config.allow_shared_memories {
+            allowed |= 0x02;
+        }
+        if self.config.allow_memory64 {
+            allowed |= 0x04;
+        }
+        if flags & !allowed != 0 {
+            return Err(DecodeError::MemoryLimitsFlags { flags, offset });
+        }
+
+        Ok(MemoryType {
+            limits: self.decode_limits(flags)?,
+            shared: flags & 0x02 != 0,
+            memory64: flags & 0x04 != 0,
+        })
+    }
+
+    /// Decode the minimum and, if flag 0x01 is set, the maximum of limits whose `flags` were
+    /// already read; they are 64-bit varuints if flag 0x04 is set.
+    fn decode_limits(&mut self, flags: u8) -> Result<WasmLimits> {
+        let mut decode_bound = || match flags & 0x04 != 0 {
+            true => self.decode_varuint64(),
+            false => Ok(u32::from(self.decode_varuint()?).into()),
+        };
+        let min = decode_bound()?;
+        let max = if flags & 0x01 != 0 {
+            Some(decode_bound()?)
+        } else {
+            None
+        };
+
+        Ok(WasmLimits { min, max })
+    }
+
+    fn decode_global_type(&mut self) -> Result<GlobalType> {
+        // The value type is represented by a single byte.
+        let value_type = self.decode_value_type()?;
+
+        // The mutability is represented by a single byte.
+        let offset = self.offset();
+        let mutability_byte = self.read_byte()?;
+        let mutability = match mutability_byte {
+            0x00 => Mutability::Immutable,
+            0x01 => Mutability::Mutable,
+            _ => {
+                return Err(DecodeError::MutabilityByte {
+                    invalid_byte: mutability_byte,
+                    offset,
+                })
+            }
+        };
+
+        Ok(GlobalType {
+            value_type,
+            mutability,
+        })
+    }
+
+    decode_dummy_section!(decode_start_section, Start, "Start");
+    decode_dummy_section!(decode_data_count_section, DataCount, "Data count");
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::Xorshift;
+    use crate::types::{ConstExpr, ExportKind, ModuleKind};
 
     #[test]
     fn test_new_decoder() {
@@ -291,6 +1228,18 @@ mod tests {
         assert_eq!(decoder.reader.get_ref().len(), 4);
     }
 
+    #[test]
+    fn test_decode_section_type() {
+        let mut decoder = Decoder::new(Cursor::new(&[0x01, 0x85, 0x01][..]));
+        assert_eq!(
+            decoder.decode_section_type().unwrap(),
+            SectionHeader {
+                id: SectionType::Type,
+                size: 133
+            }
+        );
+    }
+
     #[test]
     fn test_decode_varuint() {
         let cursor = Cursor::new(&[0b10000001, 0b00000001][..]);
@@ -299,6 +1248,59 @@ mod tests {
         assert_eq!(u32::from(result), 129); // 128 (second byte) + 1 (first byte)
     }
 
+    #[test]
+    fn test_decode_varuint_overflow() {
+        let mut decoder = Decoder::new(Cursor::new(&[0xff, 0xff, 0xff, 0xff, 0x0f][..]));
+        assert_eq!(u32::from(decoder.decode_varuint().unwrap()), u32::MAX);
+
+        // 2^32 does not fit, and neither does a sixth byte.
+        for bytes in [
+            [0x80, 0x80, 0x80, 0x80, 0x10],
+            [0xff, 0xff, 0xff, 0xff, 0x8f],
+        ] {
+            let mut decoder = Decoder::new(Cursor::new(&bytes[..]));
+            assert!(matches!(
+                decoder.decode_varuint().err().unwrap(),
+                DecodeError::Numeric {
+                    offset: 0,
+                    invalid_byte,
+                    ..
+                } if invalid_byte == bytes[4] as u32
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decode_truncated_leb128() {
+        for bytes in [&[0x80][..], &[0xff, 0xff]] {
+            let mut decoder = Decoder::new(Cursor::new(bytes));
+            match decoder.decode_varuint().err().unwrap() {
+                DecodeError::UnexpectedEof {
+                    offset,
+                    needed: 1,
+                    context,
+                } => {
+                    assert_eq!(offset, bytes.len() as u64);
+                    assert_eq!(context, "varuint");
+                }
+                error => panic!("Expected UnexpectedEof, got {error}"),
+            }
+
+            let mut decoder = Decoder::new(Cursor::new(bytes));
+            assert!(matches!(
+                decoder.decode_varint32().err().unwrap(),
+                DecodeError::UnexpectedEof { context, .. } if context == "varint"
+            ));
+        }
+
+        // Without a single byte of the integer, the context is left to the caller.
+        let mut decoder = Decoder::new(Cursor::new(&[][..]));
+        assert!(matches!(
+            decoder.decode_varuint().err().unwrap(),
+            DecodeError::UnexpectedEof { context, .. } if context.is_empty()
+        ));
+    }
+
     #[test]
     fn test_read_validate() {
         let cursor = Cursor::new(&HEADER_MAGIC_BYTES[..]);
@@ -318,7 +1320,23 @@ mod tests {
         let cursor = Cursor::new(&[1, 0, 0, 0][..]);
         let mut decoder = Decoder::new(cursor);
         let version = decoder.read_version().unwrap();
-        assert_eq!(version, 1);
+        assert_eq!(
+            version,
+            WasmVersion {
+                version: 1,
+                layer: 0
+            }
+        );
+        assert_eq!(version.kind(), ModuleKind::Core);
+    }
+
+    #[test]
+    fn test_read_version_component() {
+        let cursor = Cursor::new(&[0x0d, 0x00, 0x01, 0x00][..]);
+        let mut decoder = Decoder::new(cursor);
+        let version = decoder.read_version().unwrap();
+        assert_eq!(version.version(), 0x0d);
+        assert_eq!(version.kind(), ModuleKind::Component);
     }
 
     #[test]
@@ -341,20 +1359,784 @@ mod tests {
         let import_section = decoder.decode_import_section(data.len() as u32).unwrap();
 
         // Check the result.
-        match import_section {
-            WasmSection::Import(import_section) => {
-                assert_eq!(import_section.items.len(), 1);
-                let import_entry = &import_section.items[0];
-                assert_eq!(import_entry.module_name, "mod");
-                assert_eq!(import_entry.field_name, "field");
-                match import_entry.descriptor {
-                    WasmImportDescriptor::Function(type_index) => {
-                        assert_eq!(u32::from(type_index), 0);
-                    }
-                    _ => panic!("Expected function import descriptor"),
-                }
+        assert_eq!(
+            import_section,
+            WasmSection::Import(ImportSection {
+                items: vec![WasmImportEntry {
+                    module_name: "mod".into(),
+                    field_name: "field".into(),
+                    descriptor: WasmImportDescriptor::Function(0.into()),
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_global_type_rejects_unknown_value_types() {
+        for byte in 0..=u8::MAX {
+            let data = [byte, 0x00];
+            let mut decoder = Decoder::new(Cursor::new(&data[..]));
+            let result = decoder.decode_global_type();
+
+            assert_eq!(result.is_ok(), WasmValueType::try_from(byte).is_ok());
+        }
+
+        let mut decoder = Decoder::new(Cursor::new(&[0x40, 0x00][..]));
+        let error = decoder.decode_global_type().err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::ValueType {
+                value: 0x40,
+                offset: 0
             }
-            _ => panic!("Expected import section"),
+        ));
+    }
+
+    #[test]
+    fn test_decode_type_section_random_value_types_do_not_panic() {
+        let mut rng = Xorshift::new(0x2545_f491);
+
+        for _ in 0..1000 {
+            let data = [
+                0x01, // Type count (1)
+                0x60, // Function type magic
+                0x02, // Param count (2)
+                rng.next_byte(),
+                rng.next_byte(),
+                0x01, // Return count (1)
+                rng.next_byte(),
+            ];
+
+            let mut decoder = Decoder::new(Cursor::new(&data[..]));
+            let _ = decoder.decode_type_section(data.len() as u32);
         }
     }
+
+    #[test]
+    fn test_decode_type_section_count_exceeds_size() {
+        let data = [
+            0x02, // Type count (2)
+            0x60, 0x00, 0x00, // () -> ()
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_type_section(data.len() as u32)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::MalformedCount {
+                declared: 2,
+                remaining: 3,
+                offset: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_type_section_trailing_byte() {
+        let data = [
+            0x01, // Type count (1)
+            0x60, 0x00, 0x00, // () -> ()
+            0xff, // Trailing junk
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_type_section(data.len() as u32)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::SectionSize {
+                section_id: 1,
+                declared: 5,
+                consumed: 4,
+                offset: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_import_section_declared_size_too_small() {
+        let data = [
+            0x01, // Import count (1)
+            0x03, 0x6d, 0x6f, 0x64, // Module name length (3), module name ("mod")
+            0x05, 0x66, 0x69, 0x65, 0x6c, 0x64, // Field name length (5), field name ("field")
+            0x00, // Import kind (0 = function)
+            0x00, // Function type index (0)
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_import_section(data.len() as u32 - 1)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::SectionSize {
+                section_id: 2,
+                declared: 12,
+                consumed: 13,
+                offset: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_tag_section() {
+        let data = [
+            0x01, // Tag count (1)
+            0x00, // Attribute (exception)
+            0x00, // Type index (0)
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        match decoder.decode_tag_section(data.len() as u32).unwrap() {
+            WasmSection::Tag(tag_section) => {
+                assert_eq!(tag_section.items.len(), 1);
+                assert_eq!(tag_section.items[0].attribute, 0);
+                assert_eq!(u32::from(tag_section.items[0].type_index), 0);
+            }
+            _ => panic!("Expected tag section"),
+        }
+    }
+
+    #[test]
+    fn test_decode_global_section() {
+        let data = [
+            0x02, // Global count (2)
+            0x70, 0x00, // Global type (funcref, immutable)
+            0xd2, 0x00, 0x0b, // ref.func 0, end
+            0x7f, 0x01, // Global type (mut i32)
+            0x41, 0x07, 0x0b, // i32.const 7, end
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let section = decoder.decode_global_section(data.len() as u32).unwrap();
+
+        assert_eq!(
+            section,
+            WasmSection::Global(GlobalSection {
+                items: vec![
+                    Global {
+                        global_type: GlobalType {
+                            value_type: WasmValueType::Funcref,
+                            mutability: Mutability::Immutable,
+                        },
+                        init: ConstExpr::RefFunc(0.into()),
+                    },
+                    Global {
+                        global_type: GlobalType {
+                            value_type: WasmValueType::I32,
+                            mutability: Mutability::Mutable,
+                        },
+                        init: ConstExpr::I32Const(7),
+                    },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_global_section_typed_reference() {
+        let data = [
+            0x01, // Global count (1)
+            0x63, 0x00, 0x00, // Global type ((ref null $0), immutable)
+            0xd2, 0x00, 0x0b, // ref.func 0, end
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_global_section(data.len() as u32)
+            .unwrap_err();
+        assert_eq!(error.required_feature(), Some("function-references"));
+
+        let config = DecoderConfig::new().allow_gc(true);
+        let mut decoder = Decoder::with_config(Cursor::new(&data[..]), config);
+        let section = decoder.decode_global_section(data.len() as u32).unwrap();
+
+        let WasmSection::Global(section) = section else {
+            panic!("Expected a global section, got {section:?}");
+        };
+        let value_type = section.items[0].global_type.value_type;
+        assert_eq!(
+            value_type,
+            WasmValueType::Ref(RefType::new(true, HeapType::Concrete(0)))
+        );
+        assert_eq!(value_type.to_string(), "(ref null 0)");
+    }
+
+    #[test]
+    fn test_decode_ref_type() {
+        let config = DecoderConfig::new().allow_gc(true);
+        for (bytes, expected) in [
+            (&[0x64, 0x70][..], RefType::new(false, HeapType::Func)),
+            (&[0x63, 0x73], RefType::new(true, HeapType::NoFunc)),
+            (
+                &[0x64, 0x80, 0x01],
+                RefType::new(false, HeapType::Concrete(128)),
+            ),
+        ] {
+            let mut decoder = Decoder::with_config(Cursor::new(bytes), config);
+            assert_eq!(decoder.decode_ref_type().unwrap(), expected);
+        }
+
+        // 0x40 is not an abstract heap type.
+        let mut decoder = Decoder::with_config(Cursor::new(&[0x63, 0x40][..]), config);
+        assert!(matches!(
+            decoder.decode_ref_type().unwrap_err(),
+            DecodeError::HeapType {
+                value: -0x40,
+                offset: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_table_section_flags() {
+        let data = [
+            0x02, // Table count (2)
+            0x70, 0x00, 0x02, // funcref, min 2
+            0x6f, 0x05, // externref, 64-bit with a maximum
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x20, // min 2^40
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x40, // max 2^41
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder.decode_table_section(data.len() as u32).unwrap_err();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::LimitsFlags {
+                flags: 0x05,
+                offset: 5
+            }
+        ));
+        assert_eq!(error.required_feature(), Some("memory64"));
+
+        let config = DecoderConfig::new().allow_table64(true);
+        let mut decoder = Decoder::with_config(Cursor::new(&data[..]), config);
+        let section = decoder.decode_table_section(data.len() as u32).unwrap();
+        let WasmSection::Table(TableSection { items }) = section else {
+            panic!("Expected a table section, got {section:?}");
+        };
+        assert!(!items[0].is_table64());
+        assert_eq!(items[0].limits(), &WasmLimits::new(2, None));
+        assert!(items[1].is_table64());
+        assert!(!items[1].is_shared());
+        assert_eq!(items[1].limits(), &WasmLimits::new(1 << 40, Some(1 << 41)));
+
+        // A shared funcref table with min 1.
+        let data = [0x01, 0x70, 0x02, 0x01];
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        assert!(decoder.decode_table_section(data.len() as u32).is_err());
+
+        let config = DecoderConfig::new().allow_shared_tables(true);
+        let mut decoder = Decoder::with_config(Cursor::new(&data[..]), config);
+        let section = decoder.decode_table_section(data.len() as u32).unwrap();
+        let WasmSection::Table(TableSection { items }) = section else {
+            panic!("Expected a table section, got {section:?}");
+        };
+        assert!(items[0].is_shared());
+        assert!(!items[0].is_table64());
+    }
+
+    #[test]
+    fn test_decode_memory_section_flags() {
+        let data = [
+            0x02, // Memory count (2)
+            0x01, 0x01, 0x02, // min 1, max 2
+            0x05, // 64-bit with a maximum
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x20, // min 2^40
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x40, // max 2^41
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_memory_section(data.len() as u32)
+            .unwrap_err();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::MemoryLimitsFlags {
+                flags: 0x05,
+                offset: 4
+            }
+        ));
+        assert_eq!(error.required_feature(), Some("memory64"));
+
+        let config = DecoderConfig::new().allow_memory64(true);
+        let mut decoder = Decoder::with_config(Cursor::new(&data[..]), config);
+        let section = decoder.decode_memory_section(data.len() as u32).unwrap();
+        let WasmSection::Memory(MemorySection { items }) = section else {
+            panic!("Expected a memory section, got {section:?}");
+        };
+        assert!(!items[0].is_memory64());
+        assert_eq!(items[0].limits(), &WasmLimits::new(1, Some(2)));
+        assert!(items[1].is_memory64());
+        assert!(!items[1].is_shared());
+        assert_eq!(items[1].limits(), &WasmLimits::new(1 << 40, Some(1 << 41)));
+
+        // A shared memory with min 1 and max 1, which the threads proposal requires.
+        let data = [0x01, 0x03, 0x01, 0x01];
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_memory_section(data.len() as u32)
+            .unwrap_err();
+        assert_eq!(error.required_feature(), Some("threads"));
+
+        let config = DecoderConfig::new().allow_shared_memories(true);
+        let mut decoder = Decoder::with_config(Cursor::new(&data[..]), config);
+        let section = decoder.decode_memory_section(data.len() as u32).unwrap();
+        let WasmSection::Memory(MemorySection { items }) = section else {
+            panic!("Expected a memory section, got {section:?}");
+        };
+        assert!(items[0].is_shared());
+        assert!(!items[0].is_memory64());
+
+        // Unknown flags are rejected whatever the config.
+        let data = [0x01, 0x08, 0x01];
+        let config = DecoderConfig::new()
+            .allow_shared_memories(true)
+            .allow_memory64(true);
+        let mut decoder = Decoder::with_config(Cursor::new(&data[..]), config);
+        let error = decoder
+            .decode_memory_section(data.len() as u32)
+            .unwrap_err();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::MemoryLimitsFlags { flags: 0x08, .. }
+        ));
+    }
+
+    #[test]
+    fn test_decode_element_section() {
+        let data = [
+            0x04, // Segment count (4)
+            0x00, 0x41, 0x01, 0x0b, 0x02, 0x00, 0x01, // Active at 1: functions 0, 1
+            0x01, 0x00, 0x01, 0x02, // Passive funcref: function 2
+            0x02, 0x01, 0x41, 0x00, 0x0b, 0x00, 0x00, // Active in table 1 at 0: no functions
+            0x03, 0x00, 0x01, 0x03, // Declarative funcref: function 3
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let section = decoder.decode_element_section(data.len() as u32).unwrap();
+
+        let segment = |mode, indices: &[u32]| ElementSegment {
+            mode,
+            element_type: WasmElementType::Funcref,
+            items: ElementItems::Functions(indices.iter().map(|&index| index.into()).collect()),
+        };
+        assert_eq!(
+            section,
+            WasmSection::Element(ElementSection {
+                items: vec![
+                    segment(
+                        ElementMode::Active {
+                            table_index: 0.into(),
+                            offset: ConstExpr::I32Const(1)
+                        },
+                        &[0, 1]
+                    ),
+                    segment(ElementMode::Passive, &[2]),
+                    segment(
+                        ElementMode::Active {
+                            table_index: 1.into(),
+                            offset: ConstExpr::I32Const(0)
+                        },
+                        &[]
+                    ),
+                    segment(ElementMode::Declarative, &[3]),
+                ]
+            })
+        );
+
+        let mut decoder = Decoder::new(Cursor::new(&[0x01, 0x01, 0x70, 0x00][..]));
+        let error = decoder.decode_element_section(4).unwrap_err();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::ElementKind {
+                kind: 0x70,
+                offset: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_element_section_expressions() {
+        let data = [
+            0x02, // Segment count (2)
+            0x04, 0x41, 0x02, 0x0b, // Active at 2, funcref
+            0x02, 0xd2, 0x05, 0x0b, 0xd0, 0x70, 0x0b, // ref.func 5, ref.null func
+            0x06, 0x01, 0x23, 0x00, 0x0b, 0x6f, // Active in table 1 at global 0, externref
+            0x01, 0xd0, 0x6f, 0x0b, // ref.null extern
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let section = decoder.decode_element_section(data.len() as u32).unwrap();
+
+        assert_eq!(
+            section,
+            WasmSection::Element(ElementSection {
+                items: vec![
+                    ElementSegment {
+                        mode: ElementMode::Active {
+                            table_index: 0.into(),
+                            offset: ConstExpr::I32Const(2)
+                        },
+                        element_type: WasmElementType::Funcref,
+                        items: ElementItems::Expressions(vec![
+                            ConstExpr::RefFunc(5.into()),
+                            ConstExpr::RefNull(WasmElementType::Funcref)
+                        ]),
+                    },
+                    ElementSegment {
+                        mode: ElementMode::Active {
+                            table_index: 1.into(),
+                            offset: ConstExpr::GlobalGet(0.into())
+                        },
+                        element_type: WasmElementType::Externref,
+                        items: ElementItems::Expressions(vec![ConstExpr::RefNull(
+                            WasmElementType::Externref
+                        )]),
+                    },
+                ]
+            })
+        );
+
+        let mut decoder = Decoder::new(Cursor::new(&[0x01, 0x08][..]));
+        let error = decoder.decode_element_section(2).unwrap_err();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::ElementSegmentFlags {
+                flags: 8,
+                offset: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_data_section() {
+        let data = [
+            0x03, // Segment count (3)
+            0x00, 0x41, 0x08, 0x0b, 0x02, 0x68, 0x69, // Active at i32.const 8: "hi"
+            0x01, 0x01, 0x21, // Passive: "!"
+            0x02, 0x01, 0x23, 0x00, 0x0b, 0x00, // Active in memory 1 at global.get 0: ""
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let section = decoder.decode_data_section(data.len() as u32).unwrap();
+
+        assert_eq!(
+            section,
+            WasmSection::Data(DataSection {
+                items: vec![
+                    DataSegment {
+                        mode: DataMode::Active {
+                            memory_index: 0.into(),
+                            offset: ConstExpr::I32Const(8),
+                        },
+                        data: Cow::Borrowed(b"hi"),
+                    },
+                    DataSegment {
+                        mode: DataMode::Passive,
+                        data: Cow::Borrowed(b"!"),
+                    },
+                    DataSegment {
+                        mode: DataMode::Active {
+                            memory_index: 1.into(),
+                            offset: ConstExpr::GlobalGet(0.into()),
+                        },
+                        data: Cow::Borrowed(b""),
+                    },
+                ]
+            })
+        );
+
+        let data = [0x01, 0x03, 0x00];
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_data_section(data.len() as u32)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::DataSegmentFlags {
+                flags: 3,
+                offset: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_code_section_local_runs() {
+        let data = [
+            0x01, // Body count (1)
+            0x05, // Body size (5)
+            0x01, // Local declaration count (1)
+            0x05, // Local count (5)
+            0x7c, // Local type (f64)
+            0x01, // nop
+            0x0b, // end
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        match decoder.decode_code_section(data.len() as u32).unwrap() {
+            WasmSection::Code(code_section) => {
+                let body = &code_section.items[0];
+                assert_eq!(
+                    body.locals,
+                    vec![WasmLocals {
+                        count: 5,
+                        value_type: WasmValueType::F64
+                    }]
+                );
+                assert_eq!(body.total_locals(), 5);
+                assert_eq!(body.expression, vec![0x01, 0x0b]);
+            }
+            _ => panic!("Expected code section"),
+        }
+    }
+
+    #[test]
+    fn test_decode_type_section_rejects_wide_value_type() {
+        let data = [
+            0x01, // Type count (1)
+            0x60, // Function type
+            0x01, // Param count (1)
+            0xff, 0x02, // Param type 0x17f, whose low byte is i32
+            0x00, // Return count (0)
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_type_section(data.len() as u32)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::ValueType {
+                value: 0x17f,
+                offset: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_import_section_truncated_field_name() {
+        let data = [
+            0x01, // Import count (1)
+            0x03, 0x65, 0x6e, 0x76, // Module name "env"
+            0x05, 0x61, // Field name of 5 bytes, only 1 present
+        ];
+
+        // The section is declared to be longer than the input.
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder.decode_import_section(20).err().unwrap();
+        match error.root_cause() {
+            DecodeError::UnexpectedEof {
+                offset: 7,
+                needed: 4,
+                context,
+            } => assert_eq!(context, "import field name"),
+            _ => panic!("Expected UnexpectedEof, got {error}"),
+        }
+    }
+
+    #[test]
+    fn test_decode_code_section_missing_end() {
+        let data = [
+            0x02, // Body count (2)
+            0x02, 0x00, 0x0b, // Body 0: no locals, end
+            0x03, 0x00, 0x41, 0x2a, // Body 1: no locals, i32.const 42 and no end
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_code_section(data.len() as u32)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::MissingEnd {
+                func_index: 1,
+                offset: 8
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_import_section_name_length_exceeds_section() {
+        let mut data = vec![
+            0x01, // Import count (1)
+            0xe8, 0x07, // Module name length (1000)
+        ];
+        data.resize(20, 0x61); // The section ends after 20 bytes...
+        data.resize(2000, 0x00); // ...but the input goes on
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder.decode_import_section(20).err().unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::LengthOutOfBounds {
+                field: "import module name",
+                length: 1000,
+                remaining: 17,
+                offset: 3
+            }
+        ));
+        assert!(error.to_string().contains("import module name length 1000"));
+    }
+
+    /// Decode a code section holding a single body with no locals and the given expression.
+    fn decode_single_body(expression: &[u8]) -> Result<()> {
+        let mut data = vec![0x01, expression.len() as u8 + 1, 0x00];
+        data.extend_from_slice(expression);
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        decoder.decode_code_section(data.len() as u32).map(|_| ())
+    }
+
+    #[test]
+    fn test_decode_code_section_balanced_control() {
+        // block, if, else, end, end, end
+        assert!(
+            decode_single_body(&[0x02, 0x40, 0x41, 0x00, 0x04, 0x40, 0x05, 0x0b, 0x0b, 0x0b])
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_decode_code_section_extra_end() {
+        let error = decode_single_body(&[0x01, 0x0b, 0x0b]).err().unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::UnbalancedControl {
+                func_index: 0,
+                instruction: "end",
+                offset: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_code_section_stray_else() {
+        // block, else, end, end
+        let error = decode_single_body(&[0x02, 0x40, 0x05, 0x0b, 0x0b])
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::UnbalancedControl {
+                func_index: 0,
+                instruction: "else",
+                offset: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_import_section_invalid_kind() {
+        let data = [
+            0x01, // Import count (1)
+            0x03, 0x65, 0x6e, 0x76, // Module name "env"
+            0x01, 0x66, // Field name "f"
+            0x04, // Import kind (none)
+            0x00,
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_import_section(data.len() as u32)
+            .err()
+            .unwrap();
+        match &error {
+            DecodeError::InSection {
+                section_id: 2,
+                item_index: Some(0),
+                source,
+            } => assert!(matches!(
+                **source,
+                DecodeError::ImportKind {
+                    kind: 0x04,
+                    offset: 7
+                }
+            )),
+            _ => panic!("Expected InSection, got {error}"),
+        }
+        assert_eq!(
+            error.to_string(),
+            "import section, entry 0: Invalid import kind 0x04 at offset 0x7."
+        );
+    }
+
+    #[test]
+    fn test_decode_export_section() {
+        let data = [
+            0x04, // Export count (4)
+            0x01, b'f', 0x00, 0x03, // "f": function 3
+            0x01, b't', 0x01, 0x00, // "t": table 0
+            0x01, b'm', 0x02, 0x00, // "m": memory 0
+            0x01, b'g', 0x03, 0x02, // "g": global 2
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let exports = match decoder.decode_export_section(data.len() as u32).unwrap() {
+            WasmSection::Export(exports) => exports,
+            _ => panic!("Expected export section"),
+        };
+
+        let name = |export: &WasmExportEntry| export.name().to_string();
+        assert_eq!(exports.functions().map(name).collect::<Vec<_>>(), ["f"]);
+        assert_eq!(exports.tables().map(name).collect::<Vec<_>>(), ["t"]);
+        assert_eq!(exports.memories().map(name).collect::<Vec<_>>(), ["m"]);
+        assert_eq!(exports.globals().map(name).collect::<Vec<_>>(), ["g"]);
+        assert_eq!(exports.items()[0].descriptor().index(), 3);
+        assert_eq!(exports.items()[3].descriptor().index(), 2);
+
+        assert_eq!(exports.kind_of("f"), Some(ExportKind::Function));
+        assert_eq!(exports.kind_of("t"), Some(ExportKind::Table));
+        assert_eq!(exports.kind_of("m"), Some(ExportKind::Memory));
+        assert_eq!(exports.kind_of("g"), Some(ExportKind::Global));
+        assert_eq!(exports.kind_of("missing"), None);
+    }
+
+    #[test]
+    fn test_decode_export_section_invalid_kind() {
+        let data = [
+            0x01, // Export count (1)
+            0x01, b'x', // Name "x"
+            0x04, // Export kind (none)
+            0x00,
+        ];
+
+        let mut decoder = Decoder::new(Cursor::new(&data[..]));
+        let error = decoder
+            .decode_export_section(data.len() as u32)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::ExportKind {
+                kind: 0x04,
+                offset: 3
+            }
+        ));
+        assert_eq!(ExportKind::try_from(0x04), Err(0x04));
+    }
+
+    #[test]
+    fn test_decode_function_section_truncated_count() {
+        let mut decoder = Decoder::new(Cursor::new(&[0x80][..]));
+        let error = decoder.decode_function_section(1).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::InSection {
+                section_id: 3,
+                item_index: None,
+                ..
+            }
+        ));
+        assert!(error.to_string().starts_with("function section: "));
+    }
 }
@@ -0,0 +1,756 @@
+use crate::decode::decoder::Decoder;
+use crate::decode::DecodeError;
+use crate::decode::{DecoderConfig, Result};
+use crate::encode::encode_instruction;
+use crate::types::{
+    ConstExpr, ElementItems, ElementMode, ElementSegment, FuncIndex, GlobalIndex, MemoryIndex,
+    TableIndex, TypeIndex, WasmExportDescriptor, WasmValueType,
+};
+use crate::wasm::instructions::{
+    BlockType, ExtendedInstruction, Instruction, MemArg, SimdInstruction,
+};
+use crate::wasm::opcodes::Opcode;
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+/// Prefix byte shared by the saturating truncation and bulk memory instructions.
+const EXTENDED_PREFIX: u8 = 0xfc;
+
+/// Prefix byte of the fixed-width SIMD instructions.
+const SIMD_PREFIX: u8 = 0xfd;
+
+/// Highest sub-opcode assigned by the fixed-width SIMD proposal.
+const SIMD_MAX_OPCODE: u32 = 0xff;
+
+/// An open block while checking that a function body's control instructions are balanced.
+enum ControlFrame {
+    /// The function body, closed by its final `end`.
+    Body,
+    /// A `block` or `loop`.
+    Block,
+    /// An `if` that has not seen its `else` yet.
+    If,
+    /// The `else` branch of an `if`.
+    Else,
+}
+
+/// How `remap_indices` rewrites the indices an instruction refers to, and the provided
+/// methods the indices in other sections. Every index space is left as it is unless its
+/// method is overridden.
+pub(crate) trait IndexRemap {
+    fn type_index(&self, index: TypeIndex) -> TypeIndex {
+        index
+    }
+
+    fn func_index(&self, index: FuncIndex) -> FuncIndex {
+        index
+    }
+
+    fn global_index(&self, index: GlobalIndex) -> GlobalIndex {
+        index
+    }
+
+    fn table_index(&self, index: TableIndex) -> TableIndex {
+        index
+    }
+
+    /// Used for exports, data segments and the memory instructions that name a memory:
+    /// `memory.size`, `memory.grow`, `memory.init`, `memory.copy` and `memory.fill`.
+    fn memory_index(&self, index: MemoryIndex) -> MemoryIndex {
+        index
+    }
+
+    fn elem_index(&self, index: u32) -> u32 {
+        index
+    }
+
+    fn data_index(&self, index: u32) -> u32 {
+        index
+    }
+
+    fn const_expr(&self, expr: ConstExpr) -> ConstExpr {
+        match expr {
+            ConstExpr::GlobalGet(index) => ConstExpr::GlobalGet(self.global_index(index)),
+            ConstExpr::RefFunc(index) => ConstExpr::RefFunc(self.func_index(index)),
+            expr => expr,
+        }
+    }
+
+    fn export(&self, descriptor: WasmExportDescriptor) -> WasmExportDescriptor {
+        match descriptor {
+            WasmExportDescriptor::Function(index) => {
+                WasmExportDescriptor::Function(self.func_index(index))
+            }
+            WasmExportDescriptor::Table(index) => {
+                WasmExportDescriptor::Table(self.table_index(index))
+            }
+            WasmExportDescriptor::Memory(index) => {
+                WasmExportDescriptor::Memory(self.memory_index(index))
+            }
+            WasmExportDescriptor::Global(index) => {
+                WasmExportDescriptor::Global(self.global_index(index))
+            }
+        }
+    }
+
+    fn element_segment(&self, segment: ElementSegment) -> ElementSegment {
+        ElementSegment {
+            mode: match segment.mode {
+                ElementMode::Active {
+                    table_index,
+                    offset,
+                } => ElementMode::Active {
+                    table_index: self.table_index(table_index),
+                    offset: self.const_expr(offset),
+                },
+                mode => mode,
+            },
+            items: match segment.items {
+                ElementItems::Functions(indices) => ElementItems::Functions(
+                    indices
+                        .into_iter()
+                        .map(|index| self.func_index(index))
+                        .collect(),
+                ),
+                ElementItems::Expressions(exprs) => ElementItems::Expressions(
+                    exprs
+                        .into_iter()
+                        .map(|expr| self.const_expr(expr))
+                        .collect(),
+                ),
+            },
+            ..segment
+        }
+    }
+}
+
+/// Copy the expression of a function body, rewriting every instruction that refers to a type,
+/// function, global, table, memory, element segment or data segment by index with the indices
+/// `remap` gives. Rewritten instructions use as few bytes as possible; all other bytes are
+/// copied unchanged. Loads and stores are not rewritten, as their memory arguments are decoded
+/// without a memory index.
+pub(crate) fn remap_indices(expression: &[u8], remap: &impl IndexRemap) -> Result<Vec<u8>> {
+    // The body was checked when it was decoded, with whatever features it needed.
+    let config = DecoderConfig::new().strict_leb128(false).allow_gc(true);
+    let mut decoder = Decoder::with_config(Cursor::new(expression), config);
+    let mut out = Vec::with_capacity(expression.len());
+    let mut copied = 0;
+
+    while !decoder.is_end() {
+        let start = decoder.position() as usize;
+        let instruction = decoder.decode_instruction()?;
+        let end = decoder.position() as usize;
+
+        let mut rewritten = Vec::new();
+        if encode_remapped(&instruction, expression[start], remap, &mut rewritten) {
+            out.extend_from_slice(&expression[copied..start]);
+            out.extend_from_slice(&rewritten);
+            copied = end;
+        }
+    }
+
+    out.extend_from_slice(&expression[copied..]);
+    Ok(out)
+}
+
+/// Copy the expression of a function body with every integer in its shortest form, as
+/// LEB128 allows padding the same value with extra bytes.
+pub(crate) fn canonicalize_expression(expression: &[u8]) -> Result<Vec<u8>> {
+    // As in `remap_indices`, the body was checked when it was decoded.
+    let config = DecoderConfig::new().strict_leb128(false).allow_gc(true);
+    let mut decoder = Decoder::with_config(Cursor::new(expression), config);
+    let mut out = Vec::with_capacity(expression.len());
+
+    while !decoder.is_end() {
+        let opcode = expression[decoder.position() as usize];
+        encode_instruction(&decoder.decode_instruction()?, opcode, &mut out);
+    }
+    Ok(out)
+}
+
+/// Append `instruction`, whose opcode byte is `opcode`, with its indices remapped. Returns
+/// false, appending nothing, if it does not refer to anything by index.
+fn encode_remapped(
+    instruction: &Instruction,
+    opcode: u8,
+    remap: &impl IndexRemap,
+    out: &mut Vec<u8>,
+) -> bool {
+    match remap_instruction(instruction, remap) {
+        Some(remapped) => {
+            encode_instruction(&remapped, opcode, out);
+            true
+        }
+        None => false,
+    }
+}
+
+/// `instruction` with the indices `remap` gives, or `None` if it does not refer to anything
+/// by index.
+fn remap_instruction(instruction: &Instruction, remap: &impl IndexRemap) -> Option<Instruction> {
+    use ExtendedInstruction::*;
+
+    let remapped = match *instruction {
+        Instruction::Block(BlockType::TypeIndex(type_index)) => {
+            Instruction::Block(BlockType::TypeIndex(remap.type_index(type_index)))
+        }
+        Instruction::Loop(BlockType::TypeIndex(type_index)) => {
+            Instruction::Loop(BlockType::TypeIndex(remap.type_index(type_index)))
+        }
+        Instruction::If(BlockType::TypeIndex(type_index)) => {
+            Instruction::If(BlockType::TypeIndex(remap.type_index(type_index)))
+        }
+        Instruction::Call(func_index) => Instruction::Call(remap.func_index(func_index)),
+        Instruction::RefFunc(func_index) => Instruction::RefFunc(remap.func_index(func_index)),
+        Instruction::CallIndirect {
+            type_index,
+            table_index,
+        } => Instruction::CallIndirect {
+            type_index: remap.type_index(type_index),
+            table_index: remap.table_index(table_index),
+        },
+        Instruction::GetGlobal(global_index) => {
+            Instruction::GetGlobal(remap.global_index(global_index))
+        }
+        Instruction::SetGlobal(global_index) => {
+            Instruction::SetGlobal(remap.global_index(global_index))
+        }
+        Instruction::TableGet(table_index) => Instruction::TableGet(remap.table_index(table_index)),
+        Instruction::TableSet(table_index) => Instruction::TableSet(remap.table_index(table_index)),
+        Instruction::CurrentMemory(memory) => {
+            Instruction::CurrentMemory(remap.memory_index(memory))
+        }
+        Instruction::GrowMemory(memory) => Instruction::GrowMemory(remap.memory_index(memory)),
+        Instruction::Extended(extended) => Instruction::Extended(match extended {
+            MemoryInit { data_index, memory } => MemoryInit {
+                data_index: remap.data_index(data_index),
+                memory: remap.memory_index(memory),
+            },
+            DataDrop(data_index) => DataDrop(remap.data_index(data_index)),
+            MemoryCopy {
+                dst_memory,
+                src_memory,
+            } => MemoryCopy {
+                dst_memory: remap.memory_index(dst_memory),
+                src_memory: remap.memory_index(src_memory),
+            },
+            MemoryFill(memory) => MemoryFill(remap.memory_index(memory)),
+            TableInit { elem_index, table } => TableInit {
+                elem_index: remap.elem_index(elem_index),
+                table: remap.table_index(table),
+            },
+            ElemDrop(elem_index) => ElemDrop(remap.elem_index(elem_index)),
+            TableCopy {
+                dst_table,
+                src_table,
+            } => TableCopy {
+                dst_table: remap.table_index(dst_table),
+                src_table: remap.table_index(src_table),
+            },
+            TableGrow(table) => TableGrow(remap.table_index(table)),
+            TableSize(table) => TableSize(remap.table_index(table)),
+            TableFill(table) => TableFill(remap.table_index(table)),
+            _ => return None,
+        }),
+        _ => return None,
+    };
+    Some(remapped)
+}
+
+/// The instructions of the expression of a function body, decoded one at a time; see
+/// `FunctionBody::instructions`. Ends after the first error.
+pub(crate) struct Instructions<'a> {
+    decoder: Decoder<'a>,
+    failed: bool,
+}
+
+impl<'a> Instructions<'a> {
+    pub(crate) fn new(expression: &'a [u8]) -> Self {
+        // As in `remap_indices`, the body was checked when it was decoded.
+        let config = DecoderConfig::new().strict_leb128(false).allow_gc(true);
+        Instructions {
+            decoder: Decoder::with_config(Cursor::new(expression), config),
+            failed: false,
+        }
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Result<Instruction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.decoder.is_end() {
+            return None;
+        }
+        let instruction = self.decoder.decode_instruction();
+        self.failed = instruction.is_err();
+        Some(instruction)
+    }
+}
+
+impl<'a> Decoder<'a> {
+    /// Decode a single instruction, including any immediates that follow its opcode.
+    pub(crate) fn decode_instruction(&mut self) -> Result<Instruction> {
+        let offset = self.offset();
+        let byte = self.read_byte()?;
+        let opcode =
+            Opcode::try_from(byte).map_err(|opcode| DecodeError::OpCode { opcode, offset })?;
+
+        use Opcode::*;
+        let instruction = match opcode {
+            Block => Instruction::Block(self.decode_block_type()?),
+            Loop => Instruction::Loop(self.decode_block_type()?),
+            If => Instruction::If(self.decode_block_type()?),
+            Br => Instruction::Br(self.decode_varuint()?.into()),
+            BrIf => Instruction::BrIf(self.decode_varuint()?.into()),
+            BrTable => {
+                let targets = self.decode_vec(|decoder| Ok(decoder.decode_varuint()?.into()))?;
+
+                Instruction::BrTable {
+                    targets,
+                    default: self.decode_varuint()?.into(),
+                }
+            }
+            Call => Instruction::Call(self.decode_varuint()?.into()),
+            CallIndirect => Instruction::CallIndirect {
+                type_index: self.decode_varuint()?.into(),
+                table_index: self.decode_varuint()?.into(),
+            },
+            GetLocal => Instruction::GetLocal(self.decode_varuint()?.into()),
+            SetLocal => Instruction::SetLocal(self.decode_varuint()?.into()),
+            TeeLocal => Instruction::TeeLocal(self.decode_varuint()?.into()),
+            GetGlobal => Instruction::GetGlobal(self.decode_varuint()?.into()),
+            SetGlobal => Instruction::SetGlobal(self.decode_varuint()?.into()),
+            SelectTyped => Instruction::SelectTyped(self.decode_vec(Decoder::decode_value_type)?),
+            TableGet => Instruction::TableGet(self.decode_varuint()?.into()),
+            TableSet => Instruction::TableSet(self.decode_varuint()?.into()),
+            I32Load | I64Load | F32Load | F64Load | I32Load8S | I32Load8U | I32Load16S
+            | I32Load16U | I64Load8S | I64Load8U | I64Load16S | I64Load16U | I64Load32S
+            | I64Load32U | I32Store | I64Store | F32Store | F64Store | I32Store8 | I32Store16
+            | I64Store8 | I64Store16 | I64Store32 => {
+                Instruction::Memory(opcode, self.decode_memarg()?)
+            }
+            CurrentMemory => Instruction::CurrentMemory(self.decode_varuint()?.into()),
+            GrowMemory => Instruction::GrowMemory(self.decode_varuint()?.into()),
+            I32Const => Instruction::I32Const(self.decode_varint32()?),
+            I64Const => Instruction::I64Const(self.decode_varint64()?),
+            F32Const => Instruction::F32Const(f32::from_le_bytes(self.read_array()?)),
+            F64Const => Instruction::F64Const(f64::from_le_bytes(self.read_array()?)),
+            RefNull => Instruction::RefNull(self.decode_element_type()?),
+            RefFunc => Instruction::RefFunc(self.decode_varuint()?.into()),
+            Prefix => Instruction::Extended(self.decode_extended_instruction(offset)?),
+            SimdPrefix => Instruction::Simd(self.decode_simd_instruction(offset)?),
+            Reserved => {
+                return Err(DecodeError::OpCode {
+                    opcode: byte,
+                    offset,
+                })
+            }
+            _ => Instruction::Plain(opcode),
+        };
+
+        Ok(instruction)
+    }
+
+    /// Decode the instructions of a function body up to the end of the input, checking that
+    /// control instructions are balanced and that the last one is the `end` that closes the
+    /// body, i.e. the `end` at nesting depth zero.
+    pub(crate) fn validate_expression(&mut self, func_index: u32) -> Result<()> {
+        // The body itself is the outermost block.
+        let mut frames = vec![ControlFrame::Body];
+
+        while !self.is_end() {
+            let offset = self.offset();
+            let instruction = self.decode_instruction()?;
+
+            let unbalanced = |instruction| DecodeError::UnbalancedControl {
+                func_index,
+                instruction,
+                offset,
+            };
+
+            // Nothing may follow the `end` of the body.
+            if frames.is_empty() {
+                let name = match instruction {
+                    Instruction::Plain(Opcode::End) => "end",
+                    _ => "instruction after the final end",
+                };
+                return Err(unbalanced(name));
+            }
+
+            match instruction {
+                Instruction::Block(_) | Instruction::Loop(_) => frames.push(ControlFrame::Block),
+                Instruction::If(_) => frames.push(ControlFrame::If),
+                Instruction::Plain(Opcode::Else) => match frames.last_mut() {
+                    Some(frame @ ControlFrame::If) => *frame = ControlFrame::Else,
+                    _ => return Err(unbalanced("else")),
+                },
+                Instruction::Plain(Opcode::End) => {
+                    frames.pop();
+                }
+                _ => {}
+            }
+        }
+
+        if !frames.is_empty() {
+            return Err(DecodeError::MissingEnd {
+                func_index,
+                offset: self.offset(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Decode a constant expression, such as the initializer of a global: exactly one
+    /// constant instruction, followed by `end`.
+    pub(crate) fn decode_const_expr(&mut self) -> Result<ConstExpr> {
+        let (instruction, non_constant) = self.decode_const_instruction()?;
+        let expression = match instruction {
+            Instruction::I32Const(value) => ConstExpr::I32Const(value),
+            Instruction::I64Const(value) => ConstExpr::I64Const(value),
+            Instruction::F32Const(value) => ConstExpr::F32Const(value),
+            Instruction::F64Const(value) => ConstExpr::F64Const(value),
+            Instruction::GetGlobal(index) => ConstExpr::GlobalGet(index),
+            Instruction::RefNull(element_type) => ConstExpr::RefNull(element_type),
+            Instruction::RefFunc(index) => ConstExpr::RefFunc(index),
+            _ => return Err(non_constant),
+        };
+
+        match self.decode_const_instruction()? {
+            (Instruction::Plain(Opcode::End), _) => Ok(expression),
+            (_, non_constant) => Err(non_constant),
+        }
+    }
+
+    /// Decode an instruction of a constant expression, together with the error to report if it
+    /// is not allowed there.
+    fn decode_const_instruction(&mut self) -> Result<(Instruction, DecodeError)> {
+        let start = self.position();
+        let offset = self.offset();
+        let opcode = self.read_byte()?;
+        self.set_position(start);
+
+        let instruction = self.decode_instruction()?;
+        Ok((instruction, DecodeError::NonConstantExpr { opcode, offset }))
+    }
+
+    /// Decode the block type of a `block`, `loop` or `if`.
+    /// Layout: either `0x40`, a single value type byte, or a positive s33 type index.
+    pub(crate) fn decode_block_type(&mut self) -> Result<BlockType> {
+        let start = self.position();
+        let offset = self.offset();
+        let byte = self.read_byte()?;
+
+        if byte == 0x40 {
+            return Ok(BlockType::Empty);
+        }
+        if let Ok(value_type) = WasmValueType::try_from(byte) {
+            return Ok(BlockType::Value(value_type));
+        }
+
+        self.set_position(start);
+        let index = self.decode_signed(33)?;
+        u32::try_from(index)
+            .map(|index| BlockType::TypeIndex(index.into()))
+            .map_err(|_| DecodeError::Numeric {
+                current_value: index as u32,
+                invalid_byte: byte as u32,
+                offset,
+            })
+    }
+
+    fn decode_memarg(&mut self) -> Result<MemArg> {
+        Ok(MemArg {
+            align: self.decode_varuint()?.into(),
+            offset: self.decode_varuint()?.into(),
+        })
+    }
+
+    /// Decode the sub-opcode and immediates of an `0xfc` prefixed instruction whose prefix
+    /// byte is at `offset`.
+    fn decode_extended_instruction(&mut self, offset: u64) -> Result<ExtendedInstruction> {
+        use ExtendedInstruction::*;
+
+        let sub_opcode: u32 = self.decode_varuint()?.into();
+        let instruction = match sub_opcode {
+            0 => I32TruncSatF32S,
+            1 => I32TruncSatF32U,
+            2 => I32TruncSatF64S,
+            3 => I32TruncSatF64U,
+            4 => I64TruncSatF32S,
+            5 => I64TruncSatF32U,
+            6 => I64TruncSatF64S,
+            7 => I64TruncSatF64U,
+            8 => MemoryInit {
+                data_index: self.decode_varuint()?.into(),
+                memory: self.decode_varuint()?.into(),
+            },
+            9 => DataDrop(self.decode_varuint()?.into()),
+            10 => MemoryCopy {
+                dst_memory: self.decode_varuint()?.into(),
+                src_memory: self.decode_varuint()?.into(),
+            },
+            11 => MemoryFill(self.decode_varuint()?.into()),
+            12 => TableInit {
+                elem_index: self.decode_varuint()?.into(),
+                table: self.decode_varuint()?.into(),
+            },
+            13 => ElemDrop(self.decode_varuint()?.into()),
+            14 => TableCopy {
+                dst_table: self.decode_varuint()?.into(),
+                src_table: self.decode_varuint()?.into(),
+            },
+            15 => TableGrow(self.decode_varuint()?.into()),
+            16 => TableSize(self.decode_varuint()?.into()),
+            17 => TableFill(self.decode_varuint()?.into()),
+            _ => {
+                return Err(DecodeError::PrefixedOpCode {
+                    prefix: EXTENDED_PREFIX,
+                    opcode: sub_opcode,
+                    offset,
+                })
+            }
+        };
+
+        Ok(instruction)
+    }
+
+    /// Decode the sub-opcode and immediates of an `0xfd` prefixed instruction whose prefix
+    /// byte is at `offset`.
+    fn decode_simd_instruction(&mut self, offset: u64) -> Result<SimdInstruction> {
+        use SimdInstruction::*;
+
+        let opcode: u32 = self.decode_varuint()?.into();
+        let instruction = match opcode {
+            0x00..=0x0b | 0x5c | 0x5d => Memory {
+                opcode,
+                memarg: self.decode_memarg()?,
+            },
+            0x0c => V128Const(self.read_array()?),
+            0x0d => I8x16Shuffle(self.read_array()?),
+            0x15..=0x22 => Lane {
+                opcode,
+                lane: self.read_byte()?,
+            },
+            0x54..=0x5b => MemoryLane {
+                opcode,
+                memarg: self.decode_memarg()?,
+                lane: self.read_byte()?,
+            },
+            0x0e..=SIMD_MAX_OPCODE => Plain(opcode),
+            _ => {
+                return Err(DecodeError::PrefixedOpCode {
+                    prefix: SIMD_PREFIX,
+                    opcode,
+                    offset,
+                })
+            }
+        };
+
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WasmElementType;
+    use std::io::Cursor;
+
+    fn decode(bytes: &[u8]) -> Instruction {
+        Decoder::new(Cursor::new(bytes))
+            .decode_instruction()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_decode_plain_and_const_instructions() {
+        assert_eq!(decode(&[0x6a]), Instruction::Plain(Opcode::I32Add));
+        assert_eq!(decode(&[0x41, 0x7f]), Instruction::I32Const(-1));
+        assert_eq!(
+            decode(&[0x02, 0x7f]),
+            Instruction::Block(BlockType::Value(WasmValueType::I32))
+        );
+    }
+
+    #[test]
+    fn test_decode_const_expr() {
+        let decode_const = |bytes: &[u8]| Decoder::new(Cursor::new(bytes)).decode_const_expr();
+
+        assert_eq!(
+            decode_const(&[0x41, 0x0a, 0x0b]).unwrap(),
+            ConstExpr::I32Const(10)
+        );
+        assert_eq!(
+            decode_const(&[0xd0, 0x70, 0x0b]).unwrap(),
+            ConstExpr::RefNull(WasmElementType::Funcref)
+        );
+        assert_eq!(
+            decode_const(&[0x23, 0x01, 0x0b]).unwrap(),
+            ConstExpr::GlobalGet(1.into())
+        );
+
+        let error = decode_const(&[0x20, 0x00, 0x0b]).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::NonConstantExpr {
+                opcode: 0x20,
+                offset: 0
+            }
+        ));
+        assert!(error.to_string().contains("local.get"), "{error}");
+
+        // A second instruction instead of the `end`.
+        let error = decode_const(&[0x41, 0x01, 0x41, 0x02, 0x0b]).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::NonConstantExpr {
+                opcode: 0x41,
+                offset: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_memory_copy() {
+        assert_eq!(
+            decode(&[0xfc, 0x0a, 0x00, 0x00]),
+            Instruction::Extended(ExtendedInstruction::MemoryCopy {
+                dst_memory: 0.into(),
+                src_memory: 0.into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_trunc_sat() {
+        assert_eq!(
+            decode(&[0xfc, 0x06]),
+            Instruction::Extended(ExtendedInstruction::I64TruncSatF64S)
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown_extended_instruction() {
+        let mut decoder = Decoder::new(Cursor::new(&[0xfc, 0x7f][..]));
+        let error = decoder.decode_instruction().unwrap_err();
+        assert!(matches!(
+            error,
+            DecodeError::PrefixedOpCode {
+                prefix: 0xfc,
+                opcode: 0x7f,
+                offset: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_v128_const() {
+        let mut bytes = vec![0xfd, 0x0c];
+        bytes.extend(0x10..0x20);
+
+        let mut payload = [0; 16];
+        payload.copy_from_slice(&bytes[2..]);
+
+        assert_eq!(
+            decode(&bytes),
+            Instruction::Simd(SimdInstruction::V128Const(payload))
+        );
+    }
+
+    #[test]
+    fn test_decode_simd_lane_and_plain() {
+        // i32x4.extract_lane 3, then i32x4.add (sub-opcode 0xae, two-byte LEB128)
+        assert_eq!(
+            decode(&[0xfd, 0x1b, 0x03]),
+            Instruction::Simd(SimdInstruction::Lane {
+                opcode: 0x1b,
+                lane: 3
+            })
+        );
+        assert_eq!(
+            decode(&[0xfd, 0xae, 0x01]),
+            Instruction::Simd(SimdInstruction::Plain(0xae))
+        );
+    }
+
+    /// Moves every memory up by one, and data segment 0 to 2.
+    struct ShiftMemories;
+
+    impl IndexRemap for ShiftMemories {
+        fn memory_index(&self, index: MemoryIndex) -> MemoryIndex {
+            (u32::from(index) + 1).into()
+        }
+
+        fn data_index(&self, index: u32) -> u32 {
+            index + 2
+        }
+    }
+
+    #[test]
+    fn test_remap_memory_indices() {
+        let expression = [
+            0x3f, 0x00, // memory.size 0
+            0x40, 0x01, // memory.grow 1
+            0xfc, 0x08, 0x00, 0x00, // memory.init 0 0
+            0xfc, 0x0a, 0x01, 0x00, // memory.copy 1 0
+            0xfc, 0x0b, 0x02, // memory.fill 2
+            0x28, 0x02, 0x00, // i32.load, not rewritten
+            0x0b, // end
+        ];
+        let remapped = remap_indices(&expression, &ShiftMemories).unwrap();
+        assert_eq!(
+            remapped,
+            [
+                0x3f, 0x01, 0x40, 0x02, 0xfc, 0x08, 0x02, 0x01, 0xfc, 0x0a, 0x02, 0x01, 0xfc, 0x0b,
+                0x03, 0x28, 0x02, 0x00, 0x0b,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_expression() {
+        let canonical = [
+            0x02, 0x40, // block
+            0x04, 0x7f, // if (result i32)
+            0x0e, 0x02, 0x00, 0x01, 0x00, // br_table 0 1 0
+            0x0b, // end
+            0x0b, // end
+            0x11, 0x02, 0x00, // call_indirect 2 0
+            0x1c, 0x01, 0x7e, // select (result i64)
+            0x28, 0x02, 0x10, // i32.load align=4 offset=16
+            0x42, 0x7f, // i64.const -1
+            0x43, 0x00, 0x00, 0x80, 0x3f, // f32.const 1
+            0xd0, 0x70, // ref.null func
+            0xfc, 0x0c, 0x01, 0x00, // table.init 1 0
+            0xfd, 0x1b, 0x03, // i32x4.extract_lane 3
+            0xfd, 0xae, 0x01, // i32x4.add
+            0x6a, // i32.add
+            0x0b, // end
+        ];
+        assert_eq!(canonicalize_expression(&canonical).unwrap(), canonical);
+
+        // `call 2` and `i32.const 42` with their immediates padded, and a padded sub-opcode.
+        let padded = [
+            0x10, 0x82, 0x00, 0x41, 0xaa, 0x80, 0x00, 0xfc, 0x89, 0x00, 0x03, 0x0b,
+        ];
+        assert_eq!(
+            canonicalize_expression(&padded).unwrap(),
+            [0x10, 0x02, 0x41, 0x2a, 0xfc, 0x09, 0x03, 0x0b]
+        );
+    }
+
+    #[test]
+    fn test_decode_reference_instructions() {
+        assert_eq!(
+            decode(&[0xd0, 0x6f]),
+            Instruction::RefNull(WasmElementType::Externref)
+        );
+        assert_eq!(decode(&[0xd2, 0x03]), Instruction::RefFunc(3.into()));
+        assert_eq!(
+            decode(&[0x1c, 0x01, 0x7e]),
+            Instruction::SelectTyped(vec![WasmValueType::I64])
+        );
+    }
+}
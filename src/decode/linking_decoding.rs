@@ -0,0 +1,213 @@
+use crate::decode::decoder::Decoder;
+use crate::decode::{DecodeError, Result};
+use crate::types::{
+    DataSymbol, LinkingSection, LinkingSubsection, SegmentInfo, Symbol, SymbolKind,
+};
+use std::io::Cursor;
+
+/// Id of the segment info subsection.
+const WASM_SEGMENT_INFO: u8 = 5;
+
+/// Id of the symbol table subsection.
+const WASM_SYMBOL_TABLE: u8 = 8;
+
+/// Decode the contents of a `linking` custom section, after its name. Offsets in errors are
+/// relative to the start of `data`.
+///
+/// Layout:
+/// (1) version (varuint)
+/// (2) subsections, up to the end of the section
+///     - (3) id (byte)
+///     - (4) size (varuint)
+///     - (5) contents
+pub(crate) fn decode_linking_section(data: &[u8]) -> Result<LinkingSection<'_>> {
+    let mut decoder = Decoder::new(Cursor::new(data));
+    let version = decoder.decode_varuint()?.into();
+
+    let mut subsections = Vec::new();
+    while !decoder.is_end() {
+        let id = decoder.read_byte()?;
+        let size: u32 = decoder.decode_varuint()?.into();
+        let offset = decoder.offset();
+        let mut subsection = decoder.slice_decoder(size)?;
+
+        let decoded = match id {
+            WASM_SEGMENT_INFO => LinkingSubsection::SegmentInfo(subsection.decode_indexed_vec(
+                |decoder, index| {
+                    Ok(SegmentInfo {
+                        name: decoder.read_name("linking", "segment name", index)?,
+                        alignment: decoder.decode_varuint()?.into(),
+                        flags: decoder.decode_varuint()?.into(),
+                    })
+                },
+            )?),
+            WASM_SYMBOL_TABLE => LinkingSubsection::SymbolTable(
+                subsection.decode_indexed_vec(Decoder::decode_symbol)?,
+            ),
+            _ => LinkingSubsection::Other {
+                id,
+                data: subsection.read_slice(size)?,
+            },
+        };
+
+        if !subsection.is_end() {
+            return Err(DecodeError::SubsectionSize {
+                id,
+                declared: size,
+                consumed: subsection.position(),
+                offset,
+            });
+        }
+        subsections.push(decoded);
+    }
+
+    Ok(LinkingSection {
+        version,
+        subsections,
+    })
+}
+
+impl<'a> Decoder<'a> {
+    /// Decode an entry of the symbol table.
+    ///
+    /// Layout:
+    /// (1) kind (byte)
+    /// (2) flags (varuint)
+    /// (3) for data symbols: the name, then unless undefined, the segment index, offset and
+    ///     size (varuints)
+    /// (3) for section symbols: the section index (varuint)
+    /// (3) for any other symbol: the index (varuint), then unless undefined without an
+    ///     explicit name, the name
+    fn decode_symbol(&mut self, index: u32) -> Result<Symbol<'a>> {
+        let offset = self.offset();
+        let kind = self.read_byte()?;
+        let flags: u32 = self.decode_varuint()?.into();
+        let undefined = flags & Symbol::UNDEFINED != 0;
+
+        let (kind, name) = match kind {
+            1 => {
+                let name = self.read_name("linking", "symbol name", index)?;
+                let data = match undefined {
+                    true => None,
+                    false => Some(DataSymbol {
+                        segment: self.decode_varuint()?.into(),
+                        offset: self.decode_varuint64()?,
+                        size: self.decode_varuint64()?,
+                    }),
+                };
+                (SymbolKind::Data(data), Some(name))
+            }
+            3 => (SymbolKind::Section(self.decode_varuint()?.into()), None),
+            0 | 2 | 4 | 5 => {
+                let item: u32 = self.decode_varuint()?.into();
+                let name = match !undefined || flags & Symbol::EXPLICIT_NAME != 0 {
+                    true => Some(self.read_name("linking", "symbol name", index)?),
+                    false => None,
+                };
+                let kind = match kind {
+                    0 => SymbolKind::Function(item),
+                    2 => SymbolKind::Global(item),
+                    4 => SymbolKind::Tag(item),
+                    _ => SymbolKind::Table(item),
+                };
+                (kind, name)
+            }
+            _ => return Err(DecodeError::SymbolKind { kind, offset }),
+        };
+
+        Ok(Symbol { kind, flags, name })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_linking_section;
+    use crate::decode::DecodeError;
+    use crate::types::{DataSymbol, LinkingSubsection, Symbol, SymbolKind};
+
+    #[test]
+    fn test_decode_linking_symbol_table() {
+        let data = [
+            0x02, // Version 2
+            0x08, 0x09, // Symbol table, 9 bytes
+            0x01, // One symbol
+            0x00, 0x00, 0x03, // A defined function symbol for function 3, named
+            0x04, b'm', b'a', b'i', b'n', // "main"
+        ];
+
+        let section = decode_linking_section(&data).unwrap();
+        assert_eq!(section.version(), 2);
+        assert_eq!(section.subsections().len(), 1);
+
+        let symbol = &section.symbols()[0];
+        assert_eq!(symbol.kind(), SymbolKind::Function(3));
+        assert_eq!(symbol.name(), Some("main"));
+        assert!(!symbol.is_undefined());
+    }
+
+    #[test]
+    fn test_decode_linking_subsections() {
+        let data = [
+            0x02, // Version 2
+            0x05, 0x06, // Segment info, 6 bytes
+            0x01, 0x02, b'.', b'd', 0x02, 0x00, // ".d", aligned to 4 bytes, no flags
+            0x08, 0x0e, // Symbol table, 14 bytes
+            0x03, // Three symbols
+            0x00, 0x10, 0x00, // An undefined function symbol for import 0, without a name
+            0x01, 0x00, 0x01, b'x', 0x00, 0x04, 0x08, // "x": 8 bytes at 4 into segment 0
+            0x03, 0x02, 0x05, // A section symbol for section 5
+            0x06, 0x01, 0x00, // Init functions, not decoded
+        ];
+
+        let section = decode_linking_section(&data).unwrap();
+        let LinkingSubsection::SegmentInfo(segments) = &section.subsections()[0] else {
+            panic!("Expected segment info, got {:?}", section.subsections()[0]);
+        };
+        assert_eq!(segments[0].name(), ".d");
+        assert_eq!(segments[0].alignment(), 2);
+
+        let symbols = section.symbols();
+        assert_eq!(symbols[0].kind(), SymbolKind::Function(0));
+        assert!(symbols[0].is_undefined());
+        assert_eq!(symbols[0].name(), None);
+        assert_eq!(
+            symbols[1].kind(),
+            SymbolKind::Data(Some(DataSymbol {
+                segment: 0,
+                offset: 4,
+                size: 8
+            }))
+        );
+        assert_eq!(symbols[2].kind(), SymbolKind::Section(5));
+        assert_eq!(symbols[2].flags() & Symbol::UNDEFINED, 0);
+        assert_eq!(
+            section.subsections()[2],
+            LinkingSubsection::Other {
+                id: 6,
+                data: &[0x00]
+            }
+        );
+
+        // An unknown symbol kind.
+        let error = decode_linking_section(&[0x02, 0x08, 0x03, 0x01, 0x09, 0x00]).unwrap_err();
+        assert!(matches!(
+            error,
+            DecodeError::SymbolKind {
+                kind: 0x09,
+                offset: 4
+            }
+        ));
+
+        // A symbol table with a byte left over.
+        let error = decode_linking_section(&[0x02, 0x08, 0x02, 0x00, 0x00]).unwrap_err();
+        assert!(matches!(
+            error,
+            DecodeError::SubsectionSize {
+                id: 8,
+                declared: 2,
+                consumed: 1,
+                ..
+            }
+        ));
+    }
+}
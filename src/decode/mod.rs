@@ -1,57 +1,2038 @@
-use crate::decode::decoder::{Decoder, WasmDecoder};
-use crate::module::WasmModule;
-use anyhow::Result;
-use std::io::Cursor;
+use crate::decode::decoder::{check_limit, Decoder, EofContext, SectionHeader, WasmDecoder};
+use crate::module::{RawSection, WasmModule};
+use crate::types::{FuncIndex, ModuleKind, SectionInfo, SectionType, WasmSection, WasmVersion};
+use crate::wasm::opcodes::opcode_name;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-mod data_decoding;
+mod config;
 mod decoder;
+mod instruction_decoding;
+mod linking_decoding;
+mod name_decoding;
+mod warning;
+
+pub use config::DecoderConfig;
+pub(crate) use instruction_decoding::{
+    canonicalize_expression, remap_indices, IndexRemap, Instructions,
+};
+pub(crate) use linking_decoding::decode_linking_section;
+pub(crate) use name_decoding::{decode_function_names, remap_name_section};
+pub use warning::{DecodeWarning, WarningKind};
+
+/// The result of decoding; every failure is a `DecodeError`.
+pub type Result<T> = std::result::Result<T, DecodeError>;
 
 // Constant for the magic bytes expected at the start of a valid WebAssembly binary
-const HEADER_MAGIC_BYTES: [u8; 4] = [0x00, 0x61, 0x73, 0x64];
-const FUNCTION_MAGIC_BYTES: [u8; 1] = [0x60];
+pub(crate) const HEADER_MAGIC_BYTES: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
+pub(crate) const FUNCTION_MAGIC_BYTES: [u8; 1] = [0x60];
+
+// The only binary format version defined for core modules
+pub(crate) const WASM_VERSION: u32 = 1;
+
+/// A coarse classification of `DecodeError`s, e.g. for metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input is not a well-formed module.
+    Malformed,
+    /// The input may be well formed, but uses something this crate cannot decode; see
+    /// `DecodeError::required_feature`.
+    UnsupportedFeature,
+    /// The input exceeds a limit set in the `DecoderConfig`.
+    LimitExceeded,
+    /// The input could not be read.
+    Io,
+}
 
+/// Everything that can go wrong while decoding a binary. Every variant except `Io` knows where
+/// in the input it happened; see `DecodeError::offset`. Use `DecodeError::kind` to tell
+/// broken input from input using features this crate does not support.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum DecodeError {
     // Constant for the magic bytes expected at the start of a valid WebAssembly binary
-    #[error("Not a WebAssembly binary.")]
-    Binary { expected: String, found: String },
+    #[error("Not a WebAssembly binary: expected {}, found {}.", hex(.expected), hex(.found))]
+    Binary { expected: [u8; 4], found: [u8; 4] },
 
     // Error variant representing an invalid numerical value
-    #[error("An invalid numerical value was found while decoding the WebAssembly binary.")]
+    #[error("An invalid numerical value was found at offset {offset:#x} while decoding the WebAssembly binary (value {current_value:#x}, byte {invalid_byte:#04x}).")]
     Numeric {
         current_value: u32,
         invalid_byte: u32,
+        offset: u64,
     },
 
     // Error variant for invalid magic bytes in type section
-    #[error("The WebAssembly binary contains a type section with invalid magic bytes.")]
-    TypeSectionBytes,
+    #[error("The WebAssembly binary contains a type section with invalid magic byte {found:#04x} at offset {offset:#x}, expected 0x60.")]
+    TypeSectionBytes { found: u8, offset: u64 },
 
     // Error variant for invalid element types in a table type
-    #[error("The WebAssembly binary contains a table type with an invalid element type.")]
-    ElementType { invalid_byte: u8 },
+    #[error("The WebAssembly binary contains a table type with an invalid element type {invalid_byte:#04x} at offset {offset:#x}.")]
+    ElementType { invalid_byte: u8, offset: u64 },
+
+    #[error("Invalid value type {value:#04x} at offset {offset:#x}.")]
+    ValueType { value: u32, offset: u64 },
+
+    #[error("The WebAssembly binary contains a global type with an invalid mutability byte {invalid_byte:#04x} at offset {offset:#x}.")]
+    MutabilityByte { invalid_byte: u8, offset: u64 },
+
+    #[error("Unknown/unsupported WebAssembly opcode {opcode:#04x}{} at offset {offset:#x}", describe_opcode(.opcode))]
+    OpCode { opcode: u8, offset: u64 },
+
+    /// `offset` is where the contents of the section start.
+    #[error("Section {section_id} at offset {offset:#x} declares a size of {declared} bytes, but its contents take {consumed}.")]
+    SectionSize {
+        section_id: u8,
+        declared: u32,
+        consumed: u64,
+        offset: u64,
+    },
+
+    #[error("Section {current} at offset {offset:#x} must not follow section {previous}.")]
+    SectionOrder {
+        previous: u8,
+        current: u8,
+        offset: u64,
+    },
+
+    #[error("Section {id} appears twice, at offsets {first_offset:#x} and {second_offset:#x}.")]
+    DuplicateSection {
+        id: u8,
+        first_offset: u64,
+        second_offset: u64,
+    },
+
+    #[error("{remaining} bytes at offset {offset:#x} after the last section are not a section.")]
+    TrailingBytes { offset: u64, remaining: u64 },
+
+    #[error("Unsupported WebAssembly version {found:#010x}.{}", describe_version(.found))]
+    Version { found: u32 },
+
+    #[error("Unexpected end of input at offset {offset:#x} while reading the {context}: {needed} more byte(s) needed.")]
+    UnexpectedEof {
+        offset: u64,
+        needed: u64,
+        context: String,
+    },
+
+    #[error("Invalid UTF-8 in a name of {section} entry {item_index} at offset {offset:#x}.")]
+    InvalidName {
+        section: &'static str,
+        item_index: u32,
+        offset: u64,
+        #[source]
+        source: std::str::Utf8Error,
+    },
+
+    /// `offset` is the end of the body, where the final `end` was expected.
+    #[error("Function body {func_index} does not finish with an `end` at nesting depth zero (offset {offset:#x}).")]
+    MissingEnd { func_index: u32, offset: u64 },
+
+    #[error("The {field} length {length} at offset {offset:#x} exceeds the {remaining} byte(s) left in its section.")]
+    LengthOutOfBounds {
+        field: &'static str,
+        length: u32,
+        remaining: u64,
+        offset: u64,
+    },
+
+    #[error("Unbalanced {instruction} at offset {offset:#x} in function body {func_index}.")]
+    UnbalancedControl {
+        func_index: u32,
+        instruction: &'static str,
+        offset: u64,
+    },
+
+    #[error("Invalid import kind {kind:#04x} at offset {offset:#x}.")]
+    ImportKind { kind: u8, offset: u64 },
+
+    #[error("Invalid export kind {kind:#04x} at offset {offset:#x}.")]
+    ExportKind { kind: u8, offset: u64 },
+
+    /// Only reported with `DecoderConfig::strict_leb128`; `offset` is where the integer starts.
+    #[error("Integer at offset {offset:#x} is encoded with a redundant zero byte.")]
+    NonCanonicalLeb128 { offset: u64 },
+
+    /// `offset` is where the count was read.
+    #[error("{declared} entries declared at offset {offset:#x} cannot fit in the {remaining} byte(s) left in their section.")]
+    MalformedCount {
+        declared: u32,
+        remaining: u64,
+        offset: u64,
+    },
+
+    /// Only reported with `DecoderConfig::allow_gc`; `value` is the s33 the heap type is
+    /// encoded as.
+    #[error("Invalid heap type {value} at offset {offset:#x}.")]
+    HeapType { value: i64, offset: u64 },
+
+    /// Only flags 0 to 7 are defined.
+    #[error("Invalid element segment flags {flags:#x} at offset {offset:#x}.")]
+    ElementSegmentFlags { flags: u32, offset: u64 },
+
+    /// Only `0x00`, for `funcref`, is defined.
+    #[error("Invalid element kind {kind:#04x} at offset {offset:#x}.")]
+    ElementKind { kind: u8, offset: u64 },
+
+    #[error("Invalid data segment flags {flags:#x} at offset {offset:#x}.")]
+    DataSegmentFlags { flags: u32, offset: u64 },
+
+    /// The flags of the limits of a table. Shared and 64-bit tables are only decoded with
+    /// `DecoderConfig::allow_shared_tables` and `DecoderConfig::allow_table64`.
+    #[error("Invalid table limits flags {flags:#04x} at offset {offset:#x}.")]
+    LimitsFlags { flags: u8, offset: u64 },
+
+    /// The flags of the limits of a memory. Shared and 64-bit memories are only decoded with
+    /// `DecoderConfig::allow_shared_memories` and `DecoderConfig::allow_memory64`.
+    #[error("Invalid memory limits flags {flags:#04x} at offset {offset:#x}.")]
+    MemoryLimitsFlags { flags: u8, offset: u64 },
+
+    /// `opcode` is the first byte of the offending instruction; an `end` is missing if it is
+    /// a second constant instruction.
+    #[error("Instruction {opcode:#04x}{} at offset {offset:#x} is not allowed in a constant expression.", describe_opcode(.opcode))]
+    NonConstantExpr { opcode: u8, offset: u64 },
 
-    #[error("The WebAssembly binary contains a global type with an invalid mutability byte.")]
-    MutabilityByte { invalid_byte: u8 },
+    /// Reading the input failed before decoding started; only `decode_reader` returns this.
+    #[error("Failed to read the WebAssembly binary: {0}")]
+    Io(#[from] std::io::Error),
 
-    #[error("Unexpected WebAssembly OpCode received")]
-    OpCode { opcode: u8 },
+    /// Like `Io`, for the file passed to `decode_file`.
+    #[error("Failed to read the WebAssembly binary {}: {source}", .path.display())]
+    File {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// `what` names the limit of the `DecoderConfig` that was exceeded, e.g. "imports".
+    #[error("Limit on {what} exceeded at offset {offset:#x}: found {found}, the configured limit is {limit}.")]
+    LimitExceeded {
+        what: &'static str,
+        limit: u64,
+        found: u64,
+        offset: u64,
+    },
+
+    /// `kind` is the first byte of an entry of the symbol table in the `linking` section.
+    #[error("Invalid symbol kind {kind:#04x} at offset {offset:#x}.")]
+    SymbolKind { kind: u8, offset: u64 },
+
+    /// A subsection of the `linking` section whose contents did not take up its declared size.
+    #[error("Subsection {id} at offset {offset:#x} declares {declared} bytes, but its contents take {consumed}.")]
+    SubsectionSize {
+        id: u8,
+        declared: u32,
+        consumed: u64,
+        offset: u64,
+    },
+
+    /// A length or count that does not fit in a `usize`, which only happens on 16-bit targets;
+    /// see `VarUInt::to_usize_checked`.
+    #[error("{value} does not fit in the address space of this target.")]
+    UsizeOverflow { value: u32 },
+
+    #[error("Unknown section id {id} at offset {offset:#x}.")]
+    SectionId { id: u8, offset: u64 },
+
+    /// A function index past the `count` imported and defined functions; see
+    /// `WasmModule::validate`.
+    #[error("Function index {index} is out of bounds: the module has {count} functions.")]
+    FuncIndexOutOfBounds { index: u32, count: u32 },
+
+    #[error(
+        "Unknown/unsupported WebAssembly opcode {prefix:#04x} {opcode:#x} at offset {offset:#x}"
+    )]
+    PrefixedOpCode {
+        prefix: u8,
+        opcode: u32,
+        offset: u64,
+    },
+
+    /// Says which section, and which entry of it, `source` happened in. `item_index` is `None`
+    /// when the error is not inside an entry, e.g. in the entry count.
+    #[error("{} section{}: {source}", describe_section(.section_id), describe_item(.item_index))]
+    InSection {
+        section_id: u8,
+        item_index: Option<u32>,
+        source: Box<DecodeError>,
+    },
 }
 
-pub fn decode_bytes(bytes: &[u8]) -> Result<WasmModule> {
-    let mut decoder = Decoder::new(Cursor::new(bytes));
-    let mut module = WasmModule::default();
+impl DecodeError {
+    /// The absolute position in the input at which the error was detected, or `None` if the
+    /// input could not be read at all.
+    pub fn offset(&self) -> Option<u64> {
+        use DecodeError::*;
+
+        let offset = match self {
+            // The magic bytes and the version are always at the start of the binary.
+            Binary { .. } => 0,
+            Version { .. } => HEADER_MAGIC_BYTES.len() as u64,
+            DuplicateSection { second_offset, .. } => *second_offset,
+            Io(_) | File { .. } | UsizeOverflow { .. } | FuncIndexOutOfBounds { .. } => {
+                return None
+            }
+            InSection { source, .. } => return source.offset(),
+            Numeric { offset, .. }
+            | TypeSectionBytes { offset, .. }
+            | ElementType { offset, .. }
+            | ValueType { offset, .. }
+            | MutabilityByte { offset, .. }
+            | OpCode { offset, .. }
+            | SectionSize { offset, .. }
+            | SectionOrder { offset, .. }
+            | TrailingBytes { offset, .. }
+            | UnexpectedEof { offset, .. }
+            | InvalidName { offset, .. }
+            | MissingEnd { offset, .. }
+            | LengthOutOfBounds { offset, .. }
+            | UnbalancedControl { offset, .. }
+            | ImportKind { offset, .. }
+            | ExportKind { offset, .. }
+            | NonCanonicalLeb128 { offset }
+            | MalformedCount { offset, .. }
+            | HeapType { offset, .. }
+            | ElementSegmentFlags { offset, .. }
+            | ElementKind { offset, .. }
+            | DataSegmentFlags { offset, .. }
+            | LimitsFlags { offset, .. }
+            | MemoryLimitsFlags { offset, .. }
+            | NonConstantExpr { offset, .. }
+            | LimitExceeded { offset, .. }
+            | SectionId { offset, .. }
+            | SymbolKind { offset, .. }
+            | SubsectionSize { offset, .. }
+            | PrefixedOpCode { offset, .. } => *offset,
+        };
 
-    decoder.read_validate()?;
-    module.version = decoder.read_version()?;
+        Some(offset)
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        use DecodeError::*;
+
+        match self {
+            Io(_) | File { .. } => ErrorKind::Io,
+            InSection { source, .. } => source.kind(),
+            LimitExceeded { .. } | UsizeOverflow { .. } => ErrorKind::LimitExceeded,
+            Version { .. } | OpCode { .. } | PrefixedOpCode { .. } => ErrorKind::UnsupportedFeature,
+            ValueType { .. } | LimitsFlags { .. } | MemoryLimitsFlags { .. }
+                if self.required_feature().is_some() =>
+            {
+                ErrorKind::UnsupportedFeature
+            }
+            Binary { .. }
+            | Numeric { .. }
+            | TypeSectionBytes { .. }
+            | ElementType { .. }
+            | ValueType { .. }
+            | MutabilityByte { .. }
+            | SectionSize { .. }
+            | SectionOrder { .. }
+            | DuplicateSection { .. }
+            | TrailingBytes { .. }
+            | UnexpectedEof { .. }
+            | InvalidName { .. }
+            | MissingEnd { .. }
+            | LengthOutOfBounds { .. }
+            | UnbalancedControl { .. }
+            | ImportKind { .. }
+            | ExportKind { .. }
+            | NonCanonicalLeb128 { .. }
+            | MalformedCount { .. }
+            | HeapType { .. }
+            | ElementSegmentFlags { .. }
+            | ElementKind { .. }
+            | DataSegmentFlags { .. }
+            | LimitsFlags { .. }
+            | MemoryLimitsFlags { .. }
+            | NonConstantExpr { .. }
+            | SectionId { .. }
+            | SymbolKind { .. }
+            | SubsectionSize { .. }
+            | FuncIndexOutOfBounds { .. } => ErrorKind::Malformed,
+        }
+    }
 
-    while !decoder.is_end {
-        let (section_type, section_size) = decoder.decode_section_type()?;
-        let section = decoder.decode_section(section_type, section_size)?;
+    /// The proposal that the input needs support for, if the error is known to come from one,
+    /// e.g. "simd" or "threads".
+    pub fn required_feature(&self) -> Option<&'static str> {
+        use DecodeError::*;
 
-        module.consume(section);
+        match self {
+            InSection { source, .. } => source.required_feature(),
+            Version { found } if WasmVersion::from(*found).kind() == ModuleKind::Component => {
+                Some("component-model")
+            }
+            OpCode { opcode, .. } => match opcode {
+                0x06..=0x0a | 0x18 | 0x19 => Some("exception-handling"),
+                0x12 | 0x13 => Some("tail-call"),
+                0x14 | 0x15 => Some("function-references"),
+                0xfb => Some("gc"),
+                0xfe => Some("threads"),
+                _ => None,
+            },
+            PrefixedOpCode { prefix: 0xfd, .. } => Some("simd"),
+            // Typed references such as `(ref null $t)`.
+            ValueType {
+                value: 0x63 | 0x64, ..
+            } => Some("function-references"),
+            LimitsFlags { flags, .. } if flags & !0x07 != 0 => None,
+            LimitsFlags { flags, .. } if flags & 0x04 != 0 => Some("memory64"),
+            LimitsFlags { flags, .. } if flags & 0x02 != 0 => Some("shared-everything-threads"),
+            MemoryLimitsFlags { flags, .. } if flags & !0x07 != 0 => None,
+            MemoryLimitsFlags { flags, .. } if flags & 0x04 != 0 => Some("memory64"),
+            MemoryLimitsFlags { flags, .. } if flags & 0x02 != 0 => Some("threads"),
+            _ => None,
+        }
     }
 
+    /// The error underneath any `InSection` context, e.g. to match on what actually went wrong.
+    pub fn root_cause(&self) -> &DecodeError {
+        match self {
+            DecodeError::InSection { source, .. } => source.root_cause(),
+            error => error,
+        }
+    }
+}
+
+/// Render bytes as space-separated hex pairs, e.g. `00 61 73 6d`.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Render the mnemonic of an opcode as a ` (name)` suffix for error messages, if it has one.
+fn describe_opcode(opcode: &u8) -> String {
+    opcode_name(*opcode)
+        .map(|name| format!(" ({name})"))
+        .unwrap_or_default()
+}
+
+/// Name a section by its id for error messages.
+fn describe_section(section_id: &u8) -> &'static str {
+    SectionType::try_from(*section_id)
+        .map(SectionType::name)
+        .unwrap_or("unknown")
+}
+
+/// Render the index of an entry as a `, entry N` suffix for error messages, if there is one.
+fn describe_item(item_index: &Option<u32>) -> String {
+    item_index
+        .map(|index| format!(", entry {index}"))
+        .unwrap_or_default()
+}
+
+/// Explain unsupported versions that are recognizably something other than a core module.
+fn describe_version(found: &u32) -> &'static str {
+    if WasmVersion::from(*found).kind() == ModuleKind::Component {
+        " This is a component-model binary, not a core module."
+    } else {
+        ""
+    }
+}
+
+/// Tracks the non-custom sections seen so far. Custom sections may appear anywhere and any
+/// number of times, so they are never tracked.
+#[derive(Default)]
+struct SectionTracker {
+    previous: Option<SectionType>,
+    offsets: HashMap<SectionType, u64>,
+}
+
+impl SectionTracker {
+    fn visit(
+        &mut self,
+        section_type: SectionType,
+        offset: u64,
+        config: &DecoderConfig,
+    ) -> Result<()> {
+        if matches!(section_type, SectionType::Custom | SectionType::Unknown(_)) {
+            return Ok(());
+        }
+
+        if let Some(&first_offset) = self.offsets.get(&section_type) {
+            return Err(DecodeError::DuplicateSection {
+                id: section_type.id(),
+                first_offset,
+                second_offset: offset,
+            });
+        }
+        self.offsets.insert(section_type, offset);
+
+        if let Some(previous) = self.previous {
+            if config.check_section_order
+                && section_type.canonical_order() <= previous.canonical_order()
+            {
+                return Err(DecodeError::SectionOrder {
+                    previous: previous.id(),
+                    current: section_type.id(),
+                    offset,
+                });
+            }
+        }
+        self.previous = Some(section_type);
+
+        Ok(())
+    }
+}
+
+/// Decode a module from a byte slice. The module borrows names and function bodies from
+/// `bytes`; call `WasmModule::into_owned` to keep it around longer than the buffer.
+pub fn decode_bytes(bytes: &[u8]) -> Result<WasmModule<'_>> {
+    decode_bytes_with_config(bytes, &DecoderConfig::default())
+}
+
+pub fn decode_bytes_with_config<'a>(
+    bytes: &'a [u8],
+    config: &DecoderConfig,
+) -> Result<WasmModule<'a>> {
+    let mut module = WasmModule::default();
+    decode_module(bytes, config, &mut module, Err)?;
+
     Ok(module)
 }
+
+/// Decode a module at the start of `bytes` that may be followed by other data, e.g. in a
+/// container format. Rather than failing with `TrailingBytes`, decoding stops at the first
+/// bytes that are not a section, and their offset is returned as the length of the module.
+/// Data that happens to start like a section is decoded as one.
+pub fn decode_bytes_with_len(bytes: &[u8]) -> Result<(WasmModule<'_>, usize)> {
+    let mut module = WasmModule::default();
+    match decode_module(bytes, &DecoderConfig::default(), &mut module, Err) {
+        Ok(_) => Ok((module, bytes.len())),
+        Err(DecodeError::TrailingBytes { offset, .. }) => Ok((module, offset as usize)),
+        Err(error) => Err(error),
+    }
+}
+
+/// Like `decode_bytes_with_config`, but also returns constructs that are valid, yet most likely
+/// a mistake, e.g. integers encoded with more bytes than necessary.
+pub fn decode_bytes_with_warnings<'a>(
+    bytes: &'a [u8],
+    config: &DecoderConfig,
+) -> Result<(WasmModule<'a>, Vec<DecodeWarning>)> {
+    let mut module = WasmModule::default();
+    let warnings = decode_module(bytes, config, &mut module, Err)?;
+
+    Ok((module, warnings))
+}
+
+/// Decode as much of a module as possible. A section that fails to decode is skipped using
+/// its declared size and its error collected, so e.g. a corrupt code section still leaves the
+/// types, imports and exports. Errors in the header or in the framing of the sections stop
+/// decoding; they are the last error returned, and the module holds the sections before them.
+pub fn decode_bytes_lenient(bytes: &[u8]) -> (WasmModule<'_>, Vec<DecodeError>) {
+    decode_bytes_lenient_with_config(bytes, &DecoderConfig::default())
+}
+
+pub fn decode_bytes_lenient_with_config<'a>(
+    bytes: &'a [u8],
+    config: &DecoderConfig,
+) -> (WasmModule<'a>, Vec<DecodeError>) {
+    let mut module = WasmModule::default();
+    let mut errors = Vec::new();
+
+    let result = decode_module(bytes, config, &mut module, |error| {
+        errors.push(error);
+        Ok(())
+    });
+    if let Err(error) = result {
+        errors.push(error);
+    }
+
+    (module, errors)
+}
+
+/// Decode a module section by section without building a `WasmModule`: `on_section` is called
+/// with every section as soon as it is decoded, and decoding stops early once it returns
+/// `Break`. Sections after that are not decoded, so their errors are not reported either.
+///
+/// ```
+/// use std::ops::ControlFlow;
+/// use wasm_thing::types::WasmSection;
+///
+/// let bytes = std::fs::read("tests/fixtures/imports.wasm").unwrap();
+///
+/// let mut import_count = 0;
+/// wasm_thing::decode_with(&bytes, |section| match section {
+///     WasmSection::Import(imports) => {
+///         import_count = imports.items().len();
+///         ControlFlow::Break(())
+///     }
+///     _ => ControlFlow::Continue(()),
+/// })
+/// .unwrap();
+///
+/// assert_eq!(import_count, 2);
+/// ```
+pub fn decode_with<'a, F>(bytes: &'a [u8], on_section: F) -> Result<()>
+where
+    F: FnMut(&WasmSection<'a>) -> ControlFlow<()>,
+{
+    decode_with_config(bytes, &DecoderConfig::default(), on_section)
+}
+
+pub fn decode_with_config<'a, F>(
+    bytes: &'a [u8],
+    config: &DecoderConfig,
+    mut on_section: F,
+) -> Result<()>
+where
+    F: FnMut(&WasmSection<'a>) -> ControlFlow<()>,
+{
+    let mut decoder = Decoder::with_config(Cursor::new(bytes), *config);
+    decode_header(&mut decoder, config)?;
+
+    decode_sections(&mut decoder, config, |_, section| Ok(on_section(&section?)))
+}
+
+/// The function index held by the contents of a start section, which the module does not
+/// keep decoded. The index may be padded, as nothing checked it when the module was decoded.
+pub(crate) fn decode_start_function(contents: &[u8]) -> Result<FuncIndex> {
+    let config = DecoderConfig::new().strict_leb128(false);
+    let mut decoder = Decoder::with_config(Cursor::new(contents), config);
+    Ok(decoder.decode_varuint()?.into())
+}
+
+/// Decode `bytes` into `module`, returning the warnings found along the way. An error inside a
+/// section is passed to `section_error`, which either returns it to stop decoding, or returns
+/// `Ok` to skip to the next section.
+fn decode_module<'a>(
+    bytes: &'a [u8],
+    config: &DecoderConfig,
+    module: &mut WasmModule<'a>,
+    mut section_error: impl FnMut(DecodeError) -> Result<()>,
+) -> Result<Vec<DecodeWarning>> {
+    let mut decoder = Decoder::with_config(Cursor::new(bytes), *config);
+    module.version = decode_header(&mut decoder, config)?;
+
+    decode_sections(&mut decoder, config, |info, section| {
+        module.sections.push(info);
+        match section {
+            Ok(section) => {
+                if info.section_type != SectionType::Custom {
+                    let start = info.offset as usize;
+                    module.raw_sections.push(RawSection::Standard {
+                        section_type: info.section_type,
+                        contents: Cow::Borrowed(&bytes[start..start + info.length as usize]),
+                    });
+                }
+                module.consume(section)
+            }
+            Err(error) => section_error(error)?,
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+
+    let mut warnings = decoder.warnings;
+    warnings.extend(name_clash_warnings(module));
+
+    Ok(warnings)
+}
+
+/// Check the magic bytes and the version, which must be that of a core module.
+fn decode_header(decoder: &mut Decoder, config: &DecoderConfig) -> Result<WasmVersion> {
+    check_limit(
+        "module size",
+        config.max_module_size,
+        decoder.remaining(),
+        0,
+    )?;
+    if !config.skip_magic {
+        decoder.read_validate()?;
+    }
+    let version = decoder.read_version()?;
+
+    // Components share the magic bytes, but their contents are not sections of a core module.
+    let is_component = version.kind() == ModuleKind::Component;
+    if is_component || (config.check_version && u32::from(version) != WASM_VERSION) {
+        return Err(DecodeError::Version {
+            found: version.into(),
+        });
+    }
+
+    Ok(version)
+}
+
+/// Decode the sections following the header, passing each one to `on_section` along with its
+/// location. `on_section` stops decoding by returning `Break` or an error; if it is handed an
+/// error and returns `Continue`, decoding skips to the next section.
+fn decode_sections<'a>(
+    decoder: &mut Decoder<'a>,
+    config: &DecoderConfig,
+    mut on_section: impl FnMut(SectionInfo, Result<WasmSection<'a>>) -> Result<ControlFlow<()>>,
+) -> Result<()> {
+    let mut tracker = SectionTracker::default();
+
+    while !decoder.is_end() {
+        let info = decode_section_header(decoder, &mut tracker, config)?;
+        let section = decoder
+            .decode_section(info.section_type, info.length)
+            .eof_context(|| format!("{} section", info.section_type.name()));
+        let failed = section.is_err();
+
+        if on_section(info, section)?.is_break() {
+            break;
+        }
+        if failed {
+            decoder.set_position(info.offset + info.length as u64);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode the id and size of the next section, leaving the decoder at its contents.
+fn decode_section_header(
+    decoder: &mut Decoder,
+    tracker: &mut SectionTracker,
+    config: &DecoderConfig,
+) -> Result<SectionInfo> {
+    let offset = decoder.position();
+    let remaining = decoder.remaining();
+
+    // Anything that does not start with a known section id is not a section, e.g. padding or
+    // data appended to the module. A known id with a malformed size is a broken section.
+    let SectionHeader { id, size } = match decoder.decode_section_type() {
+        Ok(header) => header,
+        Err(DecodeError::SectionId { .. }) => {
+            return Err(DecodeError::TrailingBytes { offset, remaining })
+        }
+        Err(error) => return Err(error),
+    };
+    tracker.visit(id, offset, config)?;
+
+    Ok(SectionInfo {
+        section_type: id,
+        offset: decoder.position(),
+        length: size,
+    })
+}
+
+/// List the sections of a module without decoding any of their contents, e.g. to index many
+/// files quickly. The header and the framing of the sections are checked as in `decode_bytes`,
+/// but a section with corrupt contents is listed like any other.
+///
+/// ```
+/// use wasm_thing::SectionType;
+///
+/// let bytes = std::fs::read("tests/fixtures/imports.wasm").unwrap();
+/// let sections = wasm_thing::decode_headers(&bytes).unwrap();
+///
+/// assert_eq!(sections, wasm_thing::decode_bytes(&bytes).unwrap().sections());
+/// assert_eq!(sections[0].section_type(), SectionType::Type);
+/// ```
+pub fn decode_headers(bytes: &[u8]) -> Result<Vec<SectionInfo>> {
+    let config = DecoderConfig::default();
+    let mut decoder = Decoder::with_config(Cursor::new(bytes), config);
+    decode_header(&mut decoder, &config)?;
+
+    let mut tracker = SectionTracker::default();
+    let mut sections = Vec::new();
+    while !decoder.is_end() {
+        let info = decode_section_header(&mut decoder, &mut tracker, &config)?;
+
+        let remaining = decoder.remaining();
+        if info.length as u64 > remaining {
+            return Err(decoder.unexpected_eof(info.length as u64 - remaining))
+                .eof_context(|| format!("{} section", info.section_type.name()));
+        }
+        decoder.set_position(info.offset + info.length as u64);
+        sections.push(info);
+    }
+
+    Ok(sections)
+}
+
+/// A warning for every export named like the field of an import.
+fn name_clash_warnings(module: &WasmModule) -> Vec<DecodeWarning> {
+    let export_section = module
+        .sections
+        .iter()
+        .find(|section| section.section_type == SectionType::Export)
+        .map_or(0, |section| section.offset);
+
+    module
+        .exports
+        .iter()
+        .filter(|export| {
+            module
+                .imports
+                .iter()
+                .any(|import| import.field_name == export.name)
+        })
+        .map(|_| DecodeWarning {
+            kind: WarningKind::ImportExportNameClash,
+            offset: export_section,
+        })
+        .collect()
+}
+
+/// Decode a module from a reader into a module that owns all of its data. The whole input is
+/// read into memory first.
+pub fn decode_reader<R: Read>(mut reader: R) -> Result<WasmModule<'static>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    Ok(decode_bytes(&bytes)?.into_owned())
+}
+
+/// Read and decode the module in the file at `path`.
+pub fn decode_file<P: AsRef<Path>>(path: P) -> Result<WasmModule<'static>> {
+    decode_file_with_config(path, &DecoderConfig::default())
+}
+
+/// Like `decode_file`, but a file larger than `config.max_module_size` is refused before it is
+/// read.
+pub fn decode_file_with_config<P: AsRef<Path>>(
+    path: P,
+    config: &DecoderConfig,
+) -> Result<WasmModule<'static>> {
+    let path = path.as_ref();
+    let file_error = |source| DecodeError::File {
+        path: path.to_owned(),
+        source,
+    };
+
+    let mut file = File::open(path).map_err(file_error)?;
+    let size = file.metadata().map_err(file_error)?.len();
+    check_limit("module size", config.max_module_size, size, 0)?;
+
+    let mut bytes = Vec::with_capacity(size as usize);
+    file.read_to_end(&mut bytes).map_err(file_error)?;
+
+    Ok(decode_bytes_with_config(&bytes, config)?.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::ModuleBuilder;
+    use crate::encode_module;
+    use crate::types::{WasmExportDescriptor, WasmImportDescriptor, WasmValueType};
+    use crate::wasm::instructions::{BlockType, Instruction};
+    use std::borrow::Cow;
+
+    /// (module
+    ///   (import "env" "log" (func (param i32)))
+    ///   (func (export "answer") (result i32) i32.const 42))
+    const SAMPLE_MODULE: [u8; 56] = [
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header, version 1
+        0x01, 0x09, 0x02, // Type section (9 bytes), 2 types
+        0x60, 0x01, 0x7f, 0x00, // (i32) -> ()
+        0x60, 0x00, 0x01, 0x7f, // () -> (i32)
+        0x02, 0x0b, 0x01, // Import section (11 bytes), 1 import
+        0x03, 0x65, 0x6e, 0x76, // "env"
+        0x03, 0x6c, 0x6f, 0x67, // "log"
+        0x00, 0x00, // Function of type 0
+        0x03, 0x02, 0x01, 0x01, // Function section (2 bytes), 1 function of type 1
+        0x07, 0x0a, 0x01, // Export section (10 bytes), 1 export
+        0x06, 0x61, 0x6e, 0x73, 0x77, 0x65, 0x72, // "answer"
+        0x00, 0x01, // Function index 1
+        0x0a, 0x06, 0x01, // Code section (6 bytes), 1 body
+        0x04, 0x00, 0x41, 0x2a, 0x0b, // Body (4 bytes): no locals, i32.const 42, end
+    ];
+
+    #[test]
+    fn test_decode_bytes_header_only() {
+        let module = decode_bytes(&SAMPLE_MODULE[..8]).unwrap();
+
+        assert_eq!(module.version.version(), 1);
+        assert!(module.sections().is_empty());
+        assert!(module.types.is_empty());
+        assert!(module.imports.is_empty());
+        assert!(module.functions.is_empty());
+        assert!(module.exports.is_empty());
+        assert!(module.code.is_empty());
+    }
+
+    #[test]
+    fn test_decode_bytes_complete_module() {
+        let module = decode_bytes(&SAMPLE_MODULE).unwrap();
+
+        assert_eq!(module.version.version(), 1);
+
+        assert_eq!(module.types.len(), 2);
+        assert_eq!(module.types[0].params, vec![WasmValueType::I32]);
+        assert!(module.types[0].returns.is_empty());
+        assert!(module.types[1].params.is_empty());
+        assert_eq!(module.types[1].returns, vec![WasmValueType::I32]);
+
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.imports[0].module_name, "env");
+        assert_eq!(module.imports[0].field_name, "log");
+        assert!(matches!(
+            module.imports[0].descriptor,
+            WasmImportDescriptor::Function(index) if u32::from(index) == 0
+        ));
+
+        assert_eq!(module.functions.len(), 1);
+        assert_eq!(u32::from(module.functions[0]), 1);
+
+        assert_eq!(module.exports.len(), 1);
+        assert_eq!(module.exports[0].name, "answer");
+        assert!(matches!(
+            module.exports[0].descriptor,
+            WasmExportDescriptor::Function(index) if u32::from(index) == 1
+        ));
+
+        assert_eq!(module.code.len(), 1);
+        assert!(module.code[0].locals.is_empty());
+        assert_eq!(module.code[0].expression, vec![0x41, 0x2a, 0x0b]);
+    }
+
+    #[test]
+    fn test_decode_bytes_trailing_junk() {
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.extend_from_slice(&[0xde, 0xad, 0xbe]);
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::TrailingBytes {
+                offset: 56,
+                remaining: 3
+            }
+        ));
+
+        // Unless the module is expected to be followed by other data.
+        let (module, len) = decode_bytes_with_len(&bytes).unwrap();
+        assert_eq!(len, SAMPLE_MODULE.len());
+        assert_eq!(module, decode_bytes(&SAMPLE_MODULE).unwrap());
+        assert_eq!(decode_bytes_with_len(&SAMPLE_MODULE).unwrap().1, len);
+
+        // So is an unknown id followed by a size past the end of the input.
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.extend_from_slice(&[0x2a, 0x05, 0x00]);
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::TrailingBytes {
+                offset: 56,
+                remaining: 3
+            }
+        ));
+
+        // And any unknown id when unknown sections are rejected, even with a plausible size.
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.extend_from_slice(&[0x2a, 0x01, 0x00]);
+
+        let config = DecoderConfig::new().reject_unknown_sections(true);
+        let error = decode_bytes_with_config(&bytes, &config).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::TrailingBytes {
+                offset: 56,
+                remaining: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_bytes_malformed_section_size() {
+        // A type section whose size of 1 is padded to two bytes.
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x81, 0x00, 0x00,
+        ];
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::NonCanonicalLeb128 { offset: 9 }
+        ));
+    }
+
+    #[test]
+    fn test_decode_bytes_unknown_section() {
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.extend_from_slice(&[0x2a, 0x03, 0xaa, 0xbb, 0xcc]);
+
+        let module = decode_bytes(&bytes).unwrap();
+        let unknown = module.sections().last().unwrap();
+        assert_eq!(unknown.section_type, SectionType::Unknown(42));
+        assert_eq!(unknown.section_type.id(), 42);
+        assert_eq!(unknown.length, 3);
+        assert_eq!(module.code.len(), 1);
+        assert_eq!(encode_module(&module), bytes);
+
+        let mut sections = Vec::new();
+        decode_with(&bytes, |section| {
+            sections.push(section.clone());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(
+            sections.last().unwrap(),
+            &WasmSection::Unknown {
+                id: 42,
+                data: &[0xaa, 0xbb, 0xcc]
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_bytes_trailing_custom_section() {
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.extend_from_slice(&[0x00, 0x03, 0x02, 0x68, 0x69]); // Custom section "hi"
+
+        assert!(decode_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_decode_bytes_mutations_do_not_panic() {
+        // A deterministic stand-in for the fuzz target: every truncation and a set of
+        // single-byte corruptions of the sample module must decode or error, never panic.
+        for length in 0..SAMPLE_MODULE.len() {
+            let _ = decode_bytes(&SAMPLE_MODULE[..length]);
+        }
+
+        for index in 0..SAMPLE_MODULE.len() {
+            for byte in [0x00, 0x01, 0x7f, 0x80, 0xff] {
+                let mut bytes = SAMPLE_MODULE.to_vec();
+                bytes[index] = byte;
+                let _ = decode_bytes(&bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_bytes_rejects_out_of_order_sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header, version 1
+            0x07, 0x01, 0x00, // Export section, no exports
+            0x03, 0x01, 0x00, // Function section, no functions
+        ];
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::SectionOrder {
+                previous: 7,
+                current: 3,
+                offset: 11
+            }
+        ));
+
+        let config = DecoderConfig::new().check_section_order(false);
+        assert!(decode_bytes_with_config(&bytes, &config).is_ok());
+    }
+
+    #[test]
+    fn test_decode_bytes_allows_interleaved_custom_sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header, version 1
+            0x00, 0x02, 0x01, 0x61, // Custom section "a"
+            0x01, 0x01, 0x00, // Type section, no types
+            0x00, 0x02, 0x01, 0x62, // Custom section "b"
+            0x02, 0x01, 0x00, // Import section, no imports
+            0x00, 0x02, 0x01, 0x63, // Custom section "c"
+        ];
+
+        assert!(decode_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_borrowed_and_owned_decode_agree() {
+        let borrowed = decode_bytes(&SAMPLE_MODULE).unwrap();
+        let owned = decode_reader(&SAMPLE_MODULE[..]).unwrap();
+
+        assert!(matches!(
+            borrowed.imports[0].module_name,
+            Cow::Borrowed("env")
+        ));
+        assert!(matches!(owned.imports[0].module_name, Cow::Owned(_)));
+
+        for module in [borrowed.to_owned(), owned] {
+            assert_eq!(module.version, borrowed.version);
+            assert_eq!(module.types.len(), borrowed.types.len());
+            assert_eq!(
+                module.imports[0].module_name,
+                borrowed.imports[0].module_name
+            );
+            assert_eq!(module.imports[0].field_name, borrowed.imports[0].field_name);
+            assert_eq!(module.exports[0].name, borrowed.exports[0].name);
+            assert_eq!(module.code[0].expression, borrowed.code[0].expression);
+        }
+    }
+
+    #[test]
+    fn test_decode_bytes_rejects_duplicate_sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header, version 1
+            0x05, 0x01, 0x00, // Memory section, no memories
+            0x05, 0x01, 0x00, // Memory section, again
+        ];
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::DuplicateSection {
+                id: 5,
+                first_offset: 8,
+                second_offset: 11
+            }
+        ));
+
+        // Duplicates are malformed regardless of the ordering check.
+        let config = DecoderConfig::new().check_section_order(false);
+        assert!(decode_bytes_with_config(&bytes, &config).is_err());
+    }
+
+    #[test]
+    fn test_decode_bytes_allows_repeated_custom_sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header, version 1
+            0x00, 0x02, 0x01, 0x61, // Custom section "a"
+            0x00, 0x02, 0x01, 0x61, // Custom section "a"
+            0x00, 0x02, 0x01, 0x61, // Custom section "a"
+        ];
+
+        assert!(decode_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_decode_bytes_records_section_locations() {
+        let module = decode_bytes(&SAMPLE_MODULE).unwrap();
+
+        let sections = module.sections();
+        assert_eq!(sections.len(), 5);
+
+        let import = sections[1];
+        assert_eq!(import.section_type, SectionType::Import);
+        assert_eq!(import.offset, 21);
+        assert_eq!(import.length, 11);
+        assert_eq!(
+            SAMPLE_MODULE[import.offset as usize - 2],
+            SectionType::Import.id()
+        );
+    }
+
+    #[test]
+    fn test_decode_bytes_checks_version() {
+        assert!(decode_bytes(&SAMPLE_MODULE[..8]).is_ok());
+
+        let version_2 = [0x00, 0x61, 0x73, 0x6d, 0x02, 0x00, 0x00, 0x00];
+        let error = decode_bytes(&version_2).err().unwrap();
+        assert!(matches!(error, DecodeError::Version { found: 2 }));
+
+        let config = DecoderConfig::new().check_version(false);
+        let module = decode_bytes_with_config(&version_2, &config).unwrap();
+        assert_eq!(module.version.version(), 2);
+    }
+
+    #[test]
+    fn test_decode_bytes_rejects_component() {
+        let component = [0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+
+        let error = decode_bytes(&component).err().unwrap();
+        assert!(matches!(error, DecodeError::Version { found: 0x0001_000d }));
+        assert!(error.to_string().contains("component-model binary"));
+
+        // Not even a relaxed version check lets a component through as a core module.
+        let config = DecoderConfig::new().check_version(false);
+        assert!(decode_bytes_with_config(&component, &config).is_err());
+    }
+
+    #[test]
+    fn test_decode_bytes_huge_section_size() {
+        let mut bytes = SAMPLE_MODULE[..8].to_vec();
+        bytes.extend_from_slice(&[0x01, 0xff, 0xff, 0xff, 0xff, 0x0f]); // Type section, 4 GiB
+        bytes.resize(30, 0x00);
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::LimitExceeded);
+
+        // Without the limit, the size is checked against the rest of the input.
+        let unlimited = DecoderConfig::new().max_section_size(u32::MAX);
+        let error = decode_bytes_with_config(&bytes, &unlimited).err().unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::UnexpectedEof { .. }
+        ));
+    }
+
+    #[test]
+    fn test_decode_bytes_huge_item_count() {
+        // The default limits reject the counts outright. Without them, the type count is
+        // checked against the size of the section, and decoding the code section must still
+        // run out of input rather than reserve space for every item.
+        let unlimited = DecoderConfig::new()
+            .max_types(u32::MAX)
+            .max_functions(u32::MAX);
+
+        for section_id in [0x01, 0x0a] {
+            let mut bytes = SAMPLE_MODULE[..8].to_vec();
+            bytes.extend_from_slice(&[section_id, 0x05, 0xff, 0xff, 0xff, 0xff, 0x0f]); // 2^32 - 1 items
+
+            let error = decode_bytes(&bytes).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::LimitExceeded);
+
+            let error = decode_bytes_with_config(&bytes, &unlimited).err().unwrap();
+            if section_id == 0x01 {
+                assert!(matches!(
+                    error.root_cause(),
+                    DecodeError::MalformedCount {
+                        declared: u32::MAX,
+                        remaining: 0,
+                        offset: 10
+                    }
+                ));
+            } else {
+                assert!(matches!(
+                    error.root_cause(),
+                    DecodeError::UnexpectedEof { .. }
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_bytes_truncated_section_header() {
+        // The input ends right after the id byte of the import section.
+        let error = decode_bytes(&SAMPLE_MODULE[..20]).err().unwrap();
+        match &error {
+            DecodeError::UnexpectedEof {
+                offset: 20,
+                needed: 1,
+                context,
+            } => assert_eq!(context, "section header"),
+            _ => panic!("Expected UnexpectedEof, got {error}"),
+        }
+    }
+
+    #[test]
+    fn test_decode_bytes_truncated_offsets() {
+        // Inside the header, inside the type section, inside the import section, inside the
+        // code section.
+        for length in [3, 6, 12, 25, 45, 55] {
+            let error = decode_bytes(&SAMPLE_MODULE[..length]).err().unwrap();
+            match error {
+                DecodeError::UnexpectedEof { offset, .. } => assert_eq!(offset, length as u64),
+                _ => panic!("Expected UnexpectedEof at {length}, got {error}"),
+            }
+        }
+    }
+
+    /// A type section with `() -> ()`, then three imports of `env`, `a`; the field name of the
+    /// third (at offset 38) is `0xff`.
+    const INVALID_IMPORT_NAME_MODULE: [u8; 41] = [
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+        0x02, 0x19, 0x03, // import section, 3 entries
+        0x03, 0x65, 0x6e, 0x76, 0x01, 0x61, 0x00, 0x00, // import 0
+        0x03, 0x65, 0x6e, 0x76, 0x01, 0x61, 0x00, 0x00, // import 1
+        0x03, 0x65, 0x6e, 0x76, 0x01, 0xff, 0x00, 0x00, // import 2
+    ];
+
+    #[test]
+    fn test_decode_bytes_invalid_import_name() {
+        let error = decode_bytes(&INVALID_IMPORT_NAME_MODULE).err().unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::InvalidName {
+                section: "import",
+                item_index: 2,
+                offset: 38,
+                ..
+            }
+        ));
+        assert!(matches!(
+            error,
+            DecodeError::InSection {
+                section_id: 2,
+                item_index: Some(2),
+                ..
+            }
+        ));
+        assert!(error.to_string().starts_with("import section, entry 2: "));
+        assert_eq!(error.offset(), Some(38));
+    }
+
+    #[test]
+    fn test_decode_bytes_import_error_offset() {
+        // The kind of import 1 is at offset 31 of the module, but 15 of the section contents.
+        let mut bytes = INVALID_IMPORT_NAME_MODULE;
+        bytes[31] = 0x7f;
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::ImportKind {
+                kind: 0x7f,
+                offset: 31
+            }
+        ));
+        assert_eq!(error.offset(), Some(31));
+    }
+
+    #[test]
+    fn test_decode_bytes_lossy_names() {
+        let config = DecoderConfig::new().lossy_names(true);
+        let module = decode_bytes_with_config(&INVALID_IMPORT_NAME_MODULE, &config).unwrap();
+
+        assert_eq!(module.imports.len(), 3);
+        assert_eq!(module.imports[2].field_name, "\u{fffd}");
+    }
+
+    #[test]
+    fn test_decode_bytes_invalid_custom_section_name() {
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.extend_from_slice(&[0x00, 0x03, 0x02, 0xc3, 0x28]);
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::InvalidName {
+                section: "custom",
+                item_index: 0,
+                offset: 59,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let messages = [
+            (
+                DecodeError::Binary {
+                    expected: HEADER_MAGIC_BYTES,
+                    found: [0xde, 0xad, 0xbe, 0xef],
+                },
+                "Not a WebAssembly binary: expected 00 61 73 6d, found de ad be ef.",
+            ),
+            (
+                DecodeError::TypeSectionBytes {
+                    found: 0x5f,
+                    offset: 0x0b,
+                },
+                "The WebAssembly binary contains a type section with invalid magic byte 0x5f at offset 0xb, expected 0x60.",
+            ),
+            (
+                DecodeError::Numeric {
+                    current_value: 0x0fff_ffff,
+                    invalid_byte: 0x7f,
+                    offset: 0x20,
+                },
+                "An invalid numerical value was found at offset 0x20 while decoding the WebAssembly binary (value 0xfffffff, byte 0x7f).",
+            ),
+            (
+                DecodeError::ElementType {
+                    invalid_byte: 0x7f,
+                    offset: 0x20,
+                },
+                "The WebAssembly binary contains a table type with an invalid element type 0x7f at offset 0x20.",
+            ),
+            (
+                DecodeError::MutabilityByte {
+                    invalid_byte: 0x02,
+                    offset: 0x20,
+                },
+                "The WebAssembly binary contains a global type with an invalid mutability byte 0x02 at offset 0x20.",
+            ),
+            (
+                DecodeError::OpCode {
+                    opcode: 0x06,
+                    offset: 0x20,
+                },
+                "Unknown/unsupported WebAssembly opcode 0x06 at offset 0x20",
+            ),
+        ];
+
+        for (error, message) in messages {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        use ErrorKind::*;
+
+        let invalid_utf8 = vec![0xff];
+        let utf8_error = std::str::from_utf8(&invalid_utf8).unwrap_err();
+        let errors = [
+            (
+                DecodeError::Binary {
+                    expected: HEADER_MAGIC_BYTES,
+                    found: [0; 4],
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::Numeric {
+                    current_value: 0,
+                    invalid_byte: 0x80,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::TypeSectionBytes {
+                    found: 0x5f,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::ElementType {
+                    invalid_byte: 0x7f,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::ValueType {
+                    value: 0x40,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::ValueType {
+                    value: 0x63,
+                    offset: 0,
+                },
+                UnsupportedFeature,
+                Some("function-references"),
+            ),
+            (
+                DecodeError::MutabilityByte {
+                    invalid_byte: 0x02,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::OpCode {
+                    opcode: 0xfe,
+                    offset: 0,
+                },
+                UnsupportedFeature,
+                Some("threads"),
+            ),
+            (
+                DecodeError::OpCode {
+                    opcode: 0xff,
+                    offset: 0,
+                },
+                UnsupportedFeature,
+                None,
+            ),
+            (
+                DecodeError::SectionSize {
+                    section_id: 1,
+                    declared: 1,
+                    consumed: 2,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::SectionOrder {
+                    previous: 2,
+                    current: 1,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::DuplicateSection {
+                    id: 1,
+                    first_offset: 0,
+                    second_offset: 1,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::TrailingBytes {
+                    offset: 0,
+                    remaining: 1,
+                },
+                Malformed,
+                None,
+            ),
+            (DecodeError::Version { found: 2 }, UnsupportedFeature, None),
+            (
+                DecodeError::Version { found: 0x0001_000d },
+                UnsupportedFeature,
+                Some("component-model"),
+            ),
+            (
+                DecodeError::UnexpectedEof {
+                    offset: 0,
+                    needed: 1,
+                    context: String::new(),
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::InvalidName {
+                    section: "import",
+                    item_index: 0,
+                    offset: 0,
+                    source: utf8_error,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::MissingEnd {
+                    func_index: 0,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::LengthOutOfBounds {
+                    field: "code body",
+                    length: 2,
+                    remaining: 1,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::UnbalancedControl {
+                    func_index: 0,
+                    instruction: "end",
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::ImportKind { kind: 4, offset: 0 },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::ExportKind { kind: 4, offset: 0 },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::NonCanonicalLeb128 { offset: 0 },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::MalformedCount {
+                    declared: 2,
+                    remaining: 3,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::HeapType {
+                    value: -0x41,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::ElementSegmentFlags {
+                    flags: 8,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::ElementKind {
+                    kind: 0x70,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::LimitsFlags {
+                    flags: 0x05,
+                    offset: 0,
+                },
+                UnsupportedFeature,
+                Some("memory64"),
+            ),
+            (
+                DecodeError::LimitsFlags {
+                    flags: 0x02,
+                    offset: 0,
+                },
+                UnsupportedFeature,
+                Some("shared-everything-threads"),
+            ),
+            (
+                DecodeError::LimitsFlags {
+                    flags: 0x08,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::DataSegmentFlags {
+                    flags: 3,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::NonConstantExpr {
+                    opcode: 0x20,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::Io(std::io::Error::other("disconnected")),
+                Io,
+                None,
+            ),
+            (
+                DecodeError::File {
+                    path: PathBuf::from("module.wasm"),
+                    source: std::io::Error::other("disconnected"),
+                },
+                Io,
+                None,
+            ),
+            (
+                DecodeError::SectionId { id: 42, offset: 0 },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::SymbolKind {
+                    kind: 0x09,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::SubsectionSize {
+                    id: 8,
+                    declared: 1,
+                    consumed: 2,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::MemoryLimitsFlags {
+                    flags: 0x03,
+                    offset: 0,
+                },
+                UnsupportedFeature,
+                Some("threads"),
+            ),
+            (
+                DecodeError::MemoryLimitsFlags {
+                    flags: 0x08,
+                    offset: 0,
+                },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::UsizeOverflow { value: u32::MAX },
+                LimitExceeded,
+                None,
+            ),
+            (
+                DecodeError::FuncIndexOutOfBounds { index: 3, count: 3 },
+                Malformed,
+                None,
+            ),
+            (
+                DecodeError::PrefixedOpCode {
+                    prefix: 0xfd,
+                    opcode: 0x1000,
+                    offset: 0,
+                },
+                UnsupportedFeature,
+                Some("simd"),
+            ),
+            (
+                DecodeError::InSection {
+                    section_id: 10,
+                    item_index: Some(0),
+                    source: Box::new(DecodeError::PrefixedOpCode {
+                        prefix: 0xfd,
+                        opcode: 0x1000,
+                        offset: 0,
+                    }),
+                },
+                UnsupportedFeature,
+                Some("simd"),
+            ),
+        ];
+
+        for (error, kind, feature) in errors {
+            assert_eq!(error.kind(), kind, "{error}");
+            assert_eq!(error.required_feature(), feature, "{error}");
+        }
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disconnected"))
+        }
+    }
+
+    #[test]
+    fn test_decode_reader_io_error() {
+        let error = decode_reader(FailingReader).err().unwrap();
+        assert!(matches!(error, DecodeError::Io(_)));
+        assert_eq!(error.offset(), None);
+    }
+
+    #[test]
+    fn test_decode_file() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/add.wasm");
+        let module = decode_file(path).unwrap();
+        assert_eq!(module, decode_bytes(&std::fs::read(path).unwrap()).unwrap());
+
+        let config = DecoderConfig::new().max_module_size(16);
+        let error = decode_file_with_config(path, &config).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::LimitExceeded {
+                what: "module size",
+                limit: 16,
+                ..
+            }
+        ));
+
+        let error = decode_file("does/not/exist.wasm").err().unwrap();
+        assert!(
+            matches!(error, DecodeError::File { ref path, .. } if path == Path::new("does/not/exist.wasm"))
+        );
+        assert_eq!(error.kind(), ErrorKind::Io);
+        assert!(error.to_string().contains("does/not/exist.wasm"));
+    }
+
+    #[test]
+    fn test_decode_with_stops_at_break() {
+        // The export section is broken, but never decoded.
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes[46] = 0x05;
+
+        let mut seen = Vec::new();
+        decode_with(&bytes, |section| {
+            seen.push(section.clone());
+            ControlFlow::Break(())
+        })
+        .unwrap();
+
+        match &seen[..] {
+            [WasmSection::Type(types)] => assert_eq!(types.items().len(), 2),
+            other => panic!("unexpected sections {other:?}"),
+        }
+
+        let mut count = 0;
+        let error = decode_with(&bytes, |_| {
+            count += 1;
+            ControlFlow::Continue(())
+        })
+        .err()
+        .unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::ExportKind { kind: 0x05, .. }
+        ));
+        assert_eq!(count, 3);
+    }
+
+    /// `SAMPLE_MODULE` with a global section after the function section, holding one global
+    /// `(global i32 (i32.const 7))` whose mutability byte is at offset 40.
+    fn sample_module_with_global() -> Vec<u8> {
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.splice(36..36, [0x06, 0x06, 0x01, 0x7f, 0x00, 0x41, 0x07, 0x0b]);
+        bytes
+    }
+
+    #[test]
+    fn test_decode_bytes_lenient() {
+        let mut bytes = sample_module_with_global();
+        assert!(decode_bytes(&bytes).is_ok());
+
+        bytes[40] = 0x02;
+        assert!(decode_bytes(&bytes).is_err());
+
+        let (module, errors) = decode_bytes_lenient(&bytes);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            DecodeError::InSection {
+                section_id: 6,
+                item_index: Some(0),
+                ..
+            }
+        ));
+        assert!(matches!(
+            errors[0].root_cause(),
+            DecodeError::MutabilityByte {
+                invalid_byte: 0x02,
+                offset: 40
+            }
+        ));
+
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.exports.len(), 1);
+        assert_eq!(module.code.len(), 1);
+        assert_eq!(module.sections().len(), 6);
+    }
+
+    #[test]
+    fn test_decode_bytes_lenient_stops_at_framing_errors() {
+        let mut bytes = SAMPLE_MODULE.to_vec();
+        bytes.extend_from_slice(&[0xde, 0xad, 0xbe]);
+
+        let (module, errors) = decode_bytes_lenient(&bytes);
+        assert!(matches!(
+            errors[..],
+            [DecodeError::TrailingBytes { offset: 56, .. }]
+        ));
+        assert_eq!(module.sections().len(), 5);
+        assert_eq!(module.code.len(), 1);
+    }
+
+    #[test]
+    fn test_decode_bytes_limits() {
+        // Two empty passive data segments.
+        let mut bytes = sample_module_with_global();
+        bytes.extend_from_slice(&[0x0b, 0x05, 0x02, 0x01, 0x00, 0x01, 0x00]);
+        assert!(decode_bytes(&bytes).is_ok());
+
+        let limits = [
+            (DecoderConfig::new().max_module_size(63), "module size", 0),
+            (DecoderConfig::new().max_types(1), "types", 10),
+            (DecoderConfig::new().max_imports(0), "imports", 21),
+            (DecoderConfig::new().max_functions(0), "functions", 34),
+            (DecoderConfig::new().max_globals(0), "globals", 38),
+            (DecoderConfig::new().max_exports(0), "exports", 46),
+            (DecoderConfig::new().max_body_size(3), "code body size", 59),
+            (
+                DecoderConfig::new().max_data_segments(1),
+                "data segments",
+                66,
+            ),
+            (DecoderConfig::new().max_name_length(2), "name length", 22),
+            (DecoderConfig::new().max_section_size(8), "section size", 9),
+            (DecoderConfig::new().max_vector_len(0), "vector length", 12),
+        ];
+
+        for (config, expected_what, expected_offset) in limits {
+            let error = decode_bytes_with_config(&bytes, &config).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::LimitExceeded);
+            match error.root_cause() {
+                DecodeError::LimitExceeded { what, offset, .. } => {
+                    assert_eq!(*what, expected_what);
+                    assert_eq!(*offset, expected_offset, "{error}");
+                }
+                _ => panic!("Expected LimitExceeded for {expected_what}, got {error}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_bytes_section_size_limit() {
+        let bytes = (0..10_000)
+            .fold(ModuleBuilder::new(), |builder, _| {
+                builder.add_type(&[], &[])
+            })
+            .build();
+        assert_eq!(decode_bytes(&bytes).unwrap().types.len(), 10_000);
+
+        // The section is rejected from its header, before any of its 10,000 types are decoded.
+        let config = DecoderConfig::new().max_section_size(4096);
+        let error = decode_bytes_with_config(&bytes, &config).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::LimitExceeded {
+                what: "section size",
+                limit: 4096,
+                found: 30_002,
+                offset: 9,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_headers() {
+        let headers = decode_headers(&SAMPLE_MODULE).unwrap();
+        let sections: Vec<_> = headers
+            .iter()
+            .map(|info| (info.section_type, info.offset, info.length))
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                (SectionType::Type, 10, 9),
+                (SectionType::Import, 21, 11),
+                (SectionType::Function, 34, 2),
+                (SectionType::Export, 38, 10),
+                (SectionType::Code, 50, 6),
+            ]
+        );
+
+        // The contents are skipped, so a corrupt body goes unnoticed.
+        let mut corrupt = SAMPLE_MODULE;
+        corrupt[54] = 0xff;
+        assert!(decode_bytes(&corrupt).is_err());
+        assert_eq!(decode_headers(&corrupt).unwrap(), headers);
+
+        // A section running past the end of the input does not.
+        let error = decode_headers(&SAMPLE_MODULE[..55]).err().unwrap();
+        assert!(matches!(
+            error,
+            DecodeError::UnexpectedEof {
+                offset: 55,
+                needed: 1,
+                ref context,
+            } if context == "code section"
+        ));
+    }
+
+    #[test]
+    fn test_decode_bytes_skip_magic() {
+        let headerless = &SAMPLE_MODULE[4..];
+        assert!(matches!(
+            decode_bytes(headerless).err().unwrap(),
+            DecodeError::Binary { .. }
+        ));
+
+        let config = DecoderConfig::new().skip_magic(true);
+        let module = decode_bytes_with_config(headerless, &config).unwrap();
+        assert_eq!(module.version.version(), 1);
+        assert_eq!(module.imports[0].field_name, "log");
+        assert_eq!(module.sections()[1].offset, 17);
+
+        // The rest of the module is still validated.
+        let mut corrupt = headerless.to_vec();
+        corrupt[7] = 0x5f;
+        let error = decode_bytes_with_config(&corrupt, &config).err().unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::TypeSectionBytes {
+                found: 0x5f,
+                offset: 7
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_bytes_with_warnings() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x05, 0x81, 0x00, 0x60, 0x00, 0x00, // type section, count 1 in two bytes
+            0x02, 0x09, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x01, 0x66, 0x00, 0x00, // import env.f
+            0x07, 0x05, 0x01, 0x01, 0x66, 0x00, 0x00, // export "f"
+            0x00, 0x02, 0x01, 0x61, // custom section "a", without contents
+        ];
+
+        let relaxed = DecoderConfig::new().strict_leb128(false);
+        let (module, warnings) = decode_bytes_with_warnings(&bytes, &relaxed).unwrap();
+        assert_eq!(module.exports.len(), 1);
+
+        let warnings: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.kind(), warning.offset()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (WarningKind::NonMinimalLeb128, 10),
+                (WarningKind::EmptyCustomSection, 35),
+                (WarningKind::ImportExportNameClash, 28),
+            ]
+        );
+
+        let (_, warnings) =
+            decode_bytes_with_warnings(&SAMPLE_MODULE, &DecoderConfig::default()).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_decode_bytes_strict_leb128() {
+        let mut bytes = SAMPLE_MODULE[..8].to_vec();
+        bytes.extend_from_slice(&[0x01, 0x02, 0x80, 0x00]); // type section, count 0 in two bytes
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::NonCanonicalLeb128 { offset: 10 }
+        ));
+
+        let relaxed = DecoderConfig::new().strict_leb128(false);
+        let module = decode_bytes_with_config(&bytes, &relaxed).unwrap();
+        assert!(module.types.is_empty());
+    }
+
+    #[test]
+    fn test_decode_bytes_multi_value_block() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[WasmValueType::I32, WasmValueType::I32])
+            .add_type(&[], &[])
+            .add_function(
+                1,
+                &[],
+                &[
+                    0x02, 0x00, // block (type 0)
+                    0x41, 0x01, 0x41, 0x02, // i32.const 1, i32.const 2
+                    0x0b, 0x1a, 0x1a, 0x0b, // end, drop, drop, end
+                ],
+            )
+            .build();
+
+        let module = decode_bytes(&bytes).unwrap();
+        let expression = &module.code[0].expression;
+        let block = Decoder::new(Cursor::new(&expression[..]))
+            .decode_instruction()
+            .unwrap();
+        assert_eq!(block, Instruction::Block(BlockType::TypeIndex(0.into())));
+
+        let signature = module
+            .block_signature(BlockType::TypeIndex(0.into()))
+            .unwrap();
+        assert!(signature.params().is_empty());
+        assert_eq!(
+            signature.returns(),
+            [WasmValueType::I32, WasmValueType::I32]
+        );
+        assert!(module
+            .block_signature(BlockType::TypeIndex(2.into()))
+            .is_none());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::decode::decoder::Decoder;
+use crate::decode::{DecodeError, Result};
+use crate::encode::encode_varuint;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// Id of the function names subsection.
+const FUNCTION_NAMES: u8 = 1;
+
+/// Id of the local names subsection.
+const LOCAL_NAMES: u8 = 2;
+
+/// Id of the label names subsection, from the extended name section proposal.
+const LABEL_NAMES: u8 = 3;
+
+/// Copy the contents of a `name` custom section, after its name, renumbering the function
+/// index of every entry in the subsections keyed by function: the names of functions, and of
+/// their locals and labels. Entries for which `func_index` returns `None` are dropped. Other
+/// subsections are copied unchanged.
+///
+/// Layout:
+/// (1) subsections, up to the end of the section
+///     - (2) id (byte)
+///     - (3) size (varuint)
+///     - (4) contents; for the subsections keyed by function, a vector of
+///         - (5) function index (varuint)
+///         - (6) for function names: the name; for local and label names: a vector of
+///           indices (varuint) and names
+pub(crate) fn remap_name_section(
+    data: &[u8],
+    func_index: impl Fn(u32) -> Option<u32>,
+) -> Result<Vec<u8>> {
+    let mut decoder = Decoder::new(Cursor::new(data));
+    let mut out = Vec::with_capacity(data.len());
+
+    while !decoder.is_end() {
+        let id = decoder.read_byte()?;
+        let size: u32 = decoder.decode_varuint()?.into();
+        let offset = decoder.offset();
+        let mut subsection = decoder.slice_decoder(size)?;
+
+        let contents = match id {
+            FUNCTION_NAMES | LOCAL_NAMES | LABEL_NAMES => {
+                let count: u32 = subsection.decode_varuint()?.into();
+                let mut entries = Vec::new();
+                let mut kept = 0;
+                for item_index in 0..count {
+                    let index: u32 = subsection.decode_varuint()?.into();
+                    let start = subsection.position();
+                    if id == FUNCTION_NAMES {
+                        subsection.read_name("name", "function name", item_index)?;
+                    } else {
+                        let names: u32 = subsection.decode_varuint()?.into();
+                        for name_index in 0..names {
+                            subsection.decode_varuint()?;
+                            subsection.read_name("name", "local name", name_index)?;
+                        }
+                    }
+                    let end = subsection.position();
+
+                    if let Some(index) = func_index(index) {
+                        subsection.set_position(start);
+                        encode_varuint(index, &mut entries);
+                        entries.extend_from_slice(subsection.read_slice((end - start) as u32)?);
+                        kept += 1;
+                    }
+                }
+
+                let mut contents = Vec::with_capacity(entries.len() + 5);
+                encode_varuint(kept, &mut contents);
+                contents.extend_from_slice(&entries);
+                contents
+            }
+            _ => subsection.read_slice(size)?.to_vec(),
+        };
+
+        if !subsection.is_end() {
+            return Err(DecodeError::SubsectionSize {
+                id,
+                declared: size,
+                consumed: subsection.position(),
+                offset,
+            });
+        }
+        out.push(id);
+        encode_varuint(contents.len() as u32, &mut out);
+        out.extend_from_slice(&contents);
+    }
+
+    Ok(out)
+}
+
+/// The names of the function names subsection of a `name` custom section, after its name, by
+/// function index. A section without that subsection names no functions; the other
+/// subsections are skipped.
+pub(crate) fn decode_function_names(data: &[u8]) -> Result<HashMap<u32, &str>> {
+    let mut decoder = Decoder::new(Cursor::new(data));
+    let mut names = HashMap::new();
+
+    while !decoder.is_end() {
+        let id = decoder.read_byte()?;
+        let size: u32 = decoder.decode_varuint()?.into();
+        let offset = decoder.offset();
+        let mut subsection = decoder.slice_decoder(size)?;
+        if id != FUNCTION_NAMES {
+            continue;
+        }
+
+        let count: u32 = subsection.decode_varuint()?.into();
+        for item_index in 0..count {
+            let index: u32 = subsection.decode_varuint()?.into();
+            // Names are only copied when decoding with `DecoderConfig::lossy_names`.
+            if let Cow::Borrowed(name) =
+                subsection.read_name("name", "function name", item_index)?
+            {
+                names.insert(index, name);
+            }
+        }
+        if !subsection.is_end() {
+            return Err(DecodeError::SubsectionSize {
+                id,
+                declared: size,
+                consumed: subsection.position(),
+                offset,
+            });
+        }
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_function_names, remap_name_section};
+    use crate::decode::DecodeError;
+
+    #[test]
+    fn test_remap_name_section() {
+        let data = [
+            0x00, 0x02, 0x01, b'm', // Module name "m"
+            0x01, 0x0a, 0x03, // Function names, 10 bytes, 3 entries
+            0x00, 0x01, b'a', // 0: "a"
+            0x01, 0x01, b'b', // 1: "b"
+            0x02, 0x01, b'c', // 2: "c"
+            0x02, 0x08, 0x02, // Local names, 8 bytes, 2 entries
+            0x01, 0x00, // 1: no locals
+            0x02, 0x01, 0x00, 0x01, b'x', // 2: local 0 is "x"
+        ];
+
+        // Function 1 is removed, so function 2 becomes function 1.
+        let remapped = remap_name_section(&data, |index| match index {
+            0 => Some(0),
+            1 => None,
+            index => Some(index - 1),
+        })
+        .unwrap();
+        assert_eq!(
+            remapped,
+            [
+                0x00, 0x02, 0x01, b'm', // Unchanged
+                0x01, 0x07, 0x02, 0x00, 0x01, b'a', 0x01, 0x01, b'c', // 0: "a", 1: "c"
+                0x02, 0x06, 0x01, 0x01, 0x01, 0x00, 0x01, b'x', // 1: local 0 is "x"
+            ]
+        );
+
+        let names = decode_function_names(&data).unwrap();
+        assert_eq!(names.len(), 3);
+        assert_eq!((names[&0], names[&1], names[&2]), ("a", "b", "c"));
+
+        // Renumbering nothing changes nothing.
+        assert_eq!(remap_name_section(&data, Some).unwrap(), data);
+
+        let error = remap_name_section(&[0x01, 0x02, 0x00, 0x00], Some).unwrap_err();
+        assert!(matches!(
+            error,
+            DecodeError::SubsectionSize {
+                id: 1,
+                declared: 2,
+                consumed: 1,
+                ..
+            }
+        ));
+    }
+}
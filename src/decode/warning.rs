@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// Constructs that are valid, but most likely a mistake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// An integer encoded with more bytes than it needs, e.g. `0x80 0x00` for 0. Only reported
+    /// when `DecoderConfig::strict_leb128` is disabled; otherwise it is an error.
+    NonMinimalLeb128,
+    /// A custom section with a name but no contents.
+    EmptyCustomSection,
+    /// An export with the same name as the field name of an import.
+    ImportExportNameClash,
+}
+
+impl WarningKind {
+    fn message(self) -> &'static str {
+        match self {
+            WarningKind::NonMinimalLeb128 => "integer encoded with more bytes than necessary",
+            WarningKind::EmptyCustomSection => "custom section without contents",
+            WarningKind::ImportExportNameClash => "export named like an imported field",
+        }
+    }
+}
+
+/// Something suspicious found while decoding; see `decode_bytes_with_warnings`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecodeWarning {
+    pub(crate) kind: WarningKind,
+    pub(crate) offset: u64,
+}
+
+impl DecodeWarning {
+    pub fn kind(&self) -> WarningKind {
+        self.kind
+    }
+
+    /// The absolute position in the input of the suspicious construct. For a name clash, it is
+    /// the start of the export section.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {:#x}", self.kind.message(), self.offset)
+    }
+}
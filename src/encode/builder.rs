@@ -0,0 +1,255 @@
+use crate::decode::WASM_VERSION;
+use crate::encode::{
+    encode_element_section, encode_import_descriptor, encode_memory_type, encode_name,
+    encode_preamble, encode_section, encode_table_type, encode_type_section, encode_value_type,
+    encode_varuint, encode_vec,
+};
+use crate::types::{
+    ElementSegment, MemoryType, SectionType, TableType, WasmExportDescriptor, WasmFunctionType,
+    WasmImportDescriptor, WasmLimits, WasmLocals, WasmValueType,
+};
+
+/// An import of a function whose signature is type `type_index`, for `ModuleBuilder::add_import`.
+pub(crate) fn func_type(type_index: u32) -> WasmImportDescriptor {
+    WasmImportDescriptor::Function(type_index.into())
+}
+
+/// An import of a memory of at least `min` pages, for `ModuleBuilder::add_import`.
+pub(crate) fn memory_type(min: u32) -> WasmImportDescriptor {
+    WasmImportDescriptor::Memory(MemoryType::new(WasmLimits::new(min.into(), None)))
+}
+
+/// Assembles the bytes of a module section by section, so tests do not have to spell out
+/// sizes and counts by hand:
+///
+/// ```ignore
+/// let bytes = ModuleBuilder::new()
+///     .add_type(&[WasmValueType::I32], &[])
+///     .add_import("env", "log", func_type(0))
+///     .build();
+/// ```
+///
+/// Sections are emitted in the canonical order, and empty ones are left out.
+#[derive(Default)]
+pub(crate) struct ModuleBuilder {
+    types: Vec<WasmFunctionType>,
+    imports: Vec<(String, String, WasmImportDescriptor)>,
+    functions: Vec<u32>,
+    tables: Vec<TableType>,
+    memories: Vec<MemoryType>,
+    exports: Vec<(String, WasmExportDescriptor)>,
+    start: Option<u32>,
+    elements: Vec<ElementSegment>,
+    bodies: Vec<(Vec<WasmLocals>, Vec<u8>)>,
+}
+
+impl ModuleBuilder {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn add_type(mut self, params: &[WasmValueType], returns: &[WasmValueType]) -> Self {
+        self.types.push(WasmFunctionType {
+            params: params.to_vec(),
+            returns: returns.to_vec(),
+        });
+        self
+    }
+
+    pub(crate) fn add_import(
+        mut self,
+        module_name: &str,
+        field_name: &str,
+        descriptor: WasmImportDescriptor,
+    ) -> Self {
+        self.imports
+            .push((module_name.into(), field_name.into(), descriptor));
+        self
+    }
+
+    /// Add a function of type `type_index`. `expression` is the encoded body, including the
+    /// final `end`.
+    pub(crate) fn add_function(
+        mut self,
+        type_index: u32,
+        locals: &[WasmLocals],
+        expression: &[u8],
+    ) -> Self {
+        self.functions.push(type_index);
+        self.bodies.push((locals.to_vec(), expression.to_vec()));
+        self
+    }
+
+    pub(crate) fn add_table(mut self, table_type: TableType) -> Self {
+        self.tables.push(table_type);
+        self
+    }
+
+    /// Add a memory of at least `min` pages.
+    pub(crate) fn add_memory(mut self, min: u32) -> Self {
+        self.memories
+            .push(MemoryType::new(WasmLimits::new(min.into(), None)));
+        self
+    }
+
+    pub(crate) fn add_export(mut self, name: &str, descriptor: WasmExportDescriptor) -> Self {
+        self.exports.push((name.into(), descriptor));
+        self
+    }
+
+    pub(crate) fn set_start(mut self, function_index: u32) -> Self {
+        self.start = Some(function_index);
+        self
+    }
+
+    pub(crate) fn add_element(mut self, segment: ElementSegment) -> Self {
+        self.elements.push(segment);
+        self
+    }
+
+    pub(crate) fn build(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_preamble(WASM_VERSION.into(), &mut out);
+
+        if !self.types.is_empty() {
+            let mut contents = Vec::new();
+            encode_type_section(&self.types, &mut contents);
+            encode_section(SectionType::Type, &contents, &mut out);
+        }
+
+        if !self.imports.is_empty() {
+            let mut contents = Vec::new();
+            encode_vec(
+                &self.imports,
+                &mut contents,
+                |(module_name, field_name, descriptor), out| {
+                    encode_name(module_name, out);
+                    encode_name(field_name, out);
+                    encode_import_descriptor(descriptor, out);
+                },
+            );
+            encode_section(SectionType::Import, &contents, &mut out);
+        }
+
+        if !self.functions.is_empty() {
+            let mut contents = Vec::new();
+            encode_vec(&self.functions, &mut contents, |type_index, out| {
+                encode_varuint(*type_index, out)
+            });
+            encode_section(SectionType::Function, &contents, &mut out);
+        }
+
+        if !self.tables.is_empty() {
+            let mut contents = Vec::new();
+            encode_vec(&self.tables, &mut contents, encode_table_type);
+            encode_section(SectionType::Table, &contents, &mut out);
+        }
+
+        if !self.memories.is_empty() {
+            let mut contents = Vec::new();
+            encode_vec(&self.memories, &mut contents, encode_memory_type);
+            encode_section(SectionType::Memory, &contents, &mut out);
+        }
+
+        if !self.exports.is_empty() {
+            let mut contents = Vec::new();
+            encode_vec(&self.exports, &mut contents, |(name, descriptor), out| {
+                encode_name(name, out);
+                out.push(descriptor.kind() as u8);
+                encode_varuint(descriptor.index(), out);
+            });
+            encode_section(SectionType::Export, &contents, &mut out);
+        }
+
+        if let Some(function_index) = self.start {
+            let mut contents = Vec::new();
+            encode_varuint(function_index, &mut contents);
+            encode_section(SectionType::Start, &contents, &mut out);
+        }
+
+        if !self.elements.is_empty() {
+            let mut contents = Vec::new();
+            encode_element_section(&self.elements, &mut contents);
+            encode_section(SectionType::Element, &contents, &mut out);
+        }
+
+        if !self.bodies.is_empty() {
+            let mut contents = Vec::new();
+            encode_vec(&self.bodies, &mut contents, |(locals, expression), out| {
+                let mut body = Vec::new();
+                encode_vec(locals, &mut body, |locals, out| {
+                    encode_varuint(locals.count, out);
+                    encode_value_type(locals.value_type, out);
+                });
+                body.extend_from_slice(expression);
+
+                encode_varuint(body.len() as u32, out);
+                out.extend_from_slice(&body);
+            });
+            encode_section(SectionType::Code, &contents, &mut out);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_bytes;
+
+    #[test]
+    fn test_build_and_decode() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[WasmValueType::I32], &[])
+            .add_type(&[], &[WasmValueType::I32])
+            .add_import("env", "log", func_type(0))
+            .add_function(
+                1,
+                &[WasmLocals {
+                    count: 2,
+                    value_type: WasmValueType::F64,
+                }],
+                &[0x41, 0x2a, 0x0b],
+            )
+            .add_export("answer", WasmExportDescriptor::Function(1.into()))
+            .build();
+
+        let module = decode_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            module.types,
+            vec![
+                WasmFunctionType {
+                    params: vec![WasmValueType::I32],
+                    returns: vec![],
+                },
+                WasmFunctionType {
+                    params: vec![],
+                    returns: vec![WasmValueType::I32],
+                },
+            ]
+        );
+
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.imports[0].module_name, "env");
+        assert_eq!(module.imports[0].field_name, "log");
+        assert!(matches!(
+            module.imports[0].descriptor,
+            WasmImportDescriptor::Function(index) if u32::from(index) == 0
+        ));
+
+        assert_eq!(module.functions, vec![1.into()]);
+
+        assert_eq!(module.exports.len(), 1);
+        assert_eq!(module.exports[0].name, "answer");
+        assert!(matches!(
+            module.exports[0].descriptor,
+            WasmExportDescriptor::Function(index) if u32::from(index) == 1
+        ));
+
+        assert_eq!(module.code.len(), 1);
+        assert_eq!(module.code[0].total_locals(), 2);
+        assert_eq!(module.code[0].expression, vec![0x41, 0x2a, 0x0b]);
+    }
+}
@@ -0,0 +1,689 @@
+//! Encoding of the binary format, the inverse of `decode`.
+//!
+//! Only the constructs the decoder keeps are encoded; everything is appended to a `Vec<u8>`.
+
+use crate::decode::{FUNCTION_MAGIC_BYTES, HEADER_MAGIC_BYTES};
+use crate::module::{RawSection, WasmModule};
+use crate::types::{
+    ConstExpr, DataMode, DataSegment, ElementItems, ElementMode, ElementSegment, FuncIndex,
+    FunctionBody, Global, GlobalType, HeapType, MemoryType, Mutability, RefType, SectionType,
+    TableType, Tag, TypeIndex, WasmElementType, WasmExportEntry, WasmFunctionType,
+    WasmImportDescriptor, WasmImportEntry, WasmLimits, WasmValueType, WasmVersion,
+};
+use crate::wasm::instructions::{
+    BlockType, ExtendedInstruction, Instruction, MemArg, SimdInstruction,
+};
+use std::borrow::Cow;
+
+#[cfg(test)]
+mod builder;
+
+#[cfg(test)]
+pub(crate) use builder::{func_type, memory_type, ModuleBuilder};
+
+/// Append the magic bytes and the version that start every module.
+pub(crate) fn encode_preamble(version: WasmVersion, out: &mut Vec<u8>) {
+    out.extend_from_slice(&HEADER_MAGIC_BYTES);
+    out.extend_from_slice(&u32::from(version).to_le_bytes());
+}
+
+/// Append `value` as unsigned LEB128, using as few bytes as possible.
+pub(crate) fn encode_varuint(value: u32, out: &mut Vec<u8>) {
+    encode_varuint64(value.into(), out)
+}
+
+pub(crate) fn encode_varuint64(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Append a name: its length in bytes, then its UTF-8.
+pub(crate) fn encode_name(name: &str, out: &mut Vec<u8>) {
+    encode_varuint(name.len() as u32, out);
+    out.extend_from_slice(name.as_bytes());
+}
+
+/// Append a vector: the number of items, then each item as written by `encode_item`.
+pub(crate) fn encode_vec<T>(
+    items: &[T],
+    out: &mut Vec<u8>,
+    mut encode_item: impl FnMut(&T, &mut Vec<u8>),
+) {
+    encode_varuint(items.len() as u32, out);
+    for item in items {
+        encode_item(item, out);
+    }
+}
+
+/// Write a decoded module back out as a binary. Sections are written as they were read, so
+/// the output matches the input byte for byte, apart from the sections changed since, e.g. by
+/// `WasmModule::remove_custom_section` or `WasmModule::dedup_types`.
+///
+/// ```
+/// let bytes = std::fs::read("tests/fixtures/linked.wasm").unwrap();
+/// let mut module = wasm_thing::decode_bytes(&bytes).unwrap();
+/// assert_eq!(wasm_thing::encode_module(&module), bytes);
+///
+/// module.remove_custom_section("producers");
+/// let stripped = wasm_thing::encode_module(&module);
+/// assert!(stripped.len() < bytes.len());
+/// ```
+///
+/// A module built with `WasmModuleBuilder` or by `WasmModule::merge` is written with every
+/// section encoded canonically.
+pub fn encode_module(module: &WasmModule) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_preamble(module.version, &mut out);
+
+    for section in &module.raw_sections {
+        match section {
+            RawSection::Custom(section) => {
+                let mut contents = Vec::new();
+                encode_name(section.name(), &mut contents);
+                contents.extend_from_slice(section.data());
+                encode_section(SectionType::Custom, &contents, &mut out);
+            }
+            RawSection::Standard {
+                section_type,
+                contents,
+            } => encode_section(*section_type, contents, &mut out),
+        }
+    }
+
+    out
+}
+
+/// Encode every non-empty section of a module that was put together rather than decoded,
+/// for its `raw_sections`, so that `encode_module` writes all of it. Custom sections are left
+/// out; the start section is only written for a `start_function`.
+pub(crate) fn encode_sections(
+    module: &WasmModule,
+    start_function: Option<FuncIndex>,
+) -> Vec<RawSection<'static>> {
+    let mut sections = Vec::new();
+    let mut add = |section_type, is_empty: bool, encode: &dyn Fn(&mut Vec<u8>)| {
+        if !is_empty {
+            let mut contents = Vec::new();
+            encode(&mut contents);
+            sections.push(RawSection::Standard {
+                section_type,
+                contents: Cow::Owned(contents),
+            });
+        }
+    };
+
+    add(SectionType::Type, module.types.is_empty(), &|out| {
+        encode_type_section(&module.types, out)
+    });
+    add(SectionType::Import, module.imports.is_empty(), &|out| {
+        encode_import_section(&module.imports, out)
+    });
+    add(SectionType::Function, module.functions.is_empty(), &|out| {
+        encode_function_section(&module.functions, out)
+    });
+    add(SectionType::Table, module.tables.is_empty(), &|out| {
+        encode_table_section(&module.tables, out)
+    });
+    add(SectionType::Memory, module.memories.is_empty(), &|out| {
+        encode_memory_section(&module.memories, out)
+    });
+    add(SectionType::Tag, module.tags.is_empty(), &|out| {
+        encode_tag_section(&module.tags, out)
+    });
+    add(SectionType::Global, module.globals.is_empty(), &|out| {
+        encode_global_section(&module.globals, out)
+    });
+    add(SectionType::Export, module.exports.is_empty(), &|out| {
+        encode_export_section(&module.exports, out)
+    });
+    if let Some(function) = start_function {
+        add(SectionType::Start, false, &|out| {
+            encode_varuint(function.into(), out)
+        });
+    }
+    add(SectionType::Element, module.elements.is_empty(), &|out| {
+        encode_element_section(&module.elements, out)
+    });
+    // `memory.init` and `data.drop` need the count before the code section.
+    add(SectionType::DataCount, module.data.is_empty(), &|out| {
+        encode_varuint(module.data.len() as u32, out)
+    });
+    add(SectionType::Code, module.code.is_empty(), &|out| {
+        encode_code_section(&module.code, out)
+    });
+    add(SectionType::Data, module.data.is_empty(), &|out| {
+        encode_data_section(&module.data, out)
+    });
+
+    sections
+}
+
+/// Append a section: its id, the size of `contents`, then `contents`.
+pub(crate) fn encode_section(section_type: SectionType, contents: &[u8], out: &mut Vec<u8>) {
+    out.push(section_type.id());
+    encode_varuint(contents.len() as u32, out);
+    out.extend_from_slice(contents);
+}
+
+/// Append `value` as signed LEB128, using as few bytes as possible.
+pub(crate) fn encode_varint(mut value: i64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        // Done once the rest is all sign bits, and the sign bit of this byte agrees.
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Append a value type: a single byte, or for a typed reference, its prefix and heap type.
+///
+/// # Panics
+///
+/// `WasmValueType::UNSUPPORTED` has no encoding.
+pub(crate) fn encode_value_type(value_type: WasmValueType, out: &mut Vec<u8>) {
+    out.push(match value_type {
+        WasmValueType::I32 => 0x7f,
+        WasmValueType::I64 => 0x7e,
+        WasmValueType::F32 => 0x7d,
+        WasmValueType::F64 => 0x7c,
+        WasmValueType::V128 => 0x7b,
+        WasmValueType::Funcref => 0x70,
+        WasmValueType::Externref => 0x6f,
+        WasmValueType::Ref(ref_type) => return encode_ref_type(ref_type, out),
+        WasmValueType::UNSUPPORTED => panic!("unsupported value types cannot be encoded"),
+    });
+}
+
+fn encode_ref_type(ref_type: RefType, out: &mut Vec<u8>) {
+    use HeapType::*;
+
+    out.push(if ref_type.nullable { 0x63 } else { 0x64 });
+    let byte = match ref_type.heap_type {
+        Func => 0x70,
+        Extern => 0x6f,
+        Any => 0x6e,
+        Eq => 0x6d,
+        I31 => 0x6c,
+        Struct => 0x6b,
+        Array => 0x6a,
+        None => 0x71,
+        NoExtern => 0x72,
+        NoFunc => 0x73,
+        Exn => 0x69,
+        NoExn => 0x74,
+        Concrete(type_index) => return encode_varint(type_index.into(), out),
+    };
+    out.push(byte);
+}
+
+/// Append limits, starting with `flags` and the flag for whether there is a maximum.
+fn encode_limits(limits: &WasmLimits, flags: u8, out: &mut Vec<u8>) {
+    out.push(flags | u8::from(limits.max.is_some()));
+    encode_varuint64(limits.min, out);
+    if let Some(max) = limits.max {
+        encode_varuint64(max, out);
+    }
+}
+
+fn encode_element_type(element_type: WasmElementType, out: &mut Vec<u8>) {
+    out.push(match element_type {
+        WasmElementType::Funcref => 0x70,
+        WasmElementType::Externref => 0x6f,
+    });
+}
+
+pub(crate) fn encode_table_type(table_type: &TableType, out: &mut Vec<u8>) {
+    encode_element_type(table_type.element_type, out);
+    let mut flags = 0;
+    if table_type.shared {
+        flags |= 0x02;
+    }
+    if table_type.table64 {
+        flags |= 0x04;
+    }
+    encode_limits(&table_type.limits, flags, out);
+}
+
+pub(crate) fn encode_memory_type(memory_type: &MemoryType, out: &mut Vec<u8>) {
+    let mut flags = 0;
+    if memory_type.shared {
+        flags |= 0x02;
+    }
+    if memory_type.memory64 {
+        flags |= 0x04;
+    }
+    encode_limits(&memory_type.limits, flags, out);
+}
+
+pub(crate) fn encode_global_type(global_type: &GlobalType, out: &mut Vec<u8>) {
+    encode_value_type(global_type.value_type, out);
+    out.push(match global_type.mutability {
+        Mutability::Immutable => 0x00,
+        Mutability::Mutable => 0x01,
+    });
+}
+
+/// Append an import descriptor: the import kind, then the type of the import.
+pub(crate) fn encode_import_descriptor(descriptor: &WasmImportDescriptor, out: &mut Vec<u8>) {
+    out.push(descriptor.kind() as u8);
+    match descriptor {
+        WasmImportDescriptor::Function(type_index) => encode_varuint((*type_index).into(), out),
+        WasmImportDescriptor::Table(table_type) => encode_table_type(table_type, out),
+        WasmImportDescriptor::Memory(memory_type) => encode_memory_type(memory_type, out),
+        WasmImportDescriptor::Global(global_type) => encode_global_type(global_type, out),
+    }
+}
+
+/// Append the contents of a type section.
+pub(crate) fn encode_type_section(types: &[WasmFunctionType], out: &mut Vec<u8>) {
+    encode_vec(types, out, |function_type, out| {
+        out.extend_from_slice(&FUNCTION_MAGIC_BYTES);
+        for value_types in [&function_type.params, &function_type.returns] {
+            encode_vec(value_types, out, |value_type, out| {
+                encode_value_type(*value_type, out)
+            });
+        }
+    });
+}
+
+/// Append the contents of an import section.
+pub(crate) fn encode_import_section(imports: &[WasmImportEntry], out: &mut Vec<u8>) {
+    encode_vec(imports, out, |import, out| {
+        encode_name(&import.module_name, out);
+        encode_name(&import.field_name, out);
+        encode_import_descriptor(&import.descriptor, out);
+    });
+}
+
+/// Append the contents of a function section: the type index of every defined function.
+pub(crate) fn encode_function_section(functions: &[TypeIndex], out: &mut Vec<u8>) {
+    encode_vec(functions, out, |type_index, out| {
+        encode_varuint((*type_index).into(), out)
+    });
+}
+
+/// Append the contents of a tag section.
+pub(crate) fn encode_tag_section(tags: &[Tag], out: &mut Vec<u8>) {
+    encode_vec(tags, out, |tag, out| {
+        out.push(tag.attribute);
+        encode_varuint(tag.type_index.into(), out);
+    });
+}
+
+/// Append the contents of a table section.
+pub(crate) fn encode_table_section(tables: &[TableType], out: &mut Vec<u8>) {
+    encode_vec(tables, out, encode_table_type);
+}
+
+/// Append the contents of a memory section.
+pub(crate) fn encode_memory_section(memories: &[MemoryType], out: &mut Vec<u8>) {
+    encode_vec(memories, out, encode_memory_type);
+}
+
+/// Append a constant expression, including its final `end`.
+pub(crate) fn encode_const_expr(expr: ConstExpr, out: &mut Vec<u8>) {
+    match expr {
+        ConstExpr::I32Const(value) => {
+            out.push(0x41);
+            encode_varint(value.into(), out);
+        }
+        ConstExpr::I64Const(value) => {
+            out.push(0x42);
+            encode_varint(value, out);
+        }
+        ConstExpr::F32Const(value) => {
+            out.push(0x43);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        ConstExpr::F64Const(value) => {
+            out.push(0x44);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        ConstExpr::GlobalGet(index) => {
+            out.push(0x23);
+            encode_varuint(index.into(), out);
+        }
+        ConstExpr::RefNull(element_type) => {
+            out.push(0xd0);
+            encode_element_type(element_type, out);
+        }
+        ConstExpr::RefFunc(index) => {
+            out.push(0xd2);
+            encode_varuint(index.into(), out);
+        }
+    }
+    out.push(0x0b);
+}
+
+/// Append the contents of a global section.
+pub(crate) fn encode_global_section(globals: &[Global], out: &mut Vec<u8>) {
+    encode_vec(globals, out, |global, out| {
+        encode_global_type(&global.global_type, out);
+        encode_const_expr(global.init, out);
+    });
+}
+
+/// Append the contents of an export section.
+pub(crate) fn encode_export_section(exports: &[WasmExportEntry], out: &mut Vec<u8>) {
+    encode_vec(exports, out, |export, out| {
+        encode_name(&export.name, out);
+        out.push(export.descriptor.kind() as u8);
+        encode_varuint(export.descriptor.index(), out);
+    });
+}
+
+/// Append the contents of an element section. Each segment is written in the shortest of
+/// the eight forms that can hold it.
+pub(crate) fn encode_element_section(elements: &[ElementSegment], out: &mut Vec<u8>) {
+    encode_vec(elements, out, |segment, out| {
+        let expressions = matches!(segment.items, ElementItems::Expressions(_));
+        let flags = match segment.mode {
+            ElementMode::Active { table_index, .. }
+                if u32::from(table_index) == 0
+                    && segment.element_type == WasmElementType::Funcref =>
+            {
+                0
+            }
+            ElementMode::Active { .. } => 2,
+            ElementMode::Passive => 1,
+            ElementMode::Declarative => 3,
+        };
+        encode_varuint(flags | if expressions { 4 } else { 0 }, out);
+
+        if let ElementMode::Active {
+            table_index,
+            offset,
+        } = segment.mode
+        {
+            if flags == 2 {
+                encode_varuint(table_index.into(), out);
+            }
+            encode_const_expr(offset, out);
+        }
+        if flags != 0 {
+            // The element kind of function indices, or the reference type of expressions.
+            match expressions {
+                false => out.push(0x00),
+                true => encode_element_type(segment.element_type, out),
+            }
+        }
+
+        match &segment.items {
+            ElementItems::Functions(indices) => encode_vec(indices, out, |index, out| {
+                encode_varuint((*index).into(), out)
+            }),
+            ElementItems::Expressions(exprs) => {
+                encode_vec(exprs, out, |expr, out| encode_const_expr(*expr, out))
+            }
+        }
+    });
+}
+
+/// Append the contents of a data section.
+pub(crate) fn encode_data_section(data: &[DataSegment], out: &mut Vec<u8>) {
+    encode_vec(data, out, |segment, out| {
+        match segment.mode {
+            DataMode::Active {
+                memory_index,
+                offset,
+            } if u32::from(memory_index) == 0 => {
+                out.push(0x00);
+                encode_const_expr(offset, out);
+            }
+            DataMode::Active {
+                memory_index,
+                offset,
+            } => {
+                out.push(0x02);
+                encode_varuint(memory_index.into(), out);
+                encode_const_expr(offset, out);
+            }
+            DataMode::Passive => out.push(0x01),
+        }
+        encode_varuint(segment.data.len() as u32, out);
+        out.extend_from_slice(&segment.data);
+    });
+}
+
+/// Append `instruction` with every immediate in its shortest form. `opcode` is its first byte
+/// as read, since `Opcode` does not convert back to one.
+pub(crate) fn encode_instruction(instruction: &Instruction, opcode: u8, out: &mut Vec<u8>) {
+    out.push(opcode);
+    match instruction {
+        Instruction::Block(block_type)
+        | Instruction::Loop(block_type)
+        | Instruction::If(block_type) => match *block_type {
+            BlockType::Empty => out.push(0x40),
+            BlockType::Value(value_type) => encode_value_type(value_type, out),
+            BlockType::TypeIndex(type_index) => encode_varint(u32::from(type_index).into(), out),
+        },
+        Instruction::BrTable { targets, default } => {
+            encode_vec(targets, out, |&target, out| encode_varuint(target, out));
+            encode_varuint(*default, out);
+        }
+        Instruction::CallIndirect {
+            type_index,
+            table_index,
+        } => {
+            encode_varuint((*type_index).into(), out);
+            encode_varuint((*table_index).into(), out);
+        }
+        Instruction::SelectTyped(value_types) => {
+            encode_vec(value_types, out, |&value_type, out| {
+                encode_value_type(value_type, out)
+            });
+        }
+        Instruction::Br(index) | Instruction::BrIf(index) => encode_varuint(*index, out),
+        Instruction::GetLocal(index)
+        | Instruction::SetLocal(index)
+        | Instruction::TeeLocal(index) => encode_varuint((*index).into(), out),
+        Instruction::Call(index) | Instruction::RefFunc(index) => {
+            encode_varuint((*index).into(), out)
+        }
+        Instruction::GetGlobal(index) | Instruction::SetGlobal(index) => {
+            encode_varuint((*index).into(), out)
+        }
+        Instruction::TableGet(index) | Instruction::TableSet(index) => {
+            encode_varuint((*index).into(), out)
+        }
+        Instruction::CurrentMemory(index) | Instruction::GrowMemory(index) => {
+            encode_varuint((*index).into(), out)
+        }
+        Instruction::Memory(_, memarg) => encode_memarg(memarg, out),
+        Instruction::I32Const(value) => encode_varint((*value).into(), out),
+        Instruction::I64Const(value) => encode_varint(*value, out),
+        Instruction::F32Const(value) => out.extend_from_slice(&value.to_le_bytes()),
+        Instruction::F64Const(value) => out.extend_from_slice(&value.to_le_bytes()),
+        Instruction::RefNull(element_type) => encode_element_type(*element_type, out),
+        Instruction::Extended(extended) => encode_extended_instruction(extended, out),
+        Instruction::Simd(simd) => encode_simd_instruction(simd, out),
+        Instruction::Plain(_) => {}
+    }
+}
+
+fn encode_memarg(memarg: &MemArg, out: &mut Vec<u8>) {
+    encode_varuint(memarg.align, out);
+    encode_varuint(memarg.offset, out);
+}
+
+/// Append the sub-opcode and immediates of an `0xfc` prefixed instruction.
+fn encode_extended_instruction(instruction: &ExtendedInstruction, out: &mut Vec<u8>) {
+    use ExtendedInstruction::*;
+
+    let (sub_opcode, immediates) = match *instruction {
+        I32TruncSatF32S => (0, vec![]),
+        I32TruncSatF32U => (1, vec![]),
+        I32TruncSatF64S => (2, vec![]),
+        I32TruncSatF64U => (3, vec![]),
+        I64TruncSatF32S => (4, vec![]),
+        I64TruncSatF32U => (5, vec![]),
+        I64TruncSatF64S => (6, vec![]),
+        I64TruncSatF64U => (7, vec![]),
+        MemoryInit { data_index, memory } => (8, vec![data_index, memory.into()]),
+        DataDrop(data_index) => (9, vec![data_index]),
+        MemoryCopy {
+            dst_memory,
+            src_memory,
+        } => (10, vec![dst_memory.into(), src_memory.into()]),
+        MemoryFill(memory) => (11, vec![memory.into()]),
+        TableInit { elem_index, table } => (12, vec![elem_index, table.into()]),
+        ElemDrop(elem_index) => (13, vec![elem_index]),
+        TableCopy {
+            dst_table,
+            src_table,
+        } => (14, vec![dst_table.into(), src_table.into()]),
+        TableGrow(table) => (15, vec![table.into()]),
+        TableSize(table) => (16, vec![table.into()]),
+        TableFill(table) => (17, vec![table.into()]),
+    };
+    encode_varuint(sub_opcode, out);
+    for immediate in immediates {
+        encode_varuint(immediate, out);
+    }
+}
+
+/// Append the sub-opcode and immediates of an `0xfd` prefixed instruction.
+fn encode_simd_instruction(instruction: &SimdInstruction, out: &mut Vec<u8>) {
+    match instruction {
+        SimdInstruction::Memory { opcode, memarg } => {
+            encode_varuint(*opcode, out);
+            encode_memarg(memarg, out);
+        }
+        SimdInstruction::MemoryLane {
+            opcode,
+            memarg,
+            lane,
+        } => {
+            encode_varuint(*opcode, out);
+            encode_memarg(memarg, out);
+            out.push(*lane);
+        }
+        SimdInstruction::V128Const(bytes) => {
+            encode_varuint(0x0c, out);
+            out.extend_from_slice(bytes);
+        }
+        SimdInstruction::I8x16Shuffle(lanes) => {
+            encode_varuint(0x0d, out);
+            out.extend_from_slice(lanes);
+        }
+        SimdInstruction::Lane { opcode, lane } => {
+            encode_varuint(*opcode, out);
+            out.push(*lane);
+        }
+        SimdInstruction::Plain(opcode) => encode_varuint(*opcode, out),
+    }
+}
+
+/// Append the contents of a code section: every body with its size, locals and expression.
+pub(crate) fn encode_code_section(bodies: &[FunctionBody], out: &mut Vec<u8>) {
+    encode_vec(bodies, out, |body, out| {
+        let mut contents = Vec::new();
+        encode_vec(&body.locals, &mut contents, |locals, out| {
+            encode_varuint(locals.count, out);
+            encode_value_type(locals.value_type, out);
+        });
+        contents.extend_from_slice(&body.expression);
+
+        encode_varuint(contents.len() as u32, out);
+        out.extend_from_slice(&contents);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_bytes;
+
+    #[test]
+    fn test_encode_varuint() {
+        for (value, bytes) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (129, &[0x81, 0x01]),
+            (u32::MAX, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        ] {
+            let mut out = Vec::new();
+            encode_varuint(value, &mut out);
+            assert_eq!(out, bytes);
+        }
+    }
+
+    /// The module of `ModuleBuilder` with a type and a function, with `customs` after the
+    /// function section.
+    fn module_with_custom_sections(customs: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_function(0, &[], &[0x0b])
+            .build();
+
+        // The code section comes last, in six bytes; put the custom sections in front of it.
+        let code_start = bytes.len() - 6;
+        let code = bytes.split_off(code_start);
+        for (name, data) in customs {
+            let mut contents = Vec::new();
+            encode_name(name, &mut contents);
+            contents.extend_from_slice(data);
+            encode_section(SectionType::Custom, &contents, &mut bytes);
+        }
+        bytes.extend_from_slice(&code);
+        bytes
+    }
+
+    #[test]
+    fn test_encode_module_custom_sections() {
+        let bytes = module_with_custom_sections(&[("a", b"1"), ("b", b"2"), ("a", b"3")]);
+        let mut module = decode_bytes(&bytes).unwrap();
+        assert_eq!(encode_module(&module), bytes);
+
+        // The first `a` is replaced in place and the second is dropped.
+        module.set_custom_section("a", b"4".to_vec());
+        assert_eq!(
+            encode_module(&module),
+            module_with_custom_sections(&[("a", b"4"), ("b", b"2")])
+        );
+
+        module.set_custom_section("c", &b"5"[..]);
+        assert!(module.remove_custom_section("b"));
+        assert!(!module.remove_custom_section("b"));
+
+        let encoded = encode_module(&module);
+        let decoded = decode_bytes(&encoded).unwrap();
+        let customs: Vec<_> = decoded
+            .custom_sections()
+            .map(|section| (section.name(), section.data()))
+            .collect();
+        assert_eq!(customs, [("a", &b"4"[..]), ("c", b"5")]);
+        assert_eq!(decoded.code(), module.code());
+    }
+
+    #[test]
+    fn test_encode_varint() {
+        for (value, bytes) in [
+            (0, &[0x00][..]),
+            (63, &[0x3f]),
+            (64, &[0xc0, 0x00]),
+            (-1, &[0x7f]),
+            (-16, &[0x70]),
+            (-65, &[0xbf, 0x7f]),
+            (u32::MAX as i64, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        ] {
+            let mut out = Vec::new();
+            encode_varint(value, &mut out);
+            assert_eq!(out, bytes, "{value}");
+        }
+    }
+}
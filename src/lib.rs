@@ -0,0 +1,20 @@
+#[macro_use]
+extern crate doc_comment;
+
+pub mod decode;
+mod encode;
+pub mod module;
+pub mod types;
+pub mod wasm;
+
+#[cfg(test)]
+mod test_util;
+
+pub use decode::{
+    decode_bytes, decode_bytes_lenient, decode_bytes_lenient_with_config, decode_bytes_with_config,
+    decode_bytes_with_len, decode_file, decode_file_with_config, decode_headers, decode_reader,
+    decode_with, decode_with_config, DecodeError, DecoderConfig,
+};
+pub use encode::encode_module;
+pub use module::{WasmModule, WasmModuleBuilder};
+pub use types::{ModuleKind, SectionInfo, SectionType, WasmVersion};
@@ -1,11 +0,0 @@
-#[macro_use]
-extern crate doc_comment;
-
-mod decode;
-mod module;
-mod types;
-mod wasm;
-
-fn main() {
-    println!("Hello, world!");
-}
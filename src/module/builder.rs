@@ -0,0 +1,328 @@
+use crate::decode::WASM_VERSION;
+use crate::encode::encode_sections;
+use crate::module::WasmModule;
+use crate::types::{
+    ConstExpr, DataMode, DataSegment, ExternalKind, FuncIndex, FunctionBody, MemoryIndex,
+    MemoryType, TypeIndex, WasmExportDescriptor, WasmExportEntry, WasmFunctionType,
+    WasmImportDescriptor, WasmImportEntry, WasmLimits, WasmValueType,
+};
+use std::borrow::Cow;
+use thiserror::Error;
+
+/// Why `WasmModuleBuilder::build` refused the module it was given.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// `WasmValueType::UNSUPPORTED` has no encoding. `what` names where it was found, e.g.
+    /// "type 0".
+    #[error("The {what} has an unsupported value type, which cannot be encoded.")]
+    UnsupportedValueType { what: String },
+}
+
+/// Constructs a `WasmModule` in code rather than by decoding one, keeping track of the index
+/// of everything added:
+///
+/// ```
+/// use wasm_thing::module::WasmModuleBuilder;
+/// use wasm_thing::types::{FunctionBody, WasmFunctionType, WasmValueType};
+///
+/// let mut builder = WasmModuleBuilder::new();
+/// let add_type = builder.add_type(WasmFunctionType::new(
+///     vec![WasmValueType::I32, WasmValueType::I32],
+///     vec![WasmValueType::I32],
+/// ));
+/// // local.get 0, local.get 1, i32.add, end
+/// let body = FunctionBody::new(vec![], vec![0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b]);
+/// let add = builder.add_function(add_type, body);
+/// builder.add_export("add", add);
+///
+/// let module = builder.build().unwrap();
+/// assert_eq!(module.exported_functions()["add"], add);
+/// assert_eq!(module.function_signature(add.into()).unwrap().to_string(), "(i32, i32) -> i32");
+/// ```
+///
+/// Imports come first in the index space of their kind, so imports of a kind must be added
+/// before any definitions of it. The built module has no `sections`, as it was never decoded.
+#[derive(Clone, Debug, Default)]
+pub struct WasmModuleBuilder<'a> {
+    module: WasmModule<'a>,
+    num_imported_functions: u32,
+    num_imported_memories: u32,
+}
+
+impl<'a> WasmModuleBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_type(&mut self, function_type: WasmFunctionType) -> TypeIndex {
+        self.module.types.push(function_type);
+        TypeIndex::from(self.module.types.len() as u32 - 1)
+    }
+
+    /// # Panics
+    ///
+    /// If a function or memory is imported after one was defined, which would shift the
+    /// indices handed out for the definitions.
+    pub fn add_import(
+        &mut self,
+        module_name: impl Into<Cow<'a, str>>,
+        field_name: impl Into<Cow<'a, str>>,
+        descriptor: WasmImportDescriptor,
+    ) {
+        match descriptor.kind() {
+            ExternalKind::Function => {
+                assert!(
+                    self.module.functions.is_empty(),
+                    "functions must be imported before any are defined"
+                );
+                self.num_imported_functions += 1;
+            }
+            ExternalKind::Memory => {
+                assert!(
+                    self.module.memories.is_empty(),
+                    "memories must be imported before any are defined"
+                );
+                self.num_imported_memories += 1;
+            }
+            ExternalKind::Table | ExternalKind::Global => {}
+        }
+
+        self.module
+            .imports
+            .push(WasmImportEntry::new(module_name, field_name, descriptor));
+    }
+
+    /// Define a function of type `type_index`.
+    pub fn add_function(&mut self, type_index: TypeIndex, body: FunctionBody<'a>) -> FuncIndex {
+        self.module.functions.push(type_index);
+        self.module.code.push(body);
+        FuncIndex::from(self.num_imported_functions + self.module.functions.len() as u32 - 1)
+    }
+
+    /// Define a memory with `limits` in 64 KiB pages.
+    pub fn add_memory(&mut self, limits: WasmLimits) -> MemoryIndex {
+        self.module.memories.push(MemoryType::new(limits));
+        MemoryIndex::from(self.num_imported_memories + self.module.memories.len() as u32 - 1)
+    }
+
+    /// Export an item under `name`. A `FuncIndex` or `MemoryIndex` converts to the matching
+    /// descriptor.
+    pub fn add_export(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<WasmExportDescriptor>,
+    ) {
+        self.module
+            .exports
+            .push(WasmExportEntry::new(name, descriptor.into()));
+    }
+
+    /// Add an active data segment, copying `data` into `memory` at the address `offset`
+    /// evaluates to.
+    pub fn add_data_segment(
+        &mut self,
+        memory: MemoryIndex,
+        offset: ConstExpr,
+        data: impl Into<Cow<'a, [u8]>>,
+    ) {
+        self.module.data.push(DataSegment {
+            mode: DataMode::Active {
+                memory_index: memory,
+                offset,
+            },
+            data: data.into(),
+        });
+    }
+
+    /// The module, with every section encoded so that `encode_module` writes all of it.
+    /// Fails if a type, an imported global or the locals of a function use
+    /// `WasmValueType::UNSUPPORTED`.
+    pub fn build(self) -> Result<WasmModule<'a>, BuildError> {
+        let mut module = WasmModule {
+            version: WASM_VERSION.into(),
+            ..self.module
+        };
+        check_value_types(&module)?;
+        module.raw_sections = encode_sections(&module, None);
+        Ok(module)
+    }
+}
+
+/// Fail on the first value type of `module` that cannot be encoded.
+fn check_value_types(module: &WasmModule) -> Result<(), BuildError> {
+    let unsupported = |what: String| Err(BuildError::UnsupportedValueType { what });
+    let is_unsupported = |value_type: &WasmValueType| *value_type == WasmValueType::UNSUPPORTED;
+
+    for (index, function_type) in module.types.iter().enumerate() {
+        let mut value_types = function_type.params.iter().chain(&function_type.returns);
+        if value_types.any(is_unsupported) {
+            return unsupported(format!("type {index}"));
+        }
+    }
+    for import in &module.imports {
+        if let WasmImportDescriptor::Global(global_type) = &import.descriptor {
+            if is_unsupported(&global_type.value_type) {
+                return unsupported(format!(
+                    "global import {}.{}",
+                    import.module_name, import.field_name
+                ));
+            }
+        }
+    }
+    for (defined, body) in module.code.iter().enumerate() {
+        if body
+            .locals
+            .iter()
+            .any(|locals| is_unsupported(&locals.value_type))
+        {
+            return unsupported(format!("locals of defined function {defined}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::{func_type, ModuleBuilder};
+    use crate::types::{WasmLocals, WasmValueType};
+    use crate::{decode_bytes, encode_module};
+
+    const ADD_BODY: [u8; 6] = [0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b];
+
+    #[test]
+    fn test_build_add_module() {
+        let mut builder = WasmModuleBuilder::new();
+        let log_type = builder.add_type(WasmFunctionType::new(vec![WasmValueType::I32], vec![]));
+        let add_type = builder.add_type(WasmFunctionType::new(
+            vec![WasmValueType::I32, WasmValueType::I32],
+            vec![WasmValueType::I32],
+        ));
+        builder.add_import("env", "log", WasmImportDescriptor::Function(log_type));
+        let add = builder.add_function(add_type, FunctionBody::new(vec![], &ADD_BODY[..]));
+        builder.add_export("add", add);
+        let module = builder.build().unwrap();
+
+        // The imported function comes first.
+        assert_eq!(u32::from(add), 1);
+
+        // The same module, assembled from bytes.
+        let bytes = ModuleBuilder::new()
+            .add_type(&[WasmValueType::I32], &[])
+            .add_type(
+                &[WasmValueType::I32, WasmValueType::I32],
+                &[WasmValueType::I32],
+            )
+            .add_import("env", "log", func_type(0))
+            .add_function(1, &[], &ADD_BODY)
+            .add_export("add", WasmExportDescriptor::Function(1.into()))
+            .build();
+        let mut decoded = decode_bytes(&bytes).unwrap();
+        decoded.sections.clear();
+
+        assert_eq!(module, decoded);
+        assert_eq!(encode_module(&module), bytes);
+    }
+
+    #[test]
+    fn test_build_round_trip() {
+        let mut builder = WasmModuleBuilder::new();
+        let add_type = builder.add_type(WasmFunctionType::new(
+            vec![WasmValueType::I32, WasmValueType::I32],
+            vec![WasmValueType::I32],
+        ));
+        builder.add_import(
+            "env",
+            "memory",
+            WasmImportDescriptor::Memory(MemoryType::new(WasmLimits::new(1, None))),
+        );
+        let memory = builder.add_memory(WasmLimits::new(1, Some(2)));
+        builder.add_data_segment(memory, ConstExpr::I32Const(8), b"hello".to_vec());
+        let add = builder.add_function(add_type, FunctionBody::new(vec![], &ADD_BODY[..]));
+        builder.add_export("add", add);
+        builder.add_export("memory", memory);
+        let module = builder.build().unwrap();
+
+        let bytes = encode_module(&module);
+        let mut decoded = decode_bytes(&bytes).unwrap();
+        decoded.sections.clear();
+        assert_eq!(decoded, module);
+
+        let interface = decoded.interface();
+        assert_eq!(
+            decoded.function_signature(add.into()).unwrap().to_string(),
+            "(i32, i32) -> i32"
+        );
+        assert_eq!(interface.exports().len(), 2);
+        assert_eq!(decoded.memory_image(1).unwrap().segments()[0].1, b"hello");
+    }
+
+    #[test]
+    fn test_build_memory_and_data() {
+        let mut builder = WasmModuleBuilder::new();
+        builder.add_import(
+            "env",
+            "memory",
+            WasmImportDescriptor::Memory(MemoryType::new(WasmLimits::new(1, None))),
+        );
+        let memory = builder.add_memory(WasmLimits::new(1, Some(2)));
+        builder.add_data_segment(memory, ConstExpr::I32Const(8), b"hello".to_vec());
+        builder.add_export("memory", memory);
+
+        let unused = builder.add_type(WasmFunctionType::default());
+        builder.add_function(
+            unused,
+            FunctionBody::new(vec![WasmLocals::new(2, WasmValueType::I64)], vec![0x0b]),
+        );
+        let module = builder.build().unwrap();
+
+        assert_eq!(u32::from(memory), 1);
+        assert_eq!(module.memories()[0].limits().max(), Some(2));
+        assert_eq!(
+            module.data()[0].mode(),
+            DataMode::Active {
+                memory_index: 1.into(),
+                offset: ConstExpr::I32Const(8)
+            }
+        );
+        assert_eq!(module.data()[0].data(), b"hello");
+        assert_eq!(module.exports()[0].descriptor().index(), 1);
+        assert_eq!(module.code()[0].total_locals(), 2);
+    }
+
+    #[test]
+    fn test_build_unsupported_value_type() {
+        use WasmValueType::{I32, UNSUPPORTED};
+
+        let mut builder = WasmModuleBuilder::new();
+        builder.add_type(WasmFunctionType::new(vec![I32], vec![]));
+        builder.add_type(WasmFunctionType::new(vec![], vec![UNSUPPORTED]));
+        assert_eq!(
+            builder.build(),
+            Err(BuildError::UnsupportedValueType {
+                what: "type 1".into()
+            })
+        );
+
+        let mut builder = WasmModuleBuilder::new();
+        let function_type = builder.add_type(WasmFunctionType::default());
+        let locals = vec![WasmLocals::new(1, UNSUPPORTED)];
+        builder.add_function(function_type, FunctionBody::new(locals, vec![0x0b]));
+        assert_eq!(
+            builder.build(),
+            Err(BuildError::UnsupportedValueType {
+                what: "locals of defined function 0".into()
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "functions must be imported before any are defined")]
+    fn test_import_after_function() {
+        let mut builder = WasmModuleBuilder::new();
+        let function_type = builder.add_type(WasmFunctionType::default());
+        builder.add_function(function_type, FunctionBody::new(vec![], vec![0x0b]));
+        builder.add_import("env", "f", WasmImportDescriptor::Function(function_type));
+    }
+}
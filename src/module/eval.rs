@@ -0,0 +1,155 @@
+use crate::module::WasmModule;
+use crate::types::{ConstExpr, WasmImportDescriptor, WasmRef, WasmValue};
+use thiserror::Error;
+
+/// Why the initial value of a global cannot be known before the module is instantiated.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EvalError {
+    #[error("There is no global {index}.")]
+    UnknownGlobal { index: u32 },
+
+    /// The value of an imported global is provided by the host, so only the import is known.
+    #[error("Global {index} is imported from {module_name}.{field_name}; its value is only known once the module is instantiated.")]
+    ImportedGlobal {
+        index: u32,
+        module_name: String,
+        field_name: String,
+    },
+
+    /// A global may only read globals before it, which also rules out cycles.
+    #[error(
+        "Global {index} is initialized from global {referenced}, which does not come before it."
+    )]
+    ForwardReference { index: u32, referenced: u32 },
+}
+
+impl WasmModule<'_> {
+    /// Evaluate the initializer of the global at `index` of the global index space, which
+    /// counts imported globals first. `global.get` is followed through defined globals; the
+    /// value of an imported global is not known, so depending on one is an error.
+    ///
+    /// ```
+    /// use wasm_thing::types::WasmValue;
+    ///
+    /// let bytes = std::fs::read("tests/fixtures/linked.wasm").unwrap();
+    /// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+    ///
+    /// let heap_base = module
+    ///     .exports()
+    ///     .iter()
+    ///     .find(|export| export.name() == "__heap_base")
+    ///     .unwrap();
+    /// let value = module.global_initial_value(heap_base.descriptor().index());
+    /// assert_eq!(value, Ok(WasmValue::I32(1048592)));
+    /// ```
+    pub fn global_initial_value(&self, index: u32) -> Result<WasmValue, EvalError> {
+        let imported = self
+            .imports
+            .iter()
+            .filter(|import| matches!(import.descriptor, WasmImportDescriptor::Global(_)));
+        let num_imported = imported.clone().count() as u32;
+
+        if let Some(import) = imported.clone().nth(index as usize) {
+            return Err(EvalError::ImportedGlobal {
+                index,
+                module_name: import.module_name.to_string(),
+                field_name: import.field_name.to_string(),
+            });
+        }
+
+        let global = self
+            .globals
+            .get((index - num_imported) as usize)
+            .ok_or(EvalError::UnknownGlobal { index })?;
+
+        match global.init {
+            ConstExpr::GlobalGet(referenced) if u32::from(referenced) >= index => {
+                Err(EvalError::ForwardReference {
+                    index,
+                    referenced: referenced.into(),
+                })
+            }
+            init => self.evaluate(init),
+        }
+    }
+
+    /// Evaluate a constant expression, such as the offset of a data segment.
+    pub(crate) fn evaluate(&self, expr: ConstExpr) -> Result<WasmValue, EvalError> {
+        Ok(match expr {
+            ConstExpr::I32Const(value) => WasmValue::I32(value),
+            ConstExpr::I64Const(value) => WasmValue::I64(value),
+            ConstExpr::F32Const(value) => WasmValue::F32(value),
+            ConstExpr::F64Const(value) => WasmValue::F64(value),
+            ConstExpr::GlobalGet(index) => self.global_initial_value(index.into())?,
+            ConstExpr::RefNull(element_type) => WasmValue::Ref(WasmRef::Null(element_type)),
+            ConstExpr::RefFunc(func_index) => WasmValue::Ref(WasmRef::Func(func_index)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvalError;
+    use crate::module::WasmModuleBuilder;
+    use crate::types::{
+        ConstExpr, Global, GlobalType, Mutability, WasmElementType, WasmImportDescriptor, WasmRef,
+        WasmValue, WasmValueType,
+    };
+
+    fn global(init: ConstExpr) -> Global {
+        Global {
+            global_type: GlobalType::new(WasmValueType::I32, Mutability::Immutable),
+            init,
+        }
+    }
+
+    #[test]
+    fn test_global_initial_value() {
+        let mut builder = WasmModuleBuilder::new();
+        builder.add_import(
+            "env",
+            "base",
+            WasmImportDescriptor::Global(GlobalType::new(
+                WasmValueType::I32,
+                Mutability::Immutable,
+            )),
+        );
+        let mut module = builder.build().unwrap();
+        module.globals = vec![
+            global(ConstExpr::I32Const(1024)),
+            global(ConstExpr::GlobalGet(1.into())),
+            global(ConstExpr::GlobalGet(2.into())),
+            global(ConstExpr::GlobalGet(0.into())),
+            global(ConstExpr::GlobalGet(5.into())),
+            global(ConstExpr::RefNull(WasmElementType::Externref)),
+        ];
+
+        // A chain of two globals resolves to the constant at its start.
+        assert_eq!(module.global_initial_value(1), Ok(WasmValue::I32(1024)));
+        assert_eq!(module.global_initial_value(3), Ok(WasmValue::I32(1024)));
+        assert_eq!(
+            module.global_initial_value(6),
+            Ok(WasmValue::Ref(WasmRef::Null(WasmElementType::Externref)))
+        );
+
+        let imported = EvalError::ImportedGlobal {
+            index: 0,
+            module_name: "env".into(),
+            field_name: "base".into(),
+        };
+        assert_eq!(module.global_initial_value(0), Err(imported.clone()));
+        assert_eq!(module.global_initial_value(4), Err(imported));
+        assert_eq!(
+            module.global_initial_value(5),
+            Err(EvalError::ForwardReference {
+                index: 5,
+                referenced: 5
+            })
+        );
+        assert_eq!(
+            module.global_initial_value(7),
+            Err(EvalError::UnknownGlobal { index: 7 })
+        );
+    }
+}
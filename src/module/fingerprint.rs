@@ -0,0 +1,337 @@
+use crate::decode::canonicalize_expression;
+use crate::encode::{
+    encode_code_section, encode_name, encode_preamble, encode_section, encode_sections,
+};
+use crate::encode_module;
+use crate::module::{RawSection, WasmModule};
+use crate::types::{FunctionBody, SectionType};
+use std::borrow::Cow;
+
+impl WasmModule<'_> {
+    /// A SHA-256 hash of what the module does once instantiated: its version and everything
+    /// decoded from its standard sections. Custom sections are left out, and so is how the
+    /// module was encoded, e.g. integers padded with extra bytes. Two modules with the same
+    /// fingerprint are interchangeable for a runtime.
+    ///
+    /// The hash is of the module encoded canonically, as `encode_module` writes a built
+    /// module, so it only changes when the module does: a module already encoded that way,
+    /// without custom sections, has the same fingerprint and raw fingerprint.
+    ///
+    /// ```
+    /// let bytes = std::fs::read("tests/fixtures/linked.wasm").unwrap();
+    /// let mut module = wasm_thing::decode_bytes(&bytes).unwrap();
+    /// let fingerprint = module.fingerprint();
+    ///
+    /// module.remove_custom_section("producers");
+    /// assert_eq!(module.fingerprint(), fingerprint);
+    /// ```
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprint_with(|_| false)
+    }
+
+    /// Like `fingerprint`, but also hashes the name and contents of every custom section for
+    /// whose name `include_custom_section` returns true, e.g. `|name| name == "name"` to tell
+    /// apart modules whose functions are named differently.
+    pub fn fingerprint_with(&self, include_custom_section: impl Fn(&str) -> bool) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.canonical_encoding(include_custom_section));
+        hasher.finish()
+    }
+
+    /// A SHA-256 hash of the module as `encode_module` writes it. For a decoded module, that
+    /// is every byte of its sections, including custom ones, so only modules encoded exactly
+    /// the same have the same raw fingerprint.
+    pub fn raw_fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(&encode_module(self));
+        hasher.finish()
+    }
+
+    /// The module with every standard section re-encoded in its shortest form, followed by
+    /// the custom sections `include_custom_section` picks, in their original order.
+    fn canonical_encoding(&self, include_custom_section: impl Fn(&str) -> bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_preamble(self.version, &mut out);
+
+        // The start function is not decoded into the module; if its index is malformed, its
+        // bytes are all there is.
+        let start_function = self.start_function();
+        for section in encode_sections(self, start_function.as_ref().ok().copied().flatten()) {
+            let RawSection::Standard {
+                section_type,
+                contents,
+            } = section
+            else {
+                continue;
+            };
+            let contents = match section_type {
+                SectionType::Code => {
+                    let mut code = Vec::new();
+                    encode_code_section(&self.canonical_code(), &mut code);
+                    Cow::Owned(code)
+                }
+                _ => contents,
+            };
+            encode_section(section_type, &contents, &mut out);
+        }
+
+        for section in &self.raw_sections {
+            match section {
+                RawSection::Standard {
+                    section_type: SectionType::Start,
+                    contents,
+                } if start_function.is_err() => {
+                    encode_section(SectionType::Start, contents, &mut out)
+                }
+                RawSection::Custom(section) if include_custom_section(section.name()) => {
+                    let mut contents = Vec::new();
+                    encode_name(section.name(), &mut contents);
+                    contents.extend_from_slice(section.data());
+                    encode_section(SectionType::Custom, &contents, &mut out);
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+
+    /// The function bodies with their instructions in their shortest form.
+    fn canonical_code(&self) -> Vec<FunctionBody<'_>> {
+        self.code
+            .iter()
+            .map(|body| FunctionBody {
+                locals: body.locals.clone(),
+                // Only bodies that were never decoded can fail; their bytes are all there is.
+                expression: match canonicalize_expression(&body.expression) {
+                    Ok(expression) => Cow::Owned(expression),
+                    Err(_) => Cow::Borrowed(&body.expression[..]),
+                },
+            })
+            .collect()
+    }
+}
+
+/// The round constants of SHA-256.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// A minimal SHA-256 (FIPS 180-4), so fingerprints need no dependencies.
+struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    /// How many bytes of `block` are filled.
+    filled: usize,
+    /// How many bytes were hashed in total.
+    length: u64,
+}
+
+impl Sha256 {
+    fn new() -> Self {
+        Sha256 {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            block: [0; 64],
+            filled: 0,
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+        while !bytes.is_empty() {
+            let taken = bytes.len().min(64 - self.filled);
+            self.block[self.filled..self.filled + taken].copy_from_slice(&bytes[..taken]);
+            self.filled += taken;
+            bytes = &bytes[taken..];
+            if self.filled == 64 {
+                self.compress();
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; 32] {
+        let bit_length = self.length * 8;
+        self.update(&[0x80]);
+        while self.filled != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bit_length.to_be_bytes());
+
+        let mut hash = [0; 32];
+        for (bytes, word) in hash.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        hash
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (word, bytes) in w.iter_mut().zip(self.block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for (k, w) in K.iter().zip(w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(w);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (word, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+        self.filled = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sha256;
+    use crate::decode::{decode_bytes_with_config, DecoderConfig};
+    use crate::encode::ModuleBuilder;
+    use crate::module::RawSection;
+    use crate::types::SectionType;
+    use crate::types::WasmValueType;
+    use crate::{decode_bytes, encode_module};
+
+    fn sha256(bytes: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        hasher
+            .finish()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks once padded.
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            sha256(&[b'a'; 1000]),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_custom_sections() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[WasmValueType::I32])
+            .add_function(0, &[], &[0x41, 0x2a, 0x0b])
+            .build();
+        let plain = decode_bytes(&bytes).unwrap();
+        let mut debug = plain.clone();
+        debug.set_custom_section("debug", &b"main.c:1"[..]);
+        let debug_bytes = encode_module(&debug);
+        let debug = decode_bytes(&debug_bytes).unwrap();
+
+        assert_eq!(plain.fingerprint(), debug.fingerprint());
+        assert_ne!(plain.raw_fingerprint(), debug.raw_fingerprint());
+
+        let include_debug = |name: &str| name == "debug";
+        assert_ne!(
+            plain.fingerprint_with(include_debug),
+            debug.fingerprint_with(include_debug)
+        );
+        assert_eq!(plain.fingerprint_with(include_debug), plain.fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_leb128_padding() {
+        let build = |body: &[u8]| {
+            ModuleBuilder::new()
+                .add_type(&[], &[WasmValueType::I32])
+                .add_function(0, &[], body)
+                .build()
+        };
+        let config = DecoderConfig::new().strict_leb128(false);
+        // `i32.const 42`, then `i32.const 42` with its immediate padded to three bytes.
+        let canonical = build(&[0x41, 0x2a, 0x0b]);
+        let padded = build(&[0x41, 0xaa, 0x80, 0x00, 0x0b]);
+        let different = build(&[0x41, 0x2b, 0x0b]);
+        let canonical = decode_bytes_with_config(&canonical, &config).unwrap();
+        let padded = decode_bytes_with_config(&padded, &config).unwrap();
+        let different = decode_bytes_with_config(&different, &config).unwrap();
+
+        assert_eq!(canonical.fingerprint(), padded.fingerprint());
+        assert_ne!(canonical.raw_fingerprint(), padded.raw_fingerprint());
+        assert_ne!(canonical.fingerprint(), different.fingerprint());
+        // The fingerprint is a hash of the canonical encoding, which `canonical` already has.
+        assert_eq!(canonical.fingerprint(), canonical.raw_fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_start_padding() {
+        let build = |start| {
+            ModuleBuilder::new()
+                .add_type(&[], &[])
+                .add_function(0, &[], &[0x0b])
+                .add_function(0, &[], &[0x0b])
+                .set_start(start)
+                .build()
+        };
+        let [canonical, different] = [build(0), build(1)];
+        let canonical = decode_bytes(&canonical).unwrap();
+        let mut padded = canonical.clone();
+        for section in &mut padded.raw_sections {
+            if let RawSection::Standard {
+                section_type: SectionType::Start,
+                contents,
+            } = section
+            {
+                *contents = vec![0x80, 0x80, 0x00].into();
+            }
+        }
+        let different = decode_bytes(&different).unwrap();
+
+        assert_ne!(canonical.raw_fingerprint(), padded.raw_fingerprint());
+        assert_eq!(canonical.fingerprint(), padded.fingerprint());
+        assert_ne!(canonical.fingerprint(), different.fingerprint());
+    }
+}
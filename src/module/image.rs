@@ -0,0 +1,548 @@
+use crate::module::{EvalError, WasmModule};
+use crate::types::{DataMode, ElementItems, ElementMode, FuncIndex, WasmRef, WasmValue};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// The size of a page of linear memory, in bytes.
+pub const PAGE_SIZE: u64 = 64 * 1024;
+
+/// Why the initial contents of a memory cannot be worked out from the module alone.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum MemoryImageError {
+    #[error("There is no memory {index}.")]
+    UnknownMemory { index: u32 },
+
+    #[error("The offset of data segment {segment} cannot be evaluated: {source}")]
+    Offset {
+        segment: u32,
+        #[source]
+        source: EvalError,
+    },
+
+    /// Offsets into a 32-bit memory are `i32`s, and into a 64-bit memory `i64`s.
+    #[error(
+        "The offset of data segment {segment} is {found:?}, not of the index type of its memory."
+    )]
+    OffsetType { segment: u32, found: WasmValue },
+
+    /// `end` is one past the last byte of the segment; `size` is the initial size of the
+    /// memory, in bytes.
+    #[error("Data segment {segment} ends at {end:#x}, beyond the {size:#x} bytes of its memory.")]
+    OutOfBounds { segment: u32, end: u64, size: u64 },
+}
+
+/// The most elements `WasmModule::table_image` allocates for a table, the limit the JavaScript
+/// API sets on the size of tables.
+pub const MAX_TABLE_IMAGE_LEN: u64 = 10_000_000;
+
+/// Why the initial contents of a table cannot be worked out from the module alone.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TableImageError {
+    #[error("There is no table {index}.")]
+    UnknownTable { index: u32 },
+
+    #[error("The offset of element segment {segment} cannot be evaluated: {source}")]
+    Offset {
+        segment: u32,
+        #[source]
+        source: EvalError,
+    },
+
+    /// Offsets into a 32-bit table are `i32`s, and into a 64-bit table `i64`s.
+    #[error(
+        "The offset of element segment {segment} is {found:?}, not of the index type of its table."
+    )]
+    OffsetType { segment: u32, found: WasmValue },
+
+    /// The minimum size of the table is more than `MAX_TABLE_IMAGE_LEN` elements.
+    #[error("Table {index} starts out with {size} elements, more than the {limit} an image is built for.")]
+    TooLarge { index: u32, size: u64, limit: u64 },
+
+    /// `end` is one past the last element of the segment; `size` is the initial size of the
+    /// table.
+    #[error("Element segment {segment} ends at {end}, beyond the {size} elements of its table.")]
+    OutOfBounds { segment: u32, end: u64, size: u64 },
+
+    #[error("Element {item} of element segment {segment} cannot be evaluated: {source}")]
+    Element {
+        segment: u32,
+        item: u32,
+        #[source]
+        source: EvalError,
+    },
+
+    #[error("Element {item} of element segment {segment} is {found:?}, not a reference.")]
+    ElementType {
+        segment: u32,
+        item: u32,
+        found: WasmValue,
+    },
+}
+
+/// The bytes the active data segments copy into a memory when the module is instantiated; see
+/// `WasmModule::memory_image`. Bytes outside of the segments start out zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryImage {
+    segments: Vec<(u64, Vec<u8>)>,
+    overlaps: Vec<(u32, u32)>,
+}
+
+impl MemoryImage {
+    /// The initialized bytes as runs of `(address, bytes)`, sorted by address. Runs neither
+    /// overlap nor touch; adjacent segments are merged.
+    pub fn segments(&self) -> &[(u64, Vec<u8>)] {
+        &self.segments
+    }
+
+    /// Pairs of data segment indices `(earlier, later)` where the later segment overwrites
+    /// bytes of the earlier one. That is well defined, as segments are copied in order, but
+    /// rarely intended.
+    pub fn overlaps(&self) -> &[(u32, u32)] {
+        &self.overlaps
+    }
+
+    /// The memory from address 0 up to and including its last initialized byte.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let len = self
+            .segments
+            .last()
+            .map_or(0, |(address, bytes)| address + bytes.len() as u64);
+
+        let mut memory = vec![0; len as usize];
+        for (address, bytes) in &self.segments {
+            let start = *address as usize;
+            memory[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+        memory
+    }
+}
+
+impl WasmModule<'_> {
+    /// Work out the initial contents of the memory at `mem_index` of the memory index space
+    /// from the active data segments, without instantiating the module. Every segment has to
+    /// fit in the minimum size of the memory, and its offset must not depend on an imported
+    /// global.
+    ///
+    /// ```
+    /// let bytes = std::fs::read("tests/fixtures/wasi_hello.wasm").unwrap();
+    /// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+    ///
+    /// let image = module.memory_image(0).unwrap();
+    /// assert_eq!(image.segments(), [(8, b"hello, world\n".to_vec())]);
+    /// assert_eq!(&image.to_vec()[8..], b"hello, world\n");
+    /// ```
+    pub fn memory_image(&self, mem_index: u32) -> Result<MemoryImage, MemoryImageError> {
+        let memory = self
+            .memory(mem_index)
+            .ok_or(MemoryImageError::UnknownMemory { index: mem_index })?;
+        let size = memory.limits.min.saturating_mul(PAGE_SIZE);
+
+        // The segment that last wrote each range of addresses, by the start of the range:
+        // the end of the range, the index of the segment and the address the segment starts at.
+        let mut owners: BTreeMap<u64, (u64, u32, u64)> = BTreeMap::new();
+        let mut overlaps = Vec::new();
+
+        for (segment, data) in self.data.iter().enumerate() {
+            let segment = segment as u32;
+            let offset = match data.mode {
+                DataMode::Active {
+                    memory_index,
+                    offset,
+                } if u32::from(memory_index) == mem_index => offset,
+                _ => continue,
+            };
+
+            let start = match (self.evaluate(offset), memory.memory64) {
+                // The offset is an unsigned address.
+                (Ok(WasmValue::I32(start)), false) => start as u32 as u64,
+                (Ok(WasmValue::I64(start)), true) => start as u64,
+                (Ok(found), _) => return Err(MemoryImageError::OffsetType { segment, found }),
+                (Err(source), _) => return Err(MemoryImageError::Offset { segment, source }),
+            };
+            let end = start.saturating_add(data.data.len() as u64);
+            if end > size {
+                return Err(MemoryImageError::OutOfBounds { segment, end, size });
+            }
+            if start == end {
+                continue;
+            }
+
+            // Cut whatever the segment overwrites out of the ranges of earlier segments.
+            let first_overlapping = owners
+                .range(..start)
+                .next_back()
+                .filter(|(_, &(owner_end, ..))| owner_end > start)
+                .map_or(start, |(&owner_start, _)| owner_start);
+            let overwritten: Vec<_> = owners
+                .range(first_overlapping..end)
+                .map(|(&owner_start, &owner)| (owner_start, owner))
+                .collect();
+
+            for (owner_start, (owner_end, owner, owner_address)) in overwritten {
+                owners.remove(&owner_start);
+                if owner_start < start {
+                    owners.insert(owner_start, (start, owner, owner_address));
+                }
+                if owner_end > end {
+                    owners.insert(end, (owner_end, owner, owner_address));
+                }
+                if !overlaps.contains(&(owner, segment)) {
+                    overlaps.push((owner, segment));
+                }
+            }
+            owners.insert(start, (end, segment, start));
+        }
+
+        let mut segments: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (start, (end, owner, owner_address)) in owners {
+            let skip = (start - owner_address) as usize;
+            let bytes = &self.data[owner as usize].data[skip..skip + (end - start) as usize];
+
+            match segments.last_mut() {
+                Some((address, run)) if *address + run.len() as u64 == start => {
+                    run.extend_from_slice(bytes)
+                }
+                _ => segments.push((start, bytes.to_vec())),
+            }
+        }
+
+        Ok(MemoryImage { segments, overlaps })
+    }
+}
+
+impl WasmModule<'_> {
+    /// Work out the initial contents of the table at `table_index` of the table index space
+    /// from the active element segments, without instantiating the module: the function
+    /// index held by each element of the table, or `None` for null. The image has the `min`
+    /// elements the table starts out with, so a table larger than `MAX_TABLE_IMAGE_LEN` is
+    /// refused rather than allocated.
+    ///
+    /// ```
+    /// use wasm_thing::decode::DecoderConfig;
+    ///
+    /// // The linker pads the relocated integers in function bodies.
+    /// let bytes = std::fs::read("tests/fixtures/vtable.wasm").unwrap();
+    /// let config = DecoderConfig::new().strict_leb128(false);
+    /// let module = wasm_thing::decode_bytes_with_config(&bytes, &config).unwrap();
+    ///
+    /// // The functions `call_indirect` can reach.
+    /// let table = module.table_image(0).unwrap();
+    /// let reachable: Vec<_> = table.into_iter().flatten().collect();
+    /// assert_eq!(reachable.len(), 4);
+    /// ```
+    pub fn table_image(&self, table_index: u32) -> Result<Vec<Option<FuncIndex>>, TableImageError> {
+        let table = self
+            .table(table_index)
+            .ok_or(TableImageError::UnknownTable { index: table_index })?;
+        let size = table.limits.min;
+        if size > MAX_TABLE_IMAGE_LEN {
+            return Err(TableImageError::TooLarge {
+                index: table_index,
+                size,
+                limit: MAX_TABLE_IMAGE_LEN,
+            });
+        }
+        let mut elements = vec![None; size as usize];
+
+        for (segment, element) in self.elements.iter().enumerate() {
+            let segment = segment as u32;
+            let offset = match element.mode {
+                ElementMode::Active {
+                    table_index: index,
+                    offset,
+                } if u32::from(index) == table_index => offset,
+                _ => continue,
+            };
+
+            let start = match (self.evaluate(offset), table.table64) {
+                (Ok(WasmValue::I32(start)), false) => start as u32 as u64,
+                (Ok(WasmValue::I64(start)), true) => start as u64,
+                (Ok(found), _) => return Err(TableImageError::OffsetType { segment, found }),
+                (Err(source), _) => return Err(TableImageError::Offset { segment, source }),
+            };
+            let end = start.saturating_add(element.items.len() as u64);
+            if end > size {
+                return Err(TableImageError::OutOfBounds { segment, end, size });
+            }
+
+            let slots = elements[start as usize..].iter_mut();
+            match &element.items {
+                ElementItems::Functions(indices) => {
+                    for (slot, &func_index) in slots.zip(indices) {
+                        *slot = Some(func_index);
+                    }
+                }
+                ElementItems::Expressions(exprs) => {
+                    for (item, (slot, &expr)) in slots.zip(exprs).enumerate() {
+                        let item = item as u32;
+                        *slot = match self.evaluate(expr) {
+                            Ok(WasmValue::Ref(WasmRef::Func(func_index))) => Some(func_index),
+                            Ok(WasmValue::Ref(WasmRef::Null(_))) => None,
+                            Ok(found) => {
+                                return Err(TableImageError::ElementType {
+                                    segment,
+                                    item,
+                                    found,
+                                })
+                            }
+                            Err(source) => {
+                                return Err(TableImageError::Element {
+                                    segment,
+                                    item,
+                                    source,
+                                })
+                            }
+                        };
+                    }
+                }
+            }
+        }
+
+        Ok(elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryImageError, TableImageError, MAX_TABLE_IMAGE_LEN, PAGE_SIZE};
+    use crate::module::{EvalError, WasmModuleBuilder};
+    use crate::types::{
+        ConstExpr, DataMode, ElementItems, ElementMode, ElementSegment, GlobalType, Mutability,
+        TableType, WasmElementType, WasmImportDescriptor, WasmLimits, WasmValue, WasmValueType,
+    };
+
+    #[test]
+    fn test_memory_image_separate_segments() {
+        let mut builder = WasmModuleBuilder::new();
+        let memory = builder.add_memory(WasmLimits::new(1, None));
+        builder.add_data_segment(memory, ConstExpr::I32Const(16), b"world".to_vec());
+        builder.add_data_segment(memory, ConstExpr::I32Const(4), b"hello".to_vec());
+        // Adjacent to the first segment, so merged with it.
+        builder.add_data_segment(memory, ConstExpr::I32Const(21), b"!".to_vec());
+        let module = builder.build().unwrap();
+
+        let image = module.memory_image(0).unwrap();
+        assert_eq!(
+            image.segments(),
+            [(4, b"hello".to_vec()), (16, b"world!".to_vec())]
+        );
+        assert!(image.overlaps().is_empty());
+        assert_eq!(image.to_vec(), b"\0\0\0\0hello\0\0\0\0\0\0\0world!");
+    }
+
+    #[test]
+    fn test_memory_image_overlapping_segments() {
+        let mut builder = WasmModuleBuilder::new();
+        let memory = builder.add_memory(WasmLimits::new(1, None));
+        builder.add_data_segment(memory, ConstExpr::I32Const(0), b"aaaaaaaa".to_vec());
+        builder.add_data_segment(memory, ConstExpr::I32Const(2), b"bbb".to_vec());
+        builder.add_data_segment(memory, ConstExpr::I32Const(4), b"cccccc".to_vec());
+        let module = builder.build().unwrap();
+
+        // Later segments win.
+        let image = module.memory_image(0).unwrap();
+        assert_eq!(image.segments(), [(0, b"aabbcccccc".to_vec())]);
+        assert_eq!(image.overlaps(), [(0, 1), (1, 2), (0, 2)]);
+    }
+
+    #[test]
+    fn test_memory_image_errors() {
+        let mut builder = WasmModuleBuilder::new();
+        builder.add_import(
+            "env",
+            "__memory_base",
+            WasmImportDescriptor::Global(GlobalType::new(
+                WasmValueType::I32,
+                Mutability::Immutable,
+            )),
+        );
+        let memory = builder.add_memory(WasmLimits::new(1, None));
+        builder.add_data_segment(memory, ConstExpr::GlobalGet(0.into()), b"data".to_vec());
+        let module = builder.build().unwrap();
+
+        assert!(matches!(
+            module.memory_image(0),
+            Err(MemoryImageError::Offset {
+                segment: 0,
+                source: EvalError::ImportedGlobal { index: 0, .. },
+            })
+        ));
+        assert_eq!(
+            module.memory_image(1),
+            Err(MemoryImageError::UnknownMemory { index: 1 })
+        );
+
+        let mut builder = WasmModuleBuilder::new();
+        let memory = builder.add_memory(WasmLimits::new(1, None));
+        builder.add_data_segment(memory, ConstExpr::I32Const(-2), b"data".to_vec());
+        let module = builder.build().unwrap();
+
+        // The offset is unsigned, so -2 is just below 4 GiB.
+        assert_eq!(
+            module.memory_image(0),
+            Err(MemoryImageError::OutOfBounds {
+                segment: 0,
+                end: (1 << 32) + 2,
+                size: PAGE_SIZE,
+            })
+        );
+    }
+
+    #[test]
+    fn test_memory_image_memory64() {
+        let mut builder = WasmModuleBuilder::new();
+        let memory = builder.add_memory(WasmLimits::new(1, None));
+        builder.add_data_segment(memory, ConstExpr::I64Const(4), b"hello".to_vec());
+        let mut module = builder.build().unwrap();
+        module.memories[0].memory64 = true;
+
+        // Offsets into a 64-bit memory are `i64`s.
+        let image = module.memory_image(0).unwrap();
+        assert_eq!(image.segments(), [(4, b"hello".to_vec())]);
+
+        module.data[0].mode = DataMode::Active {
+            memory_index: memory,
+            offset: ConstExpr::I32Const(4),
+        };
+        assert_eq!(
+            module.memory_image(0),
+            Err(MemoryImageError::OffsetType {
+                segment: 0,
+                found: WasmValue::I32(4),
+            })
+        );
+    }
+
+    #[test]
+    fn test_table_image_out_of_bounds() {
+        let mut module = WasmModuleBuilder::new().build().unwrap();
+        module.tables = vec![TableType::new(
+            WasmElementType::Funcref,
+            WasmLimits::new(4, None),
+        )];
+        let segment = |offset, indices: &[u32]| ElementSegment {
+            mode: ElementMode::Active {
+                table_index: 0.into(),
+                offset: ConstExpr::I32Const(offset),
+            },
+            element_type: WasmElementType::Funcref,
+            items: ElementItems::Functions(indices.iter().map(|&index| index.into()).collect()),
+        };
+        module.elements = vec![segment(0, &[7]), segment(2, &[8, 9])];
+        assert_eq!(
+            module.table_image(0),
+            Ok(vec![Some(7.into()), None, Some(8.into()), Some(9.into())])
+        );
+
+        module.elements.push(segment(3, &[10, 11]));
+        assert_eq!(
+            module.table_image(0),
+            Err(TableImageError::OutOfBounds {
+                segment: 2,
+                end: 5,
+                size: 4
+            })
+        );
+        assert_eq!(
+            module.table_image(1),
+            Err(TableImageError::UnknownTable { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_table_image_huge_table() {
+        let mut module = WasmModuleBuilder::new().build().unwrap();
+        let table64 = |min| TableType {
+            table64: true,
+            ..TableType::new(WasmElementType::Funcref, WasmLimits::new(min, None))
+        };
+        module.tables = vec![
+            TableType::new(WasmElementType::Funcref, WasmLimits::new(0xffff_ffff, None)),
+            table64(u64::MAX),
+            table64(4),
+        ];
+        assert_eq!(
+            module.table_image(0),
+            Err(TableImageError::TooLarge {
+                index: 0,
+                size: 0xffff_ffff,
+                limit: MAX_TABLE_IMAGE_LEN,
+            })
+        );
+        assert!(matches!(
+            module.table_image(1),
+            Err(TableImageError::TooLarge { index: 1, .. })
+        ));
+
+        // Offsets into a 64-bit table are `i64`s.
+        let segment = |offset| ElementSegment {
+            mode: ElementMode::Active {
+                table_index: 2.into(),
+                offset,
+            },
+            element_type: WasmElementType::Funcref,
+            items: ElementItems::Functions(vec![5.into()]),
+        };
+        module.elements = vec![segment(ConstExpr::I64Const(2))];
+        assert_eq!(
+            module.table_image(2),
+            Ok(vec![None, None, Some(5.into()), None])
+        );
+
+        module.elements = vec![segment(ConstExpr::I32Const(2))];
+        assert_eq!(
+            module.table_image(2),
+            Err(TableImageError::OffsetType {
+                segment: 0,
+                found: WasmValue::I32(2),
+            })
+        );
+
+        module.elements = vec![segment(ConstExpr::I64Const(-1))];
+        assert_eq!(
+            module.table_image(2),
+            Err(TableImageError::OutOfBounds {
+                segment: 0,
+                end: u64::MAX,
+                size: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_table_image_expressions() {
+        let mut module = WasmModuleBuilder::new().build().unwrap();
+        module.tables = vec![TableType::new(
+            WasmElementType::Funcref,
+            WasmLimits::new(3, None),
+        )];
+        module.elements = vec![ElementSegment {
+            mode: ElementMode::Active {
+                table_index: 0.into(),
+                offset: ConstExpr::I32Const(0),
+            },
+            element_type: WasmElementType::Funcref,
+            items: ElementItems::Expressions(vec![
+                ConstExpr::RefFunc(4.into()),
+                ConstExpr::RefNull(WasmElementType::Funcref),
+                ConstExpr::RefFunc(1.into()),
+            ]),
+        }];
+        assert_eq!(
+            module.table_image(0),
+            Ok(vec![Some(4.into()), None, Some(1.into())])
+        );
+
+        module.elements[0].items = ElementItems::Expressions(vec![ConstExpr::I32Const(1)]);
+        assert_eq!(
+            module.table_image(0),
+            Err(TableImageError::ElementType {
+                segment: 0,
+                item: 0,
+                found: WasmValue::I32(1)
+            })
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::module::{FunctionRef, WasmModule};
+use crate::types::{ExternalKind, WasmImportDescriptor};
+
+/// Where an item of an index space comes from: the `n`th import of its kind, or the `n`th
+/// definition of its kind in the module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexOrigin {
+    Imported(u32),
+    Defined(u32),
+}
+
+/// How many items of one kind a module imports and defines. Imports come first, so indices
+/// below `imported` are those of imports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IndexSpace {
+    pub(crate) imported: u32,
+    pub(crate) defined: u32,
+}
+
+impl IndexSpace {
+    pub fn imported(&self) -> u32 {
+        self.imported
+    }
+
+    pub fn defined(&self) -> u32 {
+        self.defined
+    }
+
+    pub fn len(&self) -> u32 {
+        self.imported + self.defined
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Where the item at `index` comes from, or `None` if the space has no such item.
+    pub fn origin(&self, index: u32) -> Option<IndexOrigin> {
+        if index < self.imported {
+            Some(IndexOrigin::Imported(index))
+        } else if index < self.len() {
+            Some(IndexOrigin::Defined(index - self.imported))
+        } else {
+            None
+        }
+    }
+}
+
+/// The function, table, memory and global index spaces of a module; see
+/// `WasmModule::index_spaces`.
+///
+/// ```
+/// let bytes = std::fs::read("tests/fixtures/imports.wasm").unwrap();
+/// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+/// let spaces = module.index_spaces();
+///
+/// assert_eq!((spaces.functions().imported(), spaces.memories().imported()), (1, 1));
+/// assert!(spaces.resolve_function(spaces.functions().len()).is_none());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct IndexSpaces<'m, 'a> {
+    module: &'m WasmModule<'a>,
+    functions: IndexSpace,
+    tables: IndexSpace,
+    memories: IndexSpace,
+    globals: IndexSpace,
+}
+
+impl<'m, 'a> IndexSpaces<'m, 'a> {
+    pub(crate) fn new(module: &'m WasmModule<'a>) -> Self {
+        let counts = module.import_counts();
+        let space = |imported, defined: usize| IndexSpace {
+            imported,
+            defined: defined as u32,
+        };
+
+        IndexSpaces {
+            module,
+            functions: space(counts.functions, module.functions.len()),
+            tables: space(counts.tables, module.tables.len()),
+            memories: space(counts.memories, module.memories.len()),
+            globals: space(counts.globals, module.globals.len()),
+        }
+    }
+
+    pub fn functions(&self) -> IndexSpace {
+        self.functions
+    }
+
+    pub fn tables(&self) -> IndexSpace {
+        self.tables
+    }
+
+    pub fn memories(&self) -> IndexSpace {
+        self.memories
+    }
+
+    pub fn globals(&self) -> IndexSpace {
+        self.globals
+    }
+
+    /// The index space of items of `kind`.
+    pub fn of_kind(&self, kind: ExternalKind) -> IndexSpace {
+        match kind {
+            ExternalKind::Function => self.functions,
+            ExternalKind::Table => self.tables,
+            ExternalKind::Memory => self.memories,
+            ExternalKind::Global => self.globals,
+        }
+    }
+
+    /// The function at `index` in the function index space, or `None` if there is none.
+    pub fn resolve_function(&self, index: u32) -> Option<FunctionRef<'m, 'a>> {
+        match self.functions.origin(index)? {
+            IndexOrigin::Imported(nth) => self
+                .module
+                .imports
+                .iter()
+                .filter(|import| matches!(import.descriptor, WasmImportDescriptor::Function(_)))
+                .nth(nth as usize)
+                .map(FunctionRef::Imported),
+            IndexOrigin::Defined(nth) => {
+                let type_index = *self.module.functions.get(nth as usize)?;
+                Some(FunctionRef::Defined {
+                    type_index,
+                    body: self.module.code.get(nth as usize),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IndexOrigin, IndexSpace};
+    use crate::decode_bytes;
+    use crate::encode::{func_type, memory_type, ModuleBuilder};
+    use crate::module::FunctionRef;
+    use crate::types::{ExternalKind, GlobalType, Mutability, WasmImportDescriptor, WasmValueType};
+
+    #[test]
+    fn test_index_spaces() {
+        let global = GlobalType::new(WasmValueType::I32, Mutability::Immutable);
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_import("env", "a", func_type(0))
+            .add_import("env", "memory", memory_type(1))
+            .add_import("env", "b", func_type(0))
+            .add_import("env", "g", WasmImportDescriptor::Global(global))
+            .add_function(0, &[], &[0x0b])
+            .add_function(0, &[], &[0x01, 0x0b])
+            .add_function(0, &[], &[0x01, 0x01, 0x0b])
+            .add_memory(2)
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+        let spaces = module.index_spaces();
+
+        let space = |imported, defined| IndexSpace { imported, defined };
+        assert_eq!(spaces.functions, space(2, 3));
+        assert_eq!(spaces.tables, space(0, 0));
+        assert_eq!(spaces.memories, space(1, 1));
+        assert_eq!(spaces.globals, space(1, 0));
+        assert!(spaces.tables.is_empty());
+        assert_eq!(spaces.of_kind(ExternalKind::Memory), spaces.memories);
+
+        // The boundary between imports and definitions.
+        assert_eq!(spaces.functions.origin(1), Some(IndexOrigin::Imported(1)));
+        assert_eq!(spaces.functions.origin(2), Some(IndexOrigin::Defined(0)));
+        assert_eq!(spaces.functions.origin(4), Some(IndexOrigin::Defined(2)));
+        assert_eq!(spaces.functions.origin(5), None);
+        assert_eq!(spaces.memories.origin(1), Some(IndexOrigin::Defined(0)));
+        assert_eq!(spaces.globals.origin(1), None);
+
+        match spaces.resolve_function(1) {
+            Some(FunctionRef::Imported(import)) => assert_eq!(import.field_name(), "b"),
+            other => panic!("function 1: {other:?}"),
+        }
+        match spaces.resolve_function(2) {
+            Some(FunctionRef::Defined {
+                type_index,
+                body: Some(body),
+            }) if u32::from(type_index) == 0 => assert_eq!(body.expression(), [0x0b]),
+            other => panic!("function 2: {other:?}"),
+        }
+        assert_eq!(spaces.resolve_function(5), None);
+    }
+}
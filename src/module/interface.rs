@@ -0,0 +1,214 @@
+use crate::module::{ExportRef, WasmModule};
+use crate::types::{
+    ExternalKind, GlobalType, MemoryType, TableType, WasmFunctionType, WasmImportDescriptor,
+    WasmImportEntry,
+};
+
+/// What a module needs from and offers to the outside, without its code or data; see
+/// `WasmModule::interface`. Exports are kept with what they resolve to rather than their
+/// indices, so two builds of the same program have equal interfaces even if their functions
+/// are laid out differently.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleInterface {
+    pub(crate) types: Vec<WasmFunctionType>,
+    pub(crate) imports: Vec<WasmImportEntry<'static>>,
+    pub(crate) exports: Vec<InterfaceExport>,
+}
+
+/// An export of a `ModuleInterface`, by name.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterfaceExport {
+    pub(crate) name: String,
+    pub(crate) extern_type: ExternType,
+}
+
+impl InterfaceExport {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn extern_type(&self) -> &ExternType {
+        &self.extern_type
+    }
+}
+
+/// The type of an exported item: an owned `ExportRef`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExternType {
+    /// The signature of the exported function.
+    Function(WasmFunctionType),
+    Table(TableType),
+    Memory(MemoryType),
+    Global(GlobalType),
+}
+
+impl ExternType {
+    pub fn kind(&self) -> ExternalKind {
+        match self {
+            ExternType::Function(_) => ExternalKind::Function,
+            ExternType::Table(_) => ExternalKind::Table,
+            ExternType::Memory(_) => ExternalKind::Memory,
+            ExternType::Global(_) => ExternalKind::Global,
+        }
+    }
+}
+
+impl From<ExportRef<'_>> for ExternType {
+    fn from(export: ExportRef<'_>) -> Self {
+        match export {
+            ExportRef::Function(signature) => ExternType::Function(signature.clone()),
+            ExportRef::Table(table_type) => ExternType::Table(table_type.clone()),
+            ExportRef::Memory(memory_type) => ExternType::Memory(memory_type.clone()),
+            ExportRef::Global(global_type) => ExternType::Global(global_type.clone()),
+        }
+    }
+}
+
+impl ModuleInterface {
+    /// The type section of the module, which the type indices of function imports refer to.
+    pub fn types(&self) -> &[WasmFunctionType] {
+        &self.types
+    }
+
+    pub fn imports(&self) -> &[WasmImportEntry<'static>] {
+        &self.imports
+    }
+
+    pub fn exports(&self) -> &[InterfaceExport] {
+        &self.exports
+    }
+
+    /// The type of the first export named exactly `name`; see `WasmModule::export`.
+    pub fn export(&self, name: &str) -> Option<&ExternType> {
+        self.exports
+            .iter()
+            .find(|export| export.name == name)
+            .map(InterfaceExport::extern_type)
+    }
+
+    /// The imports whose module name is `module_name`; see `WasmModule::imports_from`.
+    pub fn imports_from<'s>(
+        &'s self,
+        module_name: &'s str,
+    ) -> impl Iterator<Item = &'s WasmImportEntry<'static>> + 's {
+        self.imports
+            .iter()
+            .filter(move |import| import.module_name == module_name)
+    }
+
+    /// The signature of an imported function, looked up in `types`. `None` if the import is
+    /// not a function, or its type index is out of range.
+    pub fn import_signature(&self, import: &WasmImportEntry) -> Option<&WasmFunctionType> {
+        match import.descriptor {
+            WasmImportDescriptor::Function(type_index) => {
+                self.types.get(u32::from(type_index) as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl WasmModule<'_> {
+    /// The types, imports and exports of the module, copied out so the module itself can be
+    /// dropped. Exports whose index is out of range are left out.
+    ///
+    /// ```
+    /// use wasm_thing::module::ExternType;
+    ///
+    /// let bytes = std::fs::read("tests/fixtures/add.wasm").unwrap();
+    /// let interface = wasm_thing::decode_bytes(&bytes).unwrap().interface();
+    ///
+    /// let Some(ExternType::Function(signature)) = interface.export("add") else {
+    ///     panic!("add is not an exported function");
+    /// };
+    /// assert_eq!(signature.to_string(), "(i32, i32) -> i32");
+    /// ```
+    pub fn interface(&self) -> ModuleInterface {
+        ModuleInterface {
+            types: self.types.clone(),
+            imports: self
+                .imports
+                .iter()
+                .map(|import| import.clone().into_owned())
+                .collect(),
+            exports: self
+                .resolved_exports()
+                .filter_map(|(export, resolved)| {
+                    Some(InterfaceExport {
+                        name: export.name.to_string(),
+                        extern_type: resolved?.into(),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExternType;
+    use crate::decode_bytes;
+    use crate::encode::{func_type, memory_type, ModuleBuilder};
+    use crate::types::{WasmExportDescriptor, WasmFunctionType, WasmValueType};
+
+    /// The same program built twice: `helper` and `run` swap places, and `run` is compiled
+    /// differently, so only the indices and code differ.
+    fn builds() -> [ModuleBuilder; 2] {
+        use WasmValueType::I32;
+
+        let first = ModuleBuilder::new()
+            .add_type(&[I32], &[I32])
+            .add_type(&[], &[])
+            .add_import("env", "log", func_type(0))
+            .add_import("env", "memory", memory_type(1))
+            .add_function(0, &[], &[0x20, 0x00, 0x0b])
+            .add_function(1, &[], &[0x0b])
+            .add_export("run", WasmExportDescriptor::Function(2.into()));
+        let second = ModuleBuilder::new()
+            .add_type(&[I32], &[I32])
+            .add_type(&[], &[])
+            .add_import("env", "log", func_type(0))
+            .add_import("env", "memory", memory_type(1))
+            .add_function(1, &[], &[0x01, 0x0b])
+            .add_function(0, &[], &[0x20, 0x00, 0x0b])
+            .add_export("run", WasmExportDescriptor::Function(1.into()));
+        [first, second]
+    }
+
+    #[test]
+    fn test_interface() {
+        let [first, second] = builds().map(|builder| builder.build());
+        assert_ne!(first, second);
+        let first = decode_bytes(&first).unwrap().interface();
+        let second = decode_bytes(&second).unwrap().interface();
+        assert_eq!(first, second);
+
+        assert_eq!(
+            first.export("run"),
+            Some(&ExternType::Function(WasmFunctionType::default()))
+        );
+        assert_eq!(first.export("helper"), None);
+
+        let log = first.imports_from("env").next().unwrap();
+        assert_eq!(log.field_name(), "log");
+        assert_eq!(
+            first.import_signature(log).unwrap().to_string(),
+            "(i32) -> i32"
+        );
+        assert_eq!(first.imports_from("env").count(), 2);
+        assert_eq!(first.imports_from("wasi").count(), 0);
+    }
+
+    #[test]
+    fn test_interface_differs_with_signature() {
+        let [first, _] = builds();
+        let first = decode_bytes(&first.build()).unwrap().interface();
+        let [changed, _] = builds();
+        let changed = changed.add_export("log", WasmExportDescriptor::Function(0.into()));
+        let changed = decode_bytes(&changed.build()).unwrap().interface();
+        assert_ne!(first, changed);
+    }
+}
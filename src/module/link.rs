@@ -0,0 +1,395 @@
+use crate::module::{ExternType, WasmModule};
+use crate::types::{
+    ExternalKind, GlobalType, MemoryType, TableType, WasmElementType, WasmFunctionType,
+    WasmImportDescriptor, WasmImportEntry, WasmLimits,
+};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// What a host provides to the modules it instantiates: every item it can satisfy an import
+/// with, by module and field name; see `WasmModule::check_imports`.
+///
+/// ```
+/// use wasm_thing::module::{ExternType, HostProfile};
+/// use wasm_thing::types::{MemoryType, WasmFunctionType, WasmLimits};
+///
+/// let host = HostProfile::new()
+///     .provide("env", "memory", ExternType::Memory(MemoryType::new(WasmLimits::new(16, None))))
+///     .provide("env", "log", ExternType::Function("(i32) -> ()".parse().unwrap()));
+/// assert!(host.get("env", "log").is_some());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostProfile {
+    pub(crate) items: HashMap<(String, String), ExternType>,
+}
+
+impl HostProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provide `extern_type` as `module_name.field_name`, replacing whatever was provided
+    /// under that name before.
+    pub fn provide(
+        mut self,
+        module_name: impl Into<String>,
+        field_name: impl Into<String>,
+        extern_type: ExternType,
+    ) -> Self {
+        self.items
+            .insert((module_name.into(), field_name.into()), extern_type);
+        self
+    }
+
+    /// What the host provides as `module_name.field_name`, if anything.
+    pub fn get(&self, module_name: &str, field_name: &str) -> Option<&ExternType> {
+        self.items
+            .get(&(module_name.to_owned(), field_name.to_owned()))
+    }
+}
+
+/// Why an import cannot be satisfied by what a `HostProfile` provides.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum LinkError {
+    #[error("The host does not provide {module_name}.{field_name}.")]
+    Missing {
+        module_name: String,
+        field_name: String,
+    },
+
+    #[error("{module_name}.{field_name} is imported as a {expected:?}, but the host provides a {found:?}.")]
+    KindMismatch {
+        module_name: String,
+        field_name: String,
+        expected: ExternalKind,
+        found: ExternalKind,
+    },
+
+    /// The type index of an imported function is out of range, so its signature is unknown.
+    #[error("{module_name}.{field_name} is imported with type {type_index}, which the module does not define.")]
+    UnknownType {
+        module_name: String,
+        field_name: String,
+        type_index: u32,
+    },
+
+    #[error(
+        "{module_name}.{field_name} is imported as {expected}, but the host provides {found}."
+    )]
+    SignatureMismatch {
+        module_name: String,
+        field_name: String,
+        expected: WasmFunctionType,
+        found: WasmFunctionType,
+    },
+
+    /// The provided limits of a memory or table do not match the imported ones: the host must
+    /// provide at least `required.min()`, and at most `required.max()` if there is one.
+    #[error("{module_name}.{field_name} is imported with limits {required}, but the host provides {provided}.")]
+    LimitsMismatch {
+        module_name: String,
+        field_name: String,
+        kind: ExternalKind,
+        required: WasmLimits,
+        provided: WasmLimits,
+    },
+
+    /// The element type, or whether it is shared or 64-bit, of a table differs.
+    #[error("{module_name}.{field_name} is imported as a table of {expected:?}, but the host provides one of {found:?}.")]
+    TableTypeMismatch {
+        module_name: String,
+        field_name: String,
+        expected: WasmElementType,
+        found: WasmElementType,
+    },
+
+    /// Whether a memory is shared or 64-bit differs.
+    #[error("{module_name}.{field_name} is imported as a memory of type {expected:?}, but the host provides one of type {found:?}.")]
+    MemoryTypeMismatch {
+        module_name: String,
+        field_name: String,
+        expected: MemoryType,
+        found: MemoryType,
+    },
+
+    #[error("{module_name}.{field_name} is imported as a global of type {expected}, but the host provides {found}.")]
+    GlobalTypeMismatch {
+        module_name: String,
+        field_name: String,
+        expected: GlobalType,
+        found: GlobalType,
+    },
+}
+
+impl WasmModule<'_> {
+    /// Check every import against what `host` provides, returning one error for each import
+    /// it cannot satisfy, in import order; no errors means the module can be instantiated
+    /// with the host's items. Follows the import matching rules of the specification:
+    /// functions need equal signatures, globals equal types, and memories and tables limits
+    /// that are at least as strict as the imported ones.
+    ///
+    /// ```
+    /// use wasm_thing::module::{ExternType, HostProfile, LinkError};
+    /// use wasm_thing::types::{MemoryType, WasmLimits};
+    ///
+    /// // (module (import "env" "memory" (memory 1 16)))
+    /// let bytes = [
+    ///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x02, 0x10, 0x01, 0x03, 0x65, 0x6e,
+    ///     0x76, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x01, 0x01, 0x10,
+    /// ];
+    /// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+    ///
+    /// let memory = |min, max| ExternType::Memory(MemoryType::new(WasmLimits::new(min, max)));
+    /// let host = HostProfile::new().provide("env", "memory", memory(1, Some(4)));
+    /// assert!(module.check_imports(&host).is_empty());
+    ///
+    /// // A memory that may grow to 32 pages breaks the importer's maximum of 16.
+    /// let host = HostProfile::new().provide("env", "memory", memory(1, Some(32)));
+    /// let errors = module.check_imports(&host);
+    /// assert!(matches!(errors[..], [LinkError::LimitsMismatch { .. }]));
+    /// ```
+    pub fn check_imports(&self, host: &HostProfile) -> Vec<LinkError> {
+        self.imports
+            .iter()
+            .filter_map(|import| {
+                let Some(provided) = host.get(&import.module_name, &import.field_name) else {
+                    return Some(LinkError::Missing {
+                        module_name: import.module_name.to_string(),
+                        field_name: import.field_name.to_string(),
+                    });
+                };
+
+                self.check_import(import, provided)
+            })
+            .collect()
+    }
+
+    /// Why `provided` cannot satisfy `import`, or `None` if it can.
+    pub(crate) fn check_import(
+        &self,
+        import: &WasmImportEntry,
+        provided: &ExternType,
+    ) -> Option<LinkError> {
+        let module_name = import.module_name.to_string();
+        let field_name = import.field_name.to_string();
+        match (&import.descriptor, provided) {
+            (&WasmImportDescriptor::Function(type_index), ExternType::Function(found)) => {
+                let type_index = u32::from(type_index);
+                let Some(expected) = self.types.get(type_index as usize) else {
+                    return Some(LinkError::UnknownType {
+                        module_name,
+                        field_name,
+                        type_index,
+                    });
+                };
+                (expected != found).then(|| LinkError::SignatureMismatch {
+                    module_name,
+                    field_name,
+                    expected: expected.clone(),
+                    found: found.clone(),
+                })
+            }
+            (WasmImportDescriptor::Table(expected), ExternType::Table(found)) => {
+                check_table(expected, found, module_name, field_name)
+            }
+            (WasmImportDescriptor::Memory(expected), ExternType::Memory(found)) => {
+                check_memory(expected, found, module_name, field_name)
+            }
+            (WasmImportDescriptor::Global(expected), ExternType::Global(found)) => {
+                (expected != found).then(|| LinkError::GlobalTypeMismatch {
+                    module_name,
+                    field_name,
+                    expected: expected.clone(),
+                    found: found.clone(),
+                })
+            }
+            (descriptor, provided) => Some(LinkError::KindMismatch {
+                module_name,
+                field_name,
+                expected: descriptor.kind(),
+                found: provided.kind(),
+            }),
+        }
+    }
+}
+
+fn check_table(
+    expected: &TableType,
+    found: &TableType,
+    module_name: String,
+    field_name: String,
+) -> Option<LinkError> {
+    if expected.element_type != found.element_type
+        || expected.shared != found.shared
+        || expected.table64 != found.table64
+    {
+        return Some(LinkError::TableTypeMismatch {
+            module_name,
+            field_name,
+            expected: expected.element_type,
+            found: found.element_type,
+        });
+    }
+
+    (!found.limits.is_subtype_of(&expected.limits)).then(|| LinkError::LimitsMismatch {
+        module_name,
+        field_name,
+        kind: ExternalKind::Table,
+        required: expected.limits.clone(),
+        provided: found.limits.clone(),
+    })
+}
+
+fn check_memory(
+    expected: &MemoryType,
+    found: &MemoryType,
+    module_name: String,
+    field_name: String,
+) -> Option<LinkError> {
+    if expected.shared != found.shared || expected.memory64 != found.memory64 {
+        return Some(LinkError::MemoryTypeMismatch {
+            module_name,
+            field_name,
+            expected: expected.clone(),
+            found: found.clone(),
+        });
+    }
+
+    (!found.limits.is_subtype_of(&expected.limits)).then(|| LinkError::LimitsMismatch {
+        module_name,
+        field_name,
+        kind: ExternalKind::Memory,
+        required: expected.limits.clone(),
+        provided: found.limits.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HostProfile, LinkError};
+    use crate::decode_bytes;
+    use crate::encode::{func_type, memory_type, ModuleBuilder};
+    use crate::module::ExternType;
+    use crate::types::{
+        ExternalKind, GlobalType, MemoryType, Mutability, TableType, WasmElementType,
+        WasmImportDescriptor, WasmLimits, WasmValueType,
+    };
+
+    fn memory(min: u64, max: Option<u64>) -> ExternType {
+        ExternType::Memory(MemoryType::new(WasmLimits::new(min, max)))
+    }
+
+    #[test]
+    fn test_check_imports() {
+        use WasmValueType::{I32, I64};
+
+        let bytes = ModuleBuilder::new()
+            .add_type(&[I32], &[])
+            .add_import("env", "log", func_type(0))
+            .add_import("env", "memory", memory_type(100))
+            .add_import("env", "missing", func_type(0))
+            .add_import(
+                "env",
+                "table",
+                WasmImportDescriptor::Table(TableType::new(
+                    WasmElementType::Funcref,
+                    WasmLimits::new(1, None),
+                )),
+            )
+            .add_import("env", "trace", func_type(0))
+            .add_import(
+                "env",
+                "depth",
+                WasmImportDescriptor::Global(GlobalType::new(I32, Mutability::Mutable)),
+            )
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+
+        let host = HostProfile::new()
+            .provide(
+                "env",
+                "log",
+                ExternType::Function("(i32) -> ()".parse().unwrap()),
+            )
+            .provide("env", "memory", memory(16, None))
+            .provide("env", "table", memory(1, None))
+            .provide(
+                "env",
+                "trace",
+                ExternType::Function("(i64) -> ()".parse().unwrap()),
+            )
+            .provide(
+                "env",
+                "depth",
+                ExternType::Global(GlobalType::new(I64, Mutability::Mutable)),
+            );
+        let errors = module.check_imports(&host);
+
+        assert_eq!(errors.len(), 5, "{errors:?}");
+        assert!(matches!(
+            &errors[0],
+            LinkError::LimitsMismatch { field_name, kind: ExternalKind::Memory, required, provided, .. }
+                if field_name == "memory" && required.min() == 100 && provided.min() == 16
+        ));
+        assert_eq!(
+            errors[1],
+            LinkError::Missing {
+                module_name: "env".into(),
+                field_name: "missing".into(),
+            }
+        );
+        assert!(matches!(
+            &errors[2],
+            LinkError::KindMismatch {
+                expected: ExternalKind::Table,
+                found: ExternalKind::Memory,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &errors[3],
+            LinkError::SignatureMismatch { field_name, .. } if field_name == "trace"
+        ));
+        assert!(matches!(&errors[4], LinkError::GlobalTypeMismatch { .. }));
+
+        // A host offering what is asked for satisfies every import.
+        let host = host
+            .provide("env", "memory", memory(100, Some(200)))
+            .provide(
+                "env",
+                "missing",
+                ExternType::Function("(i32) -> ()".parse().unwrap()),
+            )
+            .provide(
+                "env",
+                "table",
+                ExternType::Table(TableType::new(
+                    WasmElementType::Funcref,
+                    WasmLimits::new(4, Some(4)),
+                )),
+            )
+            .provide(
+                "env",
+                "trace",
+                ExternType::Function("(i32) -> ()".parse().unwrap()),
+            )
+            .provide(
+                "env",
+                "depth",
+                ExternType::Global(GlobalType::new(I32, Mutability::Mutable)),
+            );
+        assert_eq!(module.check_imports(&host), []);
+
+        // A shared memory does not satisfy an unshared import, whatever its limits.
+        let shared = MemoryType {
+            shared: true,
+            ..MemoryType::new(WasmLimits::new(100, Some(100)))
+        };
+        let host = host.provide("env", "memory", ExternType::Memory(shared));
+        assert!(matches!(
+            &module.check_imports(&host)[..],
+            [LinkError::MemoryTypeMismatch { field_name, .. }] if field_name == "memory"
+        ));
+    }
+}
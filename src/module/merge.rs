@@ -0,0 +1,577 @@
+use crate::decode::{decode_start_function, remap_indices, DecodeError, IndexRemap};
+use crate::encode::encode_sections;
+use crate::module::{ExternType, RawSection, WasmModule};
+use crate::types::{
+    DataMode, DataSegment, ExternalKind, FuncIndex, FunctionBody, Global, GlobalIndex, MemoryIndex,
+    SectionType, TableIndex, Tag, TypeIndex, WasmExportEntry, WasmImportDescriptor,
+    WasmImportEntry,
+};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// Why two modules cannot be combined by `WasmModule::merge`.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum MergeError {
+    #[error("Both modules export {name}.")]
+    DuplicateExport { name: String },
+
+    /// An import is resolved by an export of the other module that has a different type.
+    #[error("{module_name}.{field_name} is imported with a different type than the other module exports it with.")]
+    ImportMismatch {
+        module_name: String,
+        field_name: String,
+    },
+
+    /// Resolving an import leads back to itself through imports that re-export each other.
+    #[error("{module_name}.{field_name} is re-exported in a cycle between the two modules.")]
+    ImportCycle {
+        module_name: String,
+        field_name: String,
+    },
+
+    /// Loads and stores do not say which memory they access, so only one memory is supported.
+    #[error("The merged module would have more than one memory.")]
+    MultipleMemories,
+
+    #[error("Both modules have a start function.")]
+    MultipleStartFunctions,
+
+    /// `throw` and `catch` are not rewritten, so tag indices cannot be shifted.
+    #[error("Both modules declare tags.")]
+    TagsInBoth,
+
+    /// A function body or the start section could not be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+impl<'a> WasmModule<'a> {
+    /// Combine two modules into one that has the items of both, `self`'s first. Types are
+    /// deduplicated, and every index in function bodies, globals, exports and element and
+    /// data segments is rewritten to match the combined index spaces.
+    ///
+    /// An import of one module that the other exports under its field name, whatever its
+    /// module name, is resolved: references to it are made to the exported item instead, and
+    /// the import is dropped. Imports left in both modules with the same names and type are
+    /// combined into one. Custom sections are dropped, as their indices would be stale.
+    ///
+    /// This is a best-effort combination for modules written to be merged, not a linker: data
+    /// segments are kept at their addresses, so both modules must agree on how memory is laid
+    /// out, and only one of them may have a memory unless both import the same one.
+    ///
+    /// ```
+    /// let bytes = std::fs::read("tests/fixtures/add.wasm").unwrap();
+    /// let add = wasm_thing::decode_bytes(&bytes).unwrap();
+    /// let empty = wasm_thing::WasmModuleBuilder::new().build().unwrap();
+    ///
+    /// let merged = add.clone().merge(empty).unwrap();
+    /// let encoded = wasm_thing::encode_module(&merged);
+    /// let merged = wasm_thing::decode_bytes(&encoded).unwrap();
+    /// assert_eq!(merged.exports(), add.exports());
+    /// ```
+    pub fn merge(self, other: WasmModule<'a>) -> Result<WasmModule<'a>, MergeError> {
+        if !self.tags.is_empty() && !other.tags.is_empty() {
+            return Err(MergeError::TagsInBoth);
+        }
+        let start_functions = [self.start_function()?, other.start_function()?];
+        let modules = [&self, &other];
+
+        let mut types = Vec::new();
+        let mut type_indices = HashMap::new();
+        let type_maps = modules.map(|module| {
+            module
+                .types
+                .iter()
+                .map(|function_type| {
+                    *type_indices.entry(function_type).or_insert_with(|| {
+                        types.push(function_type.clone());
+                        types.len() as u32 - 1
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let [functions, tables, memories, globals] = [
+            ExternalKind::Function,
+            ExternalKind::Table,
+            ExternalKind::Memory,
+            ExternalKind::Global,
+        ]
+        .map(|kind| MergedIndexSpace::new(kind, modules, &type_maps));
+        let spaces = [functions?, tables?, memories?, globals?];
+        if spaces[2].len > 1 {
+            return Err(MergeError::MultipleMemories);
+        }
+
+        let remaps = [0, 1].map(|side| Remap {
+            types: &type_maps[side],
+            spaces: &spaces,
+            side,
+            elem_base: if side == 0 {
+                0
+            } else {
+                self.elements.len() as u32
+            },
+            data_base: if side == 0 { 0 } else { self.data.len() as u32 },
+        });
+
+        let mut merged = WasmModule {
+            version: self.version,
+            types,
+            ..WasmModule::default()
+        };
+        let mut export_names = HashSet::new();
+        for (side, module) in [self, other].into_iter().enumerate() {
+            let remap = &remaps[side];
+            let mut import_counts = [0; 4];
+            for import in module.imports {
+                let kind = import.descriptor.kind() as usize;
+                let kept = spaces[kind].kept[side][import_counts[kind]];
+                import_counts[kind] += 1;
+                if kept {
+                    merged.imports.push(WasmImportEntry {
+                        descriptor: match import.descriptor {
+                            WasmImportDescriptor::Function(type_index) => {
+                                WasmImportDescriptor::Function(remap.type_index(type_index))
+                            }
+                            descriptor => descriptor,
+                        },
+                        ..import
+                    });
+                }
+            }
+
+            merged
+                .functions
+                .extend((module.functions.iter()).map(|&type_index| remap.type_index(type_index)));
+            merged.tables.extend(module.tables);
+            merged.memories.extend(module.memories);
+            merged
+                .globals
+                .extend(module.globals.into_iter().map(|global| Global {
+                    init: remap.const_expr(global.init),
+                    ..global
+                }));
+
+            for export in module.exports {
+                if !export_names.insert(export.name.clone()) {
+                    return Err(MergeError::DuplicateExport {
+                        name: export.name.into_owned(),
+                    });
+                }
+                merged.exports.push(WasmExportEntry {
+                    descriptor: remap.export(export.descriptor),
+                    ..export
+                });
+            }
+
+            merged.elements.extend(
+                module
+                    .elements
+                    .into_iter()
+                    .map(|segment| remap.element_segment(segment)),
+            );
+            merged
+                .data
+                .extend(module.data.into_iter().map(|segment| DataSegment {
+                    mode: match segment.mode {
+                        DataMode::Active {
+                            memory_index,
+                            offset,
+                        } => DataMode::Active {
+                            memory_index: remap.memory_index(memory_index),
+                            offset: remap.const_expr(offset),
+                        },
+                        DataMode::Passive => DataMode::Passive,
+                    },
+                    ..segment
+                }));
+            merged.tags.extend(module.tags.into_iter().map(|tag| Tag {
+                type_index: remap.type_index(tag.type_index),
+                ..tag
+            }));
+
+            for body in module.code {
+                merged.code.push(FunctionBody {
+                    expression: Cow::Owned(remap_indices(&body.expression, remap)?),
+                    ..body
+                });
+            }
+        }
+
+        let start_function = match start_functions {
+            [Some(_), Some(_)] => return Err(MergeError::MultipleStartFunctions),
+            [Some(function), None] => Some(remaps[0].func_index(function)),
+            [None, Some(function)] => Some(remaps[1].func_index(function)),
+            [None, None] => None,
+        };
+        merged.raw_sections = encode_sections(&merged, start_function);
+        Ok(merged)
+    }
+
+    /// The index of the start function, from the kept contents of the start section.
+    pub(crate) fn start_function(&self) -> Result<Option<FuncIndex>, DecodeError> {
+        self.raw_sections
+            .iter()
+            .find_map(|section| match section {
+                RawSection::Standard {
+                    section_type: SectionType::Start,
+                    contents,
+                } => Some(decode_start_function(contents)),
+                _ => None,
+            })
+            .transpose()
+    }
+}
+
+/// How an import of one of the merged modules is resolved.
+#[derive(Clone, Copy)]
+enum Link {
+    /// Imported by the merged module, at this index of its index space.
+    Kept(u32),
+    /// Exported by the other module, at this index of its index space.
+    Export(u32),
+}
+
+/// The index space of one kind, as the merged module numbers the items of each module.
+struct MergedIndexSpace {
+    /// The merged index of every item, by module and its index there.
+    merged: [Vec<u32>; 2],
+    /// Whether each import of this kind is still imported by the merged module, by module.
+    kept: [Vec<bool>; 2],
+    /// The number of items in the merged index space.
+    len: u32,
+}
+
+impl MergedIndexSpace {
+    /// Number the items of `kind`: the imports left of the first module, then those of the
+    /// second, then the items defined by the first, then those defined by the second.
+    fn new(
+        kind: ExternalKind,
+        modules: [&WasmModule; 2],
+        type_maps: &[Vec<u32>; 2],
+    ) -> Result<Self, MergeError> {
+        let imports = modules.map(|module| {
+            (module.imports.iter())
+                .filter(|import| import.descriptor.kind() == kind)
+                .collect::<Vec<_>>()
+        });
+        let defined = modules.map(|module| module.index_spaces().of_kind(kind).defined);
+
+        let mut links: [Vec<Link>; 2] = Default::default();
+        let mut kept: [Vec<bool>; 2] = Default::default();
+        let mut num_kept = 0;
+        for side in 0..2 {
+            let (module, other) = (modules[side], modules[1 - side]);
+            for import in &imports[side] {
+                let mismatch = || MergeError::ImportMismatch {
+                    module_name: import.module_name.to_string(),
+                    field_name: import.field_name.to_string(),
+                };
+                let export = (other.exports.iter()).find(|export| {
+                    export.name == import.field_name && export.descriptor.kind() == kind
+                });
+
+                let link = if let Some(export) = export {
+                    let exported = other
+                        .resolve_export(&export.descriptor)
+                        .ok_or_else(mismatch)?;
+                    if module
+                        .check_import(import, &ExternType::from(exported))
+                        .is_some()
+                    {
+                        return Err(mismatch());
+                    }
+                    Link::Export(export.descriptor.index())
+                } else if let Some(first) = (side == 1)
+                    .then(|| {
+                        imports[0].iter().zip(&links[0]).find(|(first, link)| {
+                            matches!(link, Link::Kept(_)) && same_import(first, import, type_maps)
+                        })
+                    })
+                    .flatten()
+                {
+                    *first.1
+                } else {
+                    num_kept += 1;
+                    kept[side].push(true);
+                    links[side].push(Link::Kept(num_kept - 1));
+                    continue;
+                };
+                kept[side].push(false);
+                links[side].push(link);
+            }
+        }
+
+        let bases = [num_kept, num_kept + defined[0]];
+        let resolve = |side: usize, index: u32| {
+            let (mut side, mut index) = (side, index);
+            // Every import can be passed through once before the chain must be a cycle.
+            for _ in 0..=imports[0].len() + imports[1].len() {
+                match links[side].get(index as usize) {
+                    None => return Ok(bases[side] + index - imports[side].len() as u32),
+                    Some(Link::Kept(merged)) => return Ok(*merged),
+                    Some(Link::Export(exported)) => (side, index) = (1 - side, *exported),
+                }
+            }
+            let import = imports[side][index as usize];
+            Err(MergeError::ImportCycle {
+                module_name: import.module_name.to_string(),
+                field_name: import.field_name.to_string(),
+            })
+        };
+
+        let merged = [0, 1].map(|side| {
+            (0..imports[side].len() as u32 + defined[side])
+                .map(|index| resolve(side, index))
+                .collect::<Result<Vec<_>, _>>()
+        });
+        let [first, second] = merged;
+        Ok(MergedIndexSpace {
+            merged: [first?, second?],
+            kept,
+            len: bases[1] + defined[1],
+        })
+    }
+}
+
+/// Whether an import of the first module and one of the second are of the same item.
+fn same_import(
+    first: &WasmImportEntry,
+    second: &WasmImportEntry,
+    type_maps: &[Vec<u32>; 2],
+) -> bool {
+    let merged_type = |side: usize, type_index: u32| type_maps[side].get(type_index as usize);
+    first.module_name == second.module_name
+        && first.field_name == second.field_name
+        && match (&first.descriptor, &second.descriptor) {
+            (WasmImportDescriptor::Function(first), WasmImportDescriptor::Function(second)) => {
+                merged_type(0, (*first).into()) == merged_type(1, (*second).into())
+            }
+            (first, second) => first == second,
+        }
+}
+
+/// Rewrites the indices of one of the merged modules into those of the merged module.
+struct Remap<'s> {
+    types: &'s [u32],
+    /// The function, table, memory and global index spaces.
+    spaces: &'s [MergedIndexSpace; 4],
+    side: usize,
+    elem_base: u32,
+    data_base: u32,
+}
+
+impl Remap<'_> {
+    /// Indices out of range are left as they are, so that the merged module is as broken as
+    /// its input, rather than differently.
+    fn index(&self, kind: ExternalKind, index: u32) -> u32 {
+        let merged = &self.spaces[kind as usize].merged[self.side];
+        merged.get(index as usize).copied().unwrap_or(index)
+    }
+}
+
+impl IndexRemap for Remap<'_> {
+    fn type_index(&self, index: TypeIndex) -> TypeIndex {
+        let index = u32::from(index);
+        self.types
+            .get(index as usize)
+            .copied()
+            .unwrap_or(index)
+            .into()
+    }
+
+    fn func_index(&self, index: FuncIndex) -> FuncIndex {
+        self.index(ExternalKind::Function, index.into()).into()
+    }
+
+    fn global_index(&self, index: GlobalIndex) -> GlobalIndex {
+        self.index(ExternalKind::Global, index.into()).into()
+    }
+
+    fn table_index(&self, index: TableIndex) -> TableIndex {
+        self.index(ExternalKind::Table, index.into()).into()
+    }
+
+    fn memory_index(&self, index: MemoryIndex) -> MemoryIndex {
+        self.index(ExternalKind::Memory, index.into()).into()
+    }
+
+    fn elem_index(&self, index: u32) -> u32 {
+        self.elem_base + index
+    }
+
+    fn data_index(&self, index: u32) -> u32 {
+        self.data_base + index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeError;
+    use crate::encode::{func_type, memory_type, ModuleBuilder};
+    use crate::types::{WasmExportDescriptor, WasmImportDescriptor, WasmValueType};
+    use crate::{decode_bytes, encode_module, WasmModule};
+
+    fn decode(builder: ModuleBuilder) -> WasmModule<'static> {
+        decode_bytes(&builder.build()).unwrap().into_owned()
+    }
+
+    #[test]
+    fn test_merge_resolves_imports() {
+        use WasmValueType::I32;
+
+        // Calls `helper`, which it imports, and exports the result as `run`.
+        let first = decode(
+            ModuleBuilder::new()
+                .add_type(&[], &[I32])
+                .add_import("env", "helper", func_type(0))
+                .add_import("env", "memory", memory_type(1))
+                .add_function(0, &[], &[0x10, 0x00, 0x0b])
+                .add_export("run", WasmExportDescriptor::Function(1.into())),
+        );
+        // Defines `helper`, after a function of another type, and imports the same memory.
+        let second = decode(
+            ModuleBuilder::new()
+                .add_type(&[I32], &[I32])
+                .add_type(&[], &[I32])
+                .add_import("env", "memory", memory_type(1))
+                .add_function(0, &[], &[0x20, 0x00, 0x0b])
+                .add_function(1, &[], &[0x41, 0x2a, 0x10, 0x00, 0x1a, 0x0b])
+                .add_export("helper", WasmExportDescriptor::Function(1.into())),
+        );
+
+        let merged = first.merge(second).unwrap();
+        let bytes = encode_module(&merged);
+        let decoded = decode_bytes(&bytes).unwrap();
+
+        // `helper` is resolved, and the memory imported once.
+        assert_eq!(decoded.imports().len(), 1);
+        assert!(matches!(
+            decoded.imports()[0].descriptor(),
+            WasmImportDescriptor::Memory(_)
+        ));
+        let types: Vec<_> = decoded.types().iter().map(ToString::to_string).collect();
+        assert_eq!(types, ["() -> i32", "(i32) -> i32"]);
+        assert_eq!(
+            decoded.functions().map(u32::from).collect::<Vec<_>>(),
+            [0, 1, 0]
+        );
+
+        // `run` now calls the second module's `helper`, which is function 2, and `helper`'s
+        // call of its own function 0 became a call of function 1.
+        assert_eq!(decoded.code()[0].expression(), [0x10, 0x02, 0x0b]);
+        assert_eq!(
+            decoded.code()[2].expression(),
+            [0x41, 0x2a, 0x10, 0x01, 0x1a, 0x0b]
+        );
+        let exports: Vec<_> = decoded
+            .exports()
+            .iter()
+            .map(|export| (export.name(), export.descriptor().index()))
+            .collect();
+        assert_eq!(exports, [("run", 0), ("helper", 2)]);
+    }
+
+    #[test]
+    fn test_merge_with_empty_module() {
+        let fixtures: [&[u8]; 5] = [
+            include_bytes!("../../tests/fixtures/add.wasm"),
+            include_bytes!("../../tests/fixtures/imports.wasm"),
+            include_bytes!("../../tests/fixtures/wasi_hello.wasm"),
+            include_bytes!("../../tests/fixtures/features.wasm"),
+            include_bytes!("../../tests/fixtures/linked.wasm"),
+        ];
+        for bytes in fixtures {
+            let module = decode_bytes(bytes).unwrap();
+            let merged = module.clone().merge(WasmModule::default()).unwrap();
+
+            // Every section is encoded from scratch, and decodes to what it was made from.
+            let encoded = encode_module(&merged);
+            let mut decoded = decode_bytes(&encoded).unwrap();
+            decoded.sections.clear();
+            decoded.raw_sections = merged.raw_sections.clone();
+            assert_eq!(decoded, merged);
+
+            let mut expected = module;
+            expected.sections.clear();
+            expected.raw_sections = merged.raw_sections.clone();
+            assert_eq!(merged, expected);
+        }
+    }
+
+    #[test]
+    fn test_merge_errors() {
+        use WasmValueType::{I32, I64};
+
+        let exporting = || {
+            decode(
+                ModuleBuilder::new()
+                    .add_type(&[], &[I32])
+                    .add_function(0, &[], &[0x41, 0x00, 0x0b])
+                    .add_export("helper", WasmExportDescriptor::Function(0.into())),
+            )
+        };
+
+        let error = exporting().merge(exporting()).unwrap_err();
+        assert!(matches!(error, MergeError::DuplicateExport { name } if name == "helper"));
+
+        let importing = decode(ModuleBuilder::new().add_type(&[], &[I64]).add_import(
+            "env",
+            "helper",
+            func_type(0),
+        ));
+        let error = importing.merge(exporting()).unwrap_err();
+        assert!(matches!(
+            error,
+            MergeError::ImportMismatch { field_name, .. } if field_name == "helper"
+        ));
+
+        let with_memory = || decode(ModuleBuilder::new().add_memory(1));
+        let error = with_memory().merge(with_memory()).unwrap_err();
+        assert!(matches!(error, MergeError::MultipleMemories));
+    }
+
+    #[test]
+    fn test_merge_memory_limits() {
+        let exporting = || {
+            decode(
+                ModuleBuilder::new()
+                    .add_memory(1)
+                    .add_export("memory", WasmExportDescriptor::Memory(0.into())),
+            )
+        };
+        let importing =
+            |min| decode(ModuleBuilder::new().add_import("env", "memory", memory_type(min)));
+
+        // A memory of 1 page cannot stand in for one of at least 10.
+        let error = importing(10).merge(exporting()).unwrap_err();
+        assert!(matches!(
+            error,
+            MergeError::ImportMismatch { field_name, .. } if field_name == "memory"
+        ));
+
+        let merged = importing(1).merge(exporting()).unwrap();
+        assert!(merged.imports().is_empty());
+        assert_eq!(merged.memories.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_import_cycle() {
+        // Each module re-exports an import that the other module exports.
+        let reexporting = |imported: &str, exported: &str| {
+            decode(
+                ModuleBuilder::new()
+                    .add_type(&[], &[])
+                    .add_import("env", imported, func_type(0))
+                    .add_export(exported, WasmExportDescriptor::Function(0.into())),
+            )
+        };
+        let error = reexporting("a", "b")
+            .merge(reexporting("b", "a"))
+            .unwrap_err();
+        assert!(matches!(error, MergeError::ImportCycle { .. }));
+    }
+}
@@ -1,2 +1,1127 @@
-#[derive(Default)]
-pub struct WasmModule {}
+use crate::decode::{decode_function_names, decode_linking_section, DecodeError};
+use crate::types::{
+    ConstExpr, CustomSection, DataSegment, ElementItems, ElementSegment, FunctionBody, Global,
+    GlobalType, LinkingSection, MemoryType, SectionInfo, SectionType, TableType, Tag,
+    WasmExportDescriptor, WasmExportEntry, WasmFunctionType, WasmImportDescriptor, WasmImportEntry,
+    WasmLimits, WasmSection, WasmVersion,
+};
+use crate::wasm::instructions::BlockType;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+mod builder;
+mod eval;
+mod fingerprint;
+mod image;
+mod index_space;
+mod interface;
+mod link;
+mod merge;
+mod transform;
+
+pub use crate::types::{FuncIndex, MemoryIndex, TypeIndex};
+pub use builder::{BuildError, WasmModuleBuilder};
+pub use eval::EvalError;
+pub use image::{MemoryImage, MemoryImageError, TableImageError, MAX_TABLE_IMAGE_LEN, PAGE_SIZE};
+pub use index_space::{IndexOrigin, IndexSpace, IndexSpaces};
+pub use interface::{ExternType, InterfaceExport, ModuleInterface};
+pub use link::{HostProfile, LinkError};
+pub use merge::MergeError;
+pub use transform::{
+    ExportError, IndexRemapping, ReferencedFunctions, RemoveFunctionsError, TypeRemapping,
+};
+
+/// A decoded WebAssembly module.
+///
+/// Names and function bodies borrow from the buffer passed to `decode_bytes`, so decoding a
+/// slice copies as little as possible. Use `into_owned`/`to_owned` to detach a module from
+/// that buffer; `decode_reader` produces a `WasmModule<'static>` directly.
+///
+/// ```
+/// use wasm_thing::types::{WasmExportDescriptor, WasmImportDescriptor, WasmValueType};
+///
+/// // (module
+/// //   (import "env" "log" (func (param i32)))
+/// //   (func (export "answer") (result i32) i32.const 42))
+/// let bytes = [
+///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x09, 0x02, 0x60, 0x01, 0x7f,
+///     0x00, 0x60, 0x00, 0x01, 0x7f, 0x02, 0x0b, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x03, 0x6c,
+///     0x6f, 0x67, 0x00, 0x00, 0x03, 0x02, 0x01, 0x01, 0x07, 0x0a, 0x01, 0x06, 0x61, 0x6e,
+///     0x73, 0x77, 0x65, 0x72, 0x00, 0x01, 0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x2a, 0x0b,
+/// ];
+/// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+///
+/// assert_eq!(module.version().version(), 1);
+///
+/// assert_eq!(module.types().len(), 2);
+/// assert_eq!(module.types()[0].params(), [WasmValueType::I32]);
+///
+/// let import = &module.imports()[0];
+/// assert_eq!((import.module_name(), import.field_name()), ("env", "log"));
+/// assert!(matches!(import.descriptor(), WasmImportDescriptor::Function(index) if u32::from(*index) == 0));
+///
+/// assert_eq!(module.functions().map(u32::from).collect::<Vec<_>>(), [1]);
+///
+/// let export = &module.exports()[0];
+/// assert_eq!(export.name(), "answer");
+/// assert!(matches!(export.descriptor(), WasmExportDescriptor::Function(index) if u32::from(*index) == 1));
+///
+/// let body = &module.code()[0];
+/// assert!(body.locals().is_empty());
+/// assert_eq!(body.expression(), [0x41, 0x2a, 0x0b]);
+///
+/// assert!(module.memories().is_empty());
+/// assert!(module.globals().is_empty());
+/// assert!(module.tags().is_empty());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WasmModule<'a> {
+    pub(crate) version: WasmVersion,
+    pub(crate) types: Vec<WasmFunctionType>,
+    pub(crate) imports: Vec<WasmImportEntry<'a>>,
+    pub(crate) functions: Vec<TypeIndex>,
+    pub(crate) exports: Vec<WasmExportEntry<'a>>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) code: Vec<FunctionBody<'a>>,
+    pub(crate) tables: Vec<TableType>,
+    pub(crate) memories: Vec<MemoryType>,
+    pub(crate) globals: Vec<Global>,
+    pub(crate) elements: Vec<ElementSegment>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) data: Vec<DataSegment<'a>>,
+    pub(crate) tags: Vec<Tag>,
+    pub(crate) sections: Vec<SectionInfo>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) raw_sections: Vec<RawSection<'a>>,
+}
+
+/// A section as it appeared in the decoded binary, so that `encode_module` can write it back
+/// out unchanged. Custom sections are only kept here.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum RawSection<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Custom(CustomSection<'a>),
+    /// Any other section, with the bytes after its size.
+    Standard {
+        section_type: SectionType,
+        #[cfg_attr(feature = "serde", serde(borrow, with = "serde_bytes"))]
+        contents: Cow<'a, [u8]>,
+    },
+}
+
+impl RawSection<'_> {
+    fn into_owned(self) -> RawSection<'static> {
+        match self {
+            RawSection::Custom(section) => RawSection::Custom(section.into_owned()),
+            RawSection::Standard {
+                section_type,
+                contents,
+            } => RawSection::Standard {
+                section_type,
+                contents: Cow::Owned(contents.into_owned()),
+            },
+        }
+    }
+}
+
+/// A function of the function index space, which counts imported functions first, then the
+/// ones defined in the module; see `WasmModule::function`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FunctionRef<'m, 'a> {
+    Imported(&'m WasmImportEntry<'a>),
+    /// `body` is `None` if the code section has fewer bodies than the function section has
+    /// functions.
+    Defined {
+        type_index: TypeIndex,
+        body: Option<&'m FunctionBody<'a>>,
+    },
+}
+
+/// A function of the function index space with everything known about it; see
+/// `WasmModule::iter_functions`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FunctionInfo<'m, 'a> {
+    pub index: u32,
+    pub imported: bool,
+    /// `None` if the type index of the function is out of range.
+    pub signature: Option<&'m WasmFunctionType>,
+    /// Always `None` for imported functions.
+    pub body: Option<&'m FunctionBody<'a>>,
+    /// The name of the function in the `name` section, or else the name of its first export;
+    /// see `WasmModule::function_names`.
+    pub name: Option<&'m str>,
+}
+
+/// How many imports of each kind a module has, for `WasmModule::index_spaces`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ImportCounts {
+    pub(crate) functions: u32,
+    pub(crate) tables: u32,
+    pub(crate) memories: u32,
+    pub(crate) globals: u32,
+}
+
+/// An export, resolved through the index space of its kind; see `WasmModule::export`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportRef<'m> {
+    /// The signature of the exported function.
+    Function(&'m WasmFunctionType),
+    Table(&'m TableType),
+    Memory(&'m MemoryType),
+    Global(&'m GlobalType),
+}
+
+impl<'a> WasmModule<'a> {
+    /// Store a decoded section in the matching field of the module.
+    /// Sections whose contents are not decoded yet are dropped.
+    pub(crate) fn consume(&mut self, section: WasmSection<'a>) {
+        match section {
+            WasmSection::Type(section) => self.types = section.items,
+            WasmSection::Import(section) => self.imports = section.items,
+            WasmSection::Function(section) => self.functions = section.items,
+            WasmSection::Export(section) => self.exports = section.items,
+            WasmSection::Code(section) => self.code = section.items,
+            WasmSection::Table(section) => self.tables = section.items,
+            WasmSection::Memory(section) => self.memories = section.items,
+            WasmSection::Global(section) => self.globals = section.items,
+            WasmSection::Element(section) => self.elements = section.items,
+            WasmSection::Data(section) => self.data = section.items,
+            WasmSection::Tag(section) => self.tags = section.items,
+            WasmSection::Custom(section) => self.raw_sections.push(RawSection::Custom(section)),
+            WasmSection::Start(()) | WasmSection::DataCount(()) | WasmSection::Unknown { .. } => {}
+        }
+    }
+
+    pub fn version(&self) -> WasmVersion {
+        self.version
+    }
+
+    /// The function types of the type section, referenced by index from imports and functions.
+    pub fn types(&self) -> &[WasmFunctionType] {
+        &self.types
+    }
+
+    pub fn imports(&self) -> &[WasmImportEntry<'a>] {
+        &self.imports
+    }
+
+    /// The type index of every function defined in the module, in order.
+    pub fn functions(&self) -> impl Iterator<Item = TypeIndex> + '_ {
+        self.functions.iter().copied()
+    }
+
+    pub fn exports(&self) -> &[WasmExportEntry<'a>] {
+        &self.exports
+    }
+
+    pub fn num_imported_functions(&self) -> u32 {
+        self.imported_functions().count() as u32
+    }
+
+    pub fn num_defined_functions(&self) -> u32 {
+        self.functions.len() as u32
+    }
+
+    /// The function at `index` in the function index space, or `None` if there is none.
+    pub fn function(&self, index: u32) -> Option<FunctionRef<'_, 'a>> {
+        self.index_spaces().resolve_function(index)
+    }
+
+    /// How many functions, tables, memories and globals the module imports and defines, to
+    /// tell which indices refer to imports.
+    pub fn index_spaces(&self) -> IndexSpaces<'_, 'a> {
+        IndexSpaces::new(self)
+    }
+
+    /// The type of the function at `func_index` in the function index space. `None` if there
+    /// is no such function, or its type index is out of range.
+    pub fn function_signature(&self, func_index: u32) -> Option<&WasmFunctionType> {
+        let type_index = self
+            .imported_functions()
+            .chain(self.functions())
+            .nth(func_index as usize)?;
+        self.types.get(u32::from(type_index) as usize)
+    }
+
+    /// Every function in function index order: the imported ones, then the defined ones.
+    ///
+    /// ```
+    /// let bytes = std::fs::read("tests/fixtures/features.wasm").unwrap();
+    /// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+    /// let exports = module.exported_functions();
+    ///
+    /// let mut sizes: Vec<_> = (exports.iter())
+    ///     .map(|(name, &index)| {
+    ///         let function = module.iter_functions().nth(u32::from(index) as usize).unwrap();
+    ///         (name.as_str(), function.body.map_or(0, |body| body.expression().len()))
+    ///     })
+    ///     .collect();
+    /// sizes.sort();
+    /// assert_eq!(sizes, [("add", 14), ("start", 8)]);
+    ///
+    /// let add = module.iter_functions().nth(u32::from(exports["add"]) as usize).unwrap();
+    /// assert!(!add.imported);
+    /// assert_eq!(add.signature.unwrap().to_string(), "(i32, i32) -> i32");
+    /// ```
+    pub fn iter_functions(&self) -> impl Iterator<Item = FunctionInfo<'_, 'a>> {
+        let names = self.function_names();
+        let imported = self
+            .imported_functions()
+            .map(|type_index| (true, type_index, None));
+        let defined = self
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(defined, &type_index)| (false, type_index, self.code.get(defined)));
+
+        imported
+            .chain(defined)
+            .enumerate()
+            .map(move |(index, (imported, type_index, body))| FunctionInfo {
+                index: index as u32,
+                imported,
+                signature: self.types.get(u32::from(type_index) as usize),
+                body,
+                name: names.get(&(index as u32)).copied(),
+            })
+    }
+
+    /// The name of every function that has one, by function index. A name in the `name`
+    /// section takes precedence; failing that, a function is named after its first export,
+    /// as release builds usually strip the `name` section but keep their exports. A malformed
+    /// `name` section is ignored.
+    pub fn function_names(&self) -> HashMap<u32, &str> {
+        let mut names = HashMap::new();
+        for export in &self.exports {
+            if let WasmExportDescriptor::Function(index) = export.descriptor {
+                names.entry(index.into()).or_insert(&*export.name);
+            }
+        }
+
+        if let Some(section) = self.custom_sections_by_name("name").next() {
+            if let Ok(section_names) = decode_function_names(section.data()) {
+                names.extend(section_names);
+            }
+        }
+        names
+    }
+
+    /// The first function called `name`, as named by `function_names`.
+    pub fn find_function_by_name(&self, name: &str) -> Option<FunctionInfo<'_, 'a>> {
+        self.iter_functions()
+            .find(|function| function.name == Some(name))
+    }
+
+    /// The function index of every exported function, by export name. If a name is exported
+    /// more than once, the first export wins.
+    pub fn exported_functions(&self) -> HashMap<String, FuncIndex> {
+        let mut functions = HashMap::new();
+        for export in &self.exports {
+            if let WasmExportDescriptor::Function(index) = export.descriptor {
+                functions.entry(export.name.to_string()).or_insert(index);
+            }
+        }
+        functions
+    }
+
+    /// The first export named exactly `name`, resolved to what it refers to. `None` if there
+    /// is no such export, or its index is out of range.
+    pub fn export(&self, name: &str) -> Option<ExportRef<'_>> {
+        let export = self.exports.iter().find(|export| export.name == name)?;
+        self.resolve_export(&export.descriptor)
+    }
+
+    /// Every export together with what it refers to, or `None` where that cannot be resolved;
+    /// see `export`.
+    pub fn resolved_exports(
+        &self,
+    ) -> impl Iterator<Item = (&WasmExportEntry<'a>, Option<ExportRef<'_>>)> {
+        self.exports
+            .iter()
+            .map(|export| (export, self.resolve_export(&export.descriptor)))
+    }
+
+    fn resolve_export(&self, descriptor: &WasmExportDescriptor) -> Option<ExportRef<'_>> {
+        match *descriptor {
+            WasmExportDescriptor::Function(index) => self
+                .function_signature(index.into())
+                .map(ExportRef::Function),
+            WasmExportDescriptor::Table(index) => self.table(index.into()).map(ExportRef::Table),
+            WasmExportDescriptor::Memory(index) => self.memory(index.into()).map(ExportRef::Memory),
+            WasmExportDescriptor::Global(index) => self
+                .imported_globals()
+                .chain(self.globals.iter().map(Global::global_type))
+                .nth(u32::from(index) as usize)
+                .map(ExportRef::Global),
+        }
+    }
+
+    /// The bodies of the functions defined in the module, in the same order as `functions`.
+    pub fn code(&self) -> &[FunctionBody<'a>] {
+        &self.code
+    }
+
+    /// The memories defined in the memory section, not counting imported ones.
+    pub fn memories(&self) -> &[MemoryType] {
+        &self.memories
+    }
+
+    /// The number of memories in the memory index space, imported ones included.
+    pub fn num_memories(&self) -> u32 {
+        (self.imported_memories().count() + self.memories.len()) as u32
+    }
+
+    /// The memory at `index` in the memory index space, which counts imported memories first,
+    /// then the ones defined in the module. `None` if there is no such memory.
+    pub fn memory(&self, index: u32) -> Option<&MemoryType> {
+        self.imported_memories()
+            .chain(&self.memories)
+            .nth(index as usize)
+    }
+
+    /// Every table of the table index space, in index order: the imported ones, then the
+    /// ones defined in the table section.
+    pub fn tables(&self) -> Vec<&TableType> {
+        self.imported_tables().chain(&self.tables).collect()
+    }
+
+    /// The number of tables in the table index space, imported ones included.
+    pub fn num_tables(&self) -> u32 {
+        (self.imported_tables().count() + self.tables.len()) as u32
+    }
+
+    /// The table at `index` in the table index space, which counts imported tables first, then
+    /// the ones defined in the module. `None` if there is no such table.
+    pub fn table(&self, index: u32) -> Option<&TableType> {
+        self.imported_tables()
+            .chain(&self.tables)
+            .nth(index as usize)
+    }
+
+    /// The globals defined in the global section, not counting imported ones.
+    pub fn globals(&self) -> &[Global] {
+        &self.globals
+    }
+
+    /// The segments of the element section, which initialize tables.
+    pub fn elements(&self) -> &[ElementSegment] {
+        &self.elements
+    }
+
+    /// The segments of the data section.
+    pub fn data(&self) -> &[DataSegment<'a>] {
+        &self.data
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// The location of every section in the decoded buffer, in the order they appeared. Adding
+    /// or removing custom sections does not change it.
+    pub fn sections(&self) -> &[SectionInfo] {
+        &self.sections
+    }
+
+    /// The custom sections, in the order they appeared.
+    pub fn custom_sections(&self) -> impl Iterator<Item = &CustomSection<'a>> + '_ {
+        self.raw_sections
+            .iter()
+            .filter_map(|section| match section {
+                RawSection::Custom(section) => Some(section),
+                RawSection::Standard { .. } => None,
+            })
+    }
+
+    /// The custom sections called `name`, in the order they appeared.
+    pub fn custom_sections_by_name<'s>(
+        &'s self,
+        name: &'s str,
+    ) -> impl Iterator<Item = &'s CustomSection<'a>> + 's {
+        self.custom_sections()
+            .filter(move |section| section.name() == name)
+    }
+
+    /// The first `linking` custom section, decoded, or `None` if there is none. Only
+    /// relocatable object files have one; it is decoded on demand since a malformed one does
+    /// not make the module invalid.
+    pub fn linking_section(&self) -> Option<Result<LinkingSection<'_>, DecodeError>> {
+        self.custom_sections_by_name("linking")
+            .next()
+            .map(|section| decode_linking_section(section.data()))
+    }
+
+    /// Replace the data of the custom section called `name`, keeping its place among the
+    /// other sections, or add the section at the end if there is none. Any further sections of
+    /// the same name are removed.
+    pub fn set_custom_section(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        data: impl Into<Cow<'a, [u8]>>,
+    ) {
+        let section = CustomSection::new(name, data);
+        let position = self.raw_sections.iter().position(
+            |existing| matches!(existing, RawSection::Custom(existing) if existing.name == section.name),
+        );
+
+        match position {
+            Some(position) => {
+                self.remove_custom_section_after(&section.name, position + 1);
+                self.raw_sections[position] = RawSection::Custom(section);
+            }
+            None => self.raw_sections.push(RawSection::Custom(section)),
+        }
+    }
+
+    /// Remove every custom section called `name`, returning whether there was one.
+    pub fn remove_custom_section(&mut self, name: &str) -> bool {
+        self.remove_custom_section_after(name, 0)
+    }
+
+    fn remove_custom_section_after(&mut self, name: &str, start: usize) -> bool {
+        let before = self.raw_sections.len();
+        let mut index = 0;
+        self.raw_sections.retain(|section| {
+            index += 1;
+            index <= start
+                || !matches!(section, RawSection::Custom(section) if section.name == name)
+        });
+        self.raw_sections.len() != before
+    }
+
+    /// The signature of a `block`, `loop` or `if` with the given block type. Type indices are
+    /// looked up in the type section, so a block may take parameters and produce several
+    /// results; `None` if the index is out of range.
+    pub fn block_signature(&self, block_type: BlockType) -> Option<WasmFunctionType> {
+        match block_type {
+            BlockType::Empty => Some(WasmFunctionType::default()),
+            BlockType::Value(value_type) => Some(WasmFunctionType {
+                params: Vec::new(),
+                returns: vec![value_type],
+            }),
+            BlockType::TypeIndex(index) => self.types.get(u32::from(index) as usize).cloned(),
+        }
+    }
+
+    /// The imports whose module name is `module_name`, e.g. `"wasi_snapshot_preview1"`.
+    pub fn imports_from<'s>(
+        &'s self,
+        module_name: &'s str,
+    ) -> impl Iterator<Item = &'s WasmImportEntry<'a>> + 's {
+        self.imports
+            .iter()
+            .filter(move |import| import.module_name == module_name)
+    }
+
+    /// The type index of every imported function, in import order.
+    pub fn imported_functions(&self) -> impl Iterator<Item = TypeIndex> + '_ {
+        self.imports
+            .iter()
+            .filter_map(|import| match import.descriptor {
+                WasmImportDescriptor::Function(type_index) => Some(type_index),
+                _ => None,
+            })
+    }
+
+    /// The types of the tables imported by the module, in import order.
+    pub fn imported_tables(&self) -> impl Iterator<Item = &TableType> {
+        self.imports
+            .iter()
+            .filter_map(|import| match &import.descriptor {
+                WasmImportDescriptor::Table(table_type) => Some(table_type),
+                _ => None,
+            })
+    }
+
+    /// The types of the globals imported by the module, in import order.
+    pub fn imported_globals(&self) -> impl Iterator<Item = &GlobalType> {
+        self.imports
+            .iter()
+            .filter_map(|import| match &import.descriptor {
+                WasmImportDescriptor::Global(global_type) => Some(global_type),
+                _ => None,
+            })
+    }
+
+    /// The types of the memories imported by the module, in import order.
+    pub fn imported_memories(&self) -> impl Iterator<Item = &MemoryType> {
+        self.imports
+            .iter()
+            .filter_map(|import| match &import.descriptor {
+                WasmImportDescriptor::Memory(memory_type) => Some(memory_type),
+                _ => None,
+            })
+    }
+
+    /// The number of imports of each kind; `index_spaces` exposes them with the number of
+    /// definitions.
+    pub(crate) fn import_counts(&self) -> ImportCounts {
+        let mut counts = ImportCounts::default();
+        for import in &self.imports {
+            match import.descriptor {
+                WasmImportDescriptor::Function(_) => counts.functions += 1,
+                WasmImportDescriptor::Table(_) => counts.tables += 1,
+                WasmImportDescriptor::Memory(_) => counts.memories += 1,
+                WasmImportDescriptor::Global(_) => counts.globals += 1,
+            }
+        }
+        counts
+    }
+
+    /// The initial and maximum size of the module's memory: that of the first memory defined
+    /// in the memory section, or else of the first imported memory. `None` if the module has no
+    /// memory.
+    pub fn memory_requirements(&self) -> Option<WasmLimits> {
+        self.memories
+            .first()
+            .or_else(|| self.imported_memories().next())
+            .map(|memory| memory.limits.clone())
+    }
+
+    /// Check what decoding each section on its own cannot: that every function index in an
+    /// element segment, either listed or in a `ref.func`, is within the function index space.
+    pub fn validate(&self) -> Result<(), DecodeError> {
+        let count = self.num_imported_functions() + self.num_defined_functions();
+
+        for segment in &self.elements {
+            let indices: Vec<u32> = match &segment.items {
+                ElementItems::Functions(indices) => {
+                    indices.iter().map(|&index| index.into()).collect()
+                }
+                ElementItems::Expressions(exprs) => (exprs.iter())
+                    .filter_map(|expr| match *expr {
+                        ConstExpr::RefFunc(index) => Some(index.into()),
+                        _ => None,
+                    })
+                    .collect(),
+            };
+            if let Some(&index) = indices.iter().find(|&&index| index >= count) {
+                return Err(DecodeError::FuncIndexOutOfBounds { index, count });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Detach the module from the buffer it was decoded from, copying any borrowed data.
+    pub fn into_owned(self) -> WasmModule<'static> {
+        WasmModule {
+            version: self.version,
+            types: self.types,
+            imports: self
+                .imports
+                .into_iter()
+                .map(WasmImportEntry::into_owned)
+                .collect(),
+            functions: self.functions,
+            exports: self
+                .exports
+                .into_iter()
+                .map(WasmExportEntry::into_owned)
+                .collect(),
+            code: self
+                .code
+                .into_iter()
+                .map(FunctionBody::into_owned)
+                .collect(),
+            tables: self.tables,
+            memories: self.memories,
+            globals: self.globals,
+            elements: self.elements,
+            data: self.data.into_iter().map(DataSegment::into_owned).collect(),
+            tags: self.tags,
+            sections: self.sections,
+            raw_sections: self
+                .raw_sections
+                .into_iter()
+                .map(RawSection::into_owned)
+                .collect(),
+        }
+    }
+
+    /// Like `into_owned`, but leaves this module (and its borrow of the buffer) intact.
+    pub fn to_owned(&self) -> WasmModule<'static> {
+        self.clone().into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportRef, FunctionInfo, FunctionRef, ImportCounts};
+    use crate::decode::DecodeError;
+    use crate::decode_bytes;
+    use crate::encode::{func_type, memory_type, ModuleBuilder};
+    use crate::types::{
+        ConstExpr, ElementItems, ElementMode, ElementSegment, GlobalType, Mutability, SymbolKind,
+        TableType, WasmElementType, WasmExportDescriptor, WasmImportDescriptor, WasmLimits,
+        WasmValueType,
+    };
+
+    #[test]
+    fn test_imported_globals() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x02, 0x13, 0x02, // import section, 2 entries
+            0x03, 0x65, 0x6e, 0x76, 0x01, 0x66, 0x02, 0x00, 0x01, // env.f: memory, min 1
+            0x03, 0x65, 0x6e, 0x76, 0x01, 0x67, 0x03, 0x7e, 0x01, // env.g: global (mut i64)
+        ];
+
+        let module = decode_bytes(&bytes).unwrap();
+        let globals: Vec<_> = module.imported_globals().collect();
+
+        assert_eq!(globals.len(), 1);
+        assert_eq!(globals[0].value_type(), WasmValueType::I64);
+        assert!(globals[0].is_mutable());
+    }
+
+    #[test]
+    fn test_import_counts() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[WasmValueType::I32], &[])
+            .add_import("env", "log", func_type(0))
+            .build();
+        let counts = decode_bytes(&bytes).unwrap().import_counts();
+        assert_eq!(
+            counts,
+            ImportCounts {
+                functions: 1,
+                ..ImportCounts::default()
+            }
+        );
+
+        let bytes = include_bytes!("../../tests/fixtures/imports.wasm");
+        let counts = decode_bytes(bytes).unwrap().import_counts();
+        assert_eq!((counts.functions, counts.memories), (1, 1));
+        assert_eq!((counts.tables, counts.globals), (0, 0));
+    }
+
+    #[test]
+    fn test_validate_element_function_indices() {
+        // One imported and one defined function.
+        let module = |items| {
+            let bytes = ModuleBuilder::new()
+                .add_type(&[], &[])
+                .add_import("env", "f", func_type(0))
+                .add_function(0, &[], &[0x0b])
+                .add_table(TableType::new(
+                    WasmElementType::Funcref,
+                    WasmLimits::new(2, None),
+                ))
+                .add_element(ElementSegment {
+                    mode: ElementMode::Active {
+                        table_index: 0.into(),
+                        offset: ConstExpr::I32Const(0),
+                    },
+                    element_type: WasmElementType::Funcref,
+                    items,
+                })
+                .build();
+            decode_bytes(&bytes).unwrap().into_owned()
+        };
+
+        assert!(module(ElementItems::Functions(vec![0.into(), 1.into()]))
+            .validate()
+            .is_ok());
+
+        let error = module(ElementItems::Functions(vec![1.into(), 2.into()]))
+            .validate()
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            DecodeError::FuncIndexOutOfBounds { index: 2, count: 2 }
+        ));
+
+        let exprs = vec![
+            ConstExpr::RefNull(WasmElementType::Funcref),
+            ConstExpr::RefFunc(5.into()),
+        ];
+        let error = module(ElementItems::Expressions(exprs))
+            .validate()
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            DecodeError::FuncIndexOutOfBounds { index: 5, count: 2 }
+        ));
+    }
+
+    #[test]
+    fn test_custom_sections_by_name() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x00, 0x06, 0x04, b'n', b'a', b'm', b'e', 0x01, // "name", data [1]
+            0x00, 0x02, 0x01, b'x', // "x", no data
+            0x00, 0x06, 0x04, b'n', b'a', b'm', b'e', 0x02, // "name", data [2]
+        ];
+        let module = decode_bytes(&bytes).unwrap();
+
+        let data: Vec<_> = module
+            .custom_sections_by_name("name")
+            .map(|section| section.data())
+            .collect();
+        assert_eq!(data, [[0x01], [0x02]]);
+        assert_eq!(module.custom_sections_by_name("missing").count(), 0);
+
+        // The name points into `bytes`.
+        let name = module.custom_sections_by_name("name").next().unwrap();
+        let name = name.borrowed_name().unwrap();
+        assert_eq!(name.as_ptr(), bytes[11..].as_ptr());
+        assert_eq!(
+            module
+                .to_owned()
+                .custom_sections()
+                .next()
+                .unwrap()
+                .borrowed_name(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_memory_requirements() {
+        let imported = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x02, 0x0b, 0x01, // import section, 1 entry
+            0x03, 0x65, 0x6e, 0x76, 0x01, 0x6d, 0x02, 0x01, 0x01, 0x0a, // env.m: memory 1 10
+        ];
+
+        let module = decode_bytes(&imported).unwrap();
+        let limits = module.memory_requirements().unwrap();
+        assert_eq!(limits.min(), 1);
+        assert_eq!(limits.max(), Some(10));
+
+        // A defined memory takes precedence.
+        let mut defined = imported.to_vec();
+        defined.extend_from_slice(&[0x05, 0x03, 0x01, 0x00, 0x02]); // memory section: memory 2
+        let module = decode_bytes(&defined).unwrap();
+        let limits = module.memory_requirements().unwrap();
+        assert_eq!(limits.min(), 2);
+        assert_eq!(limits.max(), None);
+
+        assert!(decode_bytes(&imported[..8])
+            .unwrap()
+            .memory_requirements()
+            .is_none());
+    }
+
+    #[test]
+    fn test_decoded_modules_compare_equal() {
+        let bytes = include_bytes!("../../tests/fixtures/add.wasm");
+
+        let first = decode_bytes(bytes).unwrap();
+        assert_eq!(first, decode_bytes(bytes).unwrap());
+        assert_eq!(first.to_owned(), first);
+
+        let mut other = bytes.to_vec();
+        other[39] = 0x6b; // i32.add becomes i32.sub
+        assert_ne!(decode_bytes(&other).unwrap(), first);
+    }
+
+    #[test]
+    fn test_function_types_deduplicate_in_sets() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x0e, 0x03, // type section, 3 types
+            0x60, 0x01, 0x7f, 0x01, 0x7f, // (i32) -> (i32)
+            0x60, 0x00, 0x00, // () -> ()
+            0x60, 0x01, 0x7f, 0x01, 0x7f, // (i32) -> (i32)
+        ];
+
+        let module = decode_bytes(&bytes).unwrap();
+        let distinct: std::collections::HashSet<_> = module.types().iter().collect();
+
+        assert_eq!(distinct.len(), 2);
+        assert_eq!(module.types()[0], module.types()[2]);
+    }
+
+    #[test]
+    fn test_exported_functions() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_import("env", "f", func_type(0))
+            .add_function(0, &[], &[0x0b])
+            .add_function(0, &[], &[0x0b])
+            .add_export("first", WasmExportDescriptor::Function(1.into()))
+            .add_export("memory", WasmExportDescriptor::Memory(0.into()))
+            .add_export("second", WasmExportDescriptor::Function(2.into()))
+            .build();
+
+        let functions = decode_bytes(&bytes).unwrap().exported_functions();
+
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions["first"], 1.into());
+        assert_eq!(functions["second"], 2.into());
+    }
+
+    #[test]
+    fn test_function_index_space() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_type(&[WasmValueType::I32], &[])
+            .add_import("env", "a", func_type(0))
+            .add_import("env", "memory", memory_type(1))
+            .add_import("env", "b", func_type(1))
+            .add_function(1, &[], &[0x0b])
+            .add_function(0, &[], &[0x01, 0x0b])
+            .add_function(1, &[], &[0x01, 0x01, 0x0b])
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+
+        assert_eq!(module.num_imported_functions(), 2);
+        assert_eq!(module.num_defined_functions(), 3);
+
+        for (index, field_name) in [(0, "a"), (1, "b")] {
+            match module.function(index) {
+                Some(FunctionRef::Imported(import)) => assert_eq!(import.field_name(), field_name),
+                other => panic!("function {index}: {other:?}"),
+            }
+        }
+        for (index, expected_type, body_length) in [(2, 1, 1), (3, 0, 2), (4, 1, 3)] {
+            match module.function(index) {
+                Some(FunctionRef::Defined {
+                    type_index,
+                    body: Some(body),
+                }) => {
+                    assert_eq!(u32::from(type_index), expected_type);
+                    assert_eq!(body.expression().len(), body_length);
+                }
+                other => panic!("function {index}: {other:?}"),
+            }
+        }
+        assert_eq!(module.function(5), None);
+
+        let functions: Vec<_> = module.iter_functions().collect();
+        assert_eq!(functions.len(), 5);
+        for (index, function) in functions.iter().enumerate() {
+            assert_eq!(function.index, index as u32);
+            assert_eq!(function.imported, index < 2);
+            let expected_type = if matches!(index, 0 | 3) { 0 } else { 1 };
+            assert_eq!(function.signature, Some(&module.types()[expected_type]));
+        }
+        assert_eq!(
+            functions[4],
+            FunctionInfo {
+                index: 4,
+                imported: false,
+                signature: Some(&module.types()[1]),
+                body: Some(&module.code()[2]),
+                name: None,
+            }
+        );
+        assert_eq!(functions[1].body, None);
+
+        // Without imports, index 0 is the first defined function.
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_function(0, &[], &[0x0b])
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+        assert!(matches!(
+            module.function(0),
+            Some(FunctionRef::Defined { type_index, .. }) if u32::from(type_index) == 0
+        ));
+        assert_eq!(module.function(1), None);
+    }
+
+    #[test]
+    fn test_function_names() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_import("env", "f", func_type(0))
+            .add_function(0, &[], &[0x0b])
+            .add_function(0, &[], &[0x0b])
+            .add_export("run", WasmExportDescriptor::Function(1.into()))
+            .add_export("main", WasmExportDescriptor::Function(2.into()))
+            .add_export("also_main", WasmExportDescriptor::Function(2.into()))
+            .build();
+        let mut module = decode_bytes(&bytes).unwrap();
+
+        // Without a name section, functions are named after their first export.
+        let names: Vec<_> = module
+            .iter_functions()
+            .map(|function| function.name)
+            .collect();
+        assert_eq!(names, [None, Some("run"), Some("main")]);
+        assert_eq!(module.find_function_by_name("main").unwrap().index, 2);
+        assert!(module.find_function_by_name("also_main").is_none());
+
+        // The name section takes precedence where it names a function.
+        module.set_custom_section(
+            "name",
+            vec![
+                0x01, 0x0c, 0x02, // Function names, 12 bytes, 2 entries
+                0x00, 0x03, b'l', b'o', b'g', // 0: "log"
+                0x01, 0x04, b'i', b'n', b'i', b't', // 1: "init"
+            ],
+        );
+        let names: Vec<_> = module
+            .iter_functions()
+            .map(|function| function.name)
+            .collect();
+        assert_eq!(names, [Some("log"), Some("init"), Some("main")]);
+        assert_eq!(module.find_function_by_name("init").unwrap().index, 1);
+        assert!(module.find_function_by_name("run").is_none());
+
+        // A malformed name section is as good as none.
+        module.set_custom_section("name", vec![0x01, 0x05, 0x01]);
+        assert_eq!(module.function_names()[&1], "run");
+    }
+
+    #[test]
+    fn test_export_lookup() {
+        let global_type = GlobalType {
+            value_type: WasmValueType::I64,
+            mutability: Mutability::Immutable,
+        };
+        let bytes = ModuleBuilder::new()
+            .add_type(&[WasmValueType::I32], &[])
+            .add_type(&[], &[])
+            .add_import("env", "log", func_type(0))
+            .add_import("env", "memory", memory_type(2))
+            .add_import(
+                "env",
+                "g",
+                WasmImportDescriptor::Global(global_type.clone()),
+            )
+            .add_function(1, &[], &[0x0b])
+            .add_export("_start", WasmExportDescriptor::Function(1.into()))
+            .add_export("memory", WasmExportDescriptor::Memory(0.into()))
+            .add_export("log", WasmExportDescriptor::Function(0.into()))
+            .add_export("g", WasmExportDescriptor::Global(0.into()))
+            // Shadowed by the first `_start`.
+            .add_export("_start", WasmExportDescriptor::Function(0.into()))
+            .add_export("table", WasmExportDescriptor::Table(0.into()))
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+
+        assert!(matches!(
+            module.export("_start"),
+            Some(ExportRef::Function(function_type))
+                if function_type.params().is_empty() && function_type.returns().is_empty()
+        ));
+        assert!(matches!(
+            module.export("memory"),
+            Some(ExportRef::Memory(memory)) if memory.limits().min() == 2
+        ));
+        assert!(matches!(
+            module.export("log"),
+            Some(ExportRef::Function(function_type)) if function_type.params() == [WasmValueType::I32]
+        ));
+        assert_eq!(module.export("g"), Some(ExportRef::Global(&global_type)));
+
+        // There is no table to export.
+        assert_eq!(module.export("table"), None);
+        assert_eq!(module.export("missing"), None);
+        assert_eq!(module.export("_START"), None);
+
+        let resolved: Vec<_> = module
+            .resolved_exports()
+            .map(|(export, resolved)| (export.name(), resolved.is_some()))
+            .collect();
+        assert_eq!(
+            resolved,
+            [
+                ("_start", true),
+                ("memory", true),
+                ("log", true),
+                ("g", true),
+                ("_start", true),
+                ("table", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_memory_and_table_index_spaces() {
+        // Like Emscripten output, which imports its only memory.
+        let bytes = ModuleBuilder::new()
+            .add_import("env", "memory", memory_type(256))
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+
+        assert_eq!(module.num_memories(), 1);
+        assert_eq!(module.memory(0).unwrap().limits().min(), 256);
+        assert_eq!(module.memory(1), None);
+        assert_eq!(module.num_tables(), 0);
+        assert_eq!(module.table(0), None);
+
+        let table = |min| TableType::new(WasmElementType::Funcref, WasmLimits::new(min, None));
+        let bytes = ModuleBuilder::new()
+            .add_import("env", "table", WasmImportDescriptor::Table(table(1)))
+            .add_table(table(2))
+            .add_memory(3)
+            .add_export("table", WasmExportDescriptor::Table(1.into()))
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+
+        // The imported table shifts the defined one to index 1.
+        assert_eq!(module.num_tables(), 2);
+        assert_eq!(module.table(0), Some(&table(1)));
+        assert_eq!(module.table(1), Some(&table(2)));
+        assert_eq!(module.table(2), None);
+        assert_eq!(module.export("table"), Some(ExportRef::Table(&table(2))));
+
+        assert_eq!(module.num_memories(), 1);
+        assert_eq!(module.memory(0).unwrap().limits().min(), 3);
+    }
+
+    #[test]
+    fn test_tables() {
+        let imported = TableType::new(WasmElementType::Externref, WasmLimits::new(0, Some(4)));
+        let defined = TableType::new(WasmElementType::Funcref, WasmLimits::new(8, None));
+
+        let bytes = ModuleBuilder::new()
+            .add_import("env", "refs", WasmImportDescriptor::Table(imported.clone()))
+            .add_table(defined.clone())
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+
+        assert_eq!(module.tables(), [&imported, &defined]);
+        assert_eq!(module.num_tables(), 2);
+    }
+
+    #[test]
+    fn test_function_signature() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[WasmValueType::I32], &[])
+            .add_type(&[], &[WasmValueType::I64])
+            .add_import("env", "log", func_type(0))
+            .add_function(1, &[], &[0x42, 0x00, 0x0b])
+            // Points past the type section.
+            .add_function(2, &[], &[0x0b])
+            .build();
+        let module = decode_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            module.function_signature(0).unwrap().params(),
+            [WasmValueType::I32]
+        );
+        assert_eq!(
+            module.function_signature(1).unwrap().returns(),
+            [WasmValueType::I64]
+        );
+        assert_eq!(module.function_signature(2), None);
+        assert_eq!(module.function_signature(3), None);
+    }
+
+    #[test]
+    fn test_linking_section() {
+        let mut module = decode_bytes(include_bytes!("../../tests/fixtures/add.wasm")).unwrap();
+        assert!(module.linking_section().is_none());
+
+        // Version 2, a symbol table with a defined function symbol for function 0, "add".
+        module.set_custom_section(
+            "linking",
+            &[
+                0x02, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x03, b'a', b'd', b'd',
+            ][..],
+        );
+        let linking = module.linking_section().unwrap().unwrap();
+        assert_eq!(linking.symbols()[0].kind(), SymbolKind::Function(0));
+        assert_eq!(linking.symbols()[0].name(), Some("add"));
+
+        module.set_custom_section("linking", &[0x02, 0x08, 0x08][..]);
+        assert!(module.linking_section().unwrap().is_err());
+    }
+}
@@ -0,0 +1,832 @@
+use crate::decode::{remap_indices, remap_name_section, DecodeError, IndexRemap};
+use crate::encode::{
+    encode_code_section, encode_element_section, encode_export_section, encode_function_section,
+    encode_global_section, encode_import_section, encode_tag_section, encode_type_section,
+    encode_varuint,
+};
+use crate::module::{RawSection, WasmModule};
+use crate::types::{
+    ConstExpr, ElementItems, ExternalKind, FuncIndex, FunctionBody, SectionType, TypeIndex,
+    WasmExportDescriptor, WasmExportEntry, WasmImportDescriptor,
+};
+use crate::wasm::instructions::Instruction;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// How `WasmModule::dedup_types` renumbered the type section: the new index of every type,
+/// by its old index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeRemapping {
+    new_indices: Vec<u32>,
+}
+
+impl TypeRemapping {
+    /// The new index of the type that was at `old_index`, or `None` if there was none.
+    pub fn get(&self, old_index: u32) -> Option<u32> {
+        self.new_indices.get(old_index as usize).copied()
+    }
+
+    /// Whether every type kept its index, i.e. there were no duplicates.
+    pub fn is_identity(&self) -> bool {
+        self.new_indices
+            .iter()
+            .enumerate()
+            .all(|(old_index, &new_index)| old_index as u32 == new_index)
+    }
+
+    /// Like `get`, but leaves an index past the end of the old type section as it is.
+    fn remap(&self, old_index: u32) -> u32 {
+        self.get(old_index).unwrap_or(old_index)
+    }
+}
+
+impl IndexRemap for TypeRemapping {
+    fn type_index(&self, index: TypeIndex) -> TypeIndex {
+        self.remap(index.into()).into()
+    }
+}
+
+/// How `WasmModule::remove_functions` renumbered the function index space: the new index of
+/// every function, by its old index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexRemapping {
+    functions: Vec<Option<u32>>,
+}
+
+impl IndexRemapping {
+    /// The new index of the function that was at `old_index`, or `None` if it was removed or
+    /// there was none.
+    pub fn function(&self, old_index: u32) -> Option<u32> {
+        self.functions.get(old_index as usize).copied().flatten()
+    }
+
+    /// The old indices of the functions that were removed, in increasing order.
+    pub fn removed_functions(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.functions.len() as u32).filter(|&index| self.functions[index as usize].is_none())
+    }
+
+    /// Whether every function kept its index, i.e. none was removed.
+    pub fn is_identity(&self) -> bool {
+        self.functions
+            .iter()
+            .enumerate()
+            .all(|(old_index, &new_index)| new_index == Some(old_index as u32))
+    }
+}
+
+impl IndexRemap for IndexRemapping {
+    /// Leaves the index of a removed function as it is; nothing left refers to one.
+    fn func_index(&self, index: FuncIndex) -> FuncIndex {
+        let index = u32::from(index);
+        self.function(index).unwrap_or(index).into()
+    }
+}
+
+/// What `WasmModule::remove_functions_with` does with a function to remove that the rest of
+/// the module still refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferencedFunctions {
+    /// Fail with `RemoveFunctionsError::StillReferenced`, as `remove_functions` does.
+    Reject,
+    /// Keep the function, with a body that is only `unreachable`, so that calling it traps.
+    Stub,
+}
+
+/// Why `WasmModule::remove_functions` could not remove the functions asked for.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum RemoveFunctionsError {
+    /// The index is that of an imported function, or past the end of the index space.
+    #[error("Function {index} is not defined by the module.")]
+    NotDefined { index: u32 },
+
+    /// A function that is kept, an export, the start section, a global or an element segment
+    /// refers to the function. `name` is that of `WasmModule::function_names`, if any.
+    #[error("Function {index}{} is still referenced.", describe_name(.name))]
+    StillReferenced { index: u32, name: Option<String> },
+
+    /// A function body or the start section could not be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Render the name of a function as a ` (name)` suffix for error messages, if it has one.
+fn describe_name(name: &Option<String>) -> String {
+    name.as_ref()
+        .map(|name| format!(" ({name})"))
+        .unwrap_or_default()
+}
+
+/// The body of a stubbed function: `unreachable`, `end`.
+const UNREACHABLE_BODY: [u8; 2] = [0x00, 0x0b];
+
+/// Why `WasmModule::add_export` refused an export.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ExportError {
+    #[error("The module already exports {name}.")]
+    DuplicateName { name: String },
+
+    #[error("{kind:?} {index} does not exist, as the module has {count}.")]
+    IndexOutOfBounds {
+        kind: ExternalKind,
+        index: u32,
+        count: u32,
+    },
+}
+
+impl<'a> WasmModule<'a> {
+    /// Collapse structurally equal function types into the first of them, and rewrite every
+    /// type index to match: those of defined functions, function imports and tags, and the
+    /// `call_indirect`s and block types in function bodies. The sections that change are
+    /// re-encoded for `encode_module`.
+    ///
+    /// A module without duplicates is left untouched. Fails, leaving the module untouched, if a
+    /// function body cannot be decoded; only bodies that were not decoded can fail, e.g. ones
+    /// added to a `WasmModuleBuilder`.
+    pub fn dedup_types(&mut self) -> Result<TypeRemapping, DecodeError> {
+        let mut first_indices = HashMap::new();
+        let mut types = Vec::new();
+        let new_indices = self
+            .types
+            .iter()
+            .map(|function_type| {
+                *first_indices.entry(function_type).or_insert_with(|| {
+                    types.push(function_type.clone());
+                    types.len() as u32 - 1
+                })
+            })
+            .collect();
+        let remapping = TypeRemapping { new_indices };
+        if remapping.is_identity() {
+            return Ok(remapping);
+        }
+
+        let code = self
+            .code
+            .iter()
+            .map(|body| {
+                let expression = remap_indices(&body.expression, &remapping)?;
+                Ok(FunctionBody {
+                    locals: body.locals.clone(),
+                    expression: Cow::Owned(expression),
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+
+        let remap = |index| remapping.type_index(index);
+        self.types = types;
+        self.code = code;
+        for type_index in &mut self.functions {
+            *type_index = remap(*type_index);
+        }
+        for import in &mut self.imports {
+            if let WasmImportDescriptor::Function(type_index) = &mut import.descriptor {
+                *type_index = remap(*type_index);
+            }
+        }
+        for tag in &mut self.tags {
+            tag.type_index = remap(tag.type_index);
+        }
+
+        self.reencode_section(SectionType::Type, |module, out| {
+            encode_type_section(&module.types, out)
+        });
+        self.reencode_section(SectionType::Import, |module, out| {
+            encode_import_section(&module.imports, out)
+        });
+        self.reencode_section(SectionType::Function, |module, out| {
+            encode_function_section(&module.functions, out)
+        });
+        self.reencode_section(SectionType::Tag, |module, out| {
+            encode_tag_section(&module.tags, out)
+        });
+        self.reencode_section(SectionType::Code, |module, out| {
+            encode_code_section(&module.code, out)
+        });
+
+        Ok(remapping)
+    }
+
+    /// Remove the defined functions at `indices` of the function index space, and renumber the
+    /// functions after them, rewriting every reference to match: calls and `ref.func`s in the
+    /// bodies left, exports, the start section, globals, element segments and the function
+    /// names of the `name` section, which is dropped if it cannot be decoded. The sections that
+    /// change are re-encoded for `encode_module`.
+    ///
+    /// Fails, leaving the module untouched, if an index is not that of a defined function, if
+    /// anything but the removed functions themselves still refers to one of them, or if a
+    /// function body cannot be decoded.
+    pub fn remove_functions(
+        &mut self,
+        indices: &[u32],
+    ) -> Result<IndexRemapping, RemoveFunctionsError> {
+        self.remove_functions_with(indices, ReferencedFunctions::Reject)
+    }
+
+    /// Like `remove_functions`, with `referenced` saying what to do with functions to remove
+    /// that are still referenced. A stubbed function keeps its place in the index space, so it
+    /// is not marked as removed in the returned remapping.
+    pub fn remove_functions_with(
+        &mut self,
+        indices: &[u32],
+        referenced: ReferencedFunctions,
+    ) -> Result<IndexRemapping, RemoveFunctionsError> {
+        let num_imported = self.num_imported_functions() as usize;
+        let total = num_imported + self.functions.len();
+        let mut removed = vec![false; total];
+        for &index in indices {
+            if (index as usize) < num_imported || index as usize >= total {
+                return Err(RemoveFunctionsError::NotDefined { index });
+            }
+            removed[index as usize] = true;
+        }
+
+        let start_function = self.start_function()?;
+        let mut stubbed = vec![false; total];
+        for index in self.function_references(start_function, &removed)? {
+            let index = u32::from(index) as usize;
+            if removed.get(index) == Some(&true) {
+                match referenced {
+                    ReferencedFunctions::Reject => {
+                        let index = index as u32;
+                        return Err(RemoveFunctionsError::StillReferenced {
+                            index,
+                            name: self
+                                .function_names()
+                                .get(&index)
+                                .map(|name| name.to_string()),
+                        });
+                    }
+                    ReferencedFunctions::Stub => (removed[index], stubbed[index]) = (false, true),
+                }
+            }
+        }
+
+        let mut next_index = 0;
+        let functions = removed
+            .iter()
+            .map(|&removed| {
+                (!removed).then(|| {
+                    next_index += 1;
+                    next_index - 1
+                })
+            })
+            .collect();
+        let remapping = IndexRemapping { functions };
+        if remapping.is_identity() && !stubbed.contains(&true) {
+            return Ok(remapping);
+        }
+
+        let mut code = Vec::with_capacity(self.code.len());
+        for (defined, body) in self.code.iter().enumerate() {
+            let index = num_imported + defined;
+            if removed.get(index) == Some(&true) {
+                continue;
+            }
+            code.push(if stubbed.get(index) == Some(&true) {
+                FunctionBody {
+                    locals: Vec::new(),
+                    expression: Cow::Borrowed(&UNREACHABLE_BODY[..]),
+                }
+            } else {
+                FunctionBody {
+                    locals: body.locals.clone(),
+                    expression: Cow::Owned(remap_indices(&body.expression, &remapping)?),
+                }
+            });
+        }
+        let names = (self.custom_sections())
+            .find(|section| section.name() == "name")
+            .map(|section| remap_name_section(section.data(), |index| remapping.function(index)));
+
+        self.code = code;
+        let mut index = num_imported;
+        self.functions.retain(|_| {
+            index += 1;
+            !removed[index - 1]
+        });
+        for export in &mut self.exports {
+            export.descriptor = remapping.export(export.descriptor.clone());
+        }
+        for global in &mut self.globals {
+            global.init = remapping.const_expr(global.init);
+        }
+        self.elements = std::mem::take(&mut self.elements)
+            .into_iter()
+            .map(|segment| remapping.element_segment(segment))
+            .collect();
+
+        self.reencode_section(SectionType::Function, |module, out| {
+            encode_function_section(&module.functions, out)
+        });
+        self.reencode_section(SectionType::Export, |module, out| {
+            encode_export_section(&module.exports, out)
+        });
+        if let Some(function) = start_function {
+            self.reencode_section(SectionType::Start, |_, out| {
+                encode_varuint(remapping.func_index(function).into(), out)
+            });
+        }
+        self.reencode_section(SectionType::Global, |module, out| {
+            encode_global_section(&module.globals, out)
+        });
+        self.reencode_section(SectionType::Element, |module, out| {
+            encode_element_section(&module.elements, out)
+        });
+        self.reencode_section(SectionType::Code, |module, out| {
+            encode_code_section(&module.code, out)
+        });
+        match names {
+            Some(Ok(names)) => self.set_custom_section("name", names),
+            Some(Err(_)) => {
+                self.remove_custom_section("name");
+            }
+            None => {}
+        }
+
+        Ok(remapping)
+    }
+
+    /// The index of every function that is referred to from outside the functions marked in
+    /// `skipped`, by their index in the function index space.
+    fn function_references(
+        &self,
+        start_function: Option<FuncIndex>,
+        skipped: &[bool],
+    ) -> Result<Vec<FuncIndex>, DecodeError> {
+        let mut references: Vec<_> = start_function.into_iter().collect();
+        let const_expr = |expr: &ConstExpr| match *expr {
+            ConstExpr::RefFunc(index) => Some(index),
+            _ => None,
+        };
+
+        references.extend(
+            (self.exports.iter()).filter_map(|export| match export.descriptor {
+                WasmExportDescriptor::Function(index) => Some(index),
+                _ => None,
+            }),
+        );
+        references.extend((self.globals.iter()).filter_map(|global| const_expr(&global.init)));
+        for segment in &self.elements {
+            match &segment.items {
+                ElementItems::Functions(indices) => references.extend(indices),
+                ElementItems::Expressions(exprs) => {
+                    references.extend(exprs.iter().filter_map(const_expr))
+                }
+            }
+        }
+
+        let num_imported = self.num_imported_functions() as usize;
+        for (defined, body) in self.code.iter().enumerate() {
+            if skipped.get(num_imported + defined) == Some(&true) {
+                continue;
+            }
+            for instruction in body.instructions() {
+                if let Instruction::Call(index) | Instruction::RefFunc(index) = instruction? {
+                    references.push(index);
+                }
+            }
+        }
+        Ok(references)
+    }
+
+    /// Rename every import of `old_module.old_field` to `new_module.new_field`, e.g. to point
+    /// `wasi_snapshot_preview1` imports at a shim. Returns whether there was such an import.
+    /// Only the import section is re-encoded; every other section is written back unchanged.
+    ///
+    /// ```
+    /// let bytes = std::fs::read("tests/fixtures/wasi_hello.wasm").unwrap();
+    /// let mut module = wasm_thing::decode_bytes(&bytes).unwrap();
+    ///
+    /// assert!(module.rename_import("wasi_snapshot_preview1", "fd_write", "shim", "fd_write"));
+    /// let encoded = wasm_thing::encode_module(&module);
+    /// let module = wasm_thing::decode_bytes(&encoded).unwrap();
+    /// assert_eq!(module.imports_from("shim").count(), 1);
+    /// ```
+    pub fn rename_import(
+        &mut self,
+        old_module: &str,
+        old_field: &str,
+        new_module: impl Into<Cow<'a, str>>,
+        new_field: impl Into<Cow<'a, str>>,
+    ) -> bool {
+        let (new_module, new_field) = (new_module.into(), new_field.into());
+        let mut renamed = false;
+        for import in &mut self.imports {
+            if import.module_name == old_module && import.field_name == old_field {
+                import.module_name = new_module.clone();
+                import.field_name = new_field.clone();
+                renamed = true;
+            }
+        }
+
+        if renamed {
+            self.reencode_section(SectionType::Import, |module, out| {
+                encode_import_section(&module.imports, out)
+            });
+        }
+        renamed
+    }
+
+    /// Export the item `descriptor` refers to as `name`, re-encoding the export section, or
+    /// adding one if the module has none. Fails, leaving the module untouched, if something is
+    /// already exported as `name` or the index is past the end of its index space, imports
+    /// included.
+    pub fn add_export(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: WasmExportDescriptor,
+    ) -> Result<(), ExportError> {
+        let name = name.into();
+        if self.exports.iter().any(|export| export.name == name) {
+            return Err(ExportError::DuplicateName {
+                name: name.into_owned(),
+            });
+        }
+
+        let kind = descriptor.kind();
+        let count = self.index_spaces().of_kind(kind).len();
+        if descriptor.index() >= count {
+            return Err(ExportError::IndexOutOfBounds {
+                kind,
+                index: descriptor.index(),
+                count,
+            });
+        }
+
+        self.exports.push(WasmExportEntry { name, descriptor });
+        self.reencode_or_insert_section(SectionType::Export, |module, out| {
+            encode_export_section(&module.exports, out)
+        });
+        Ok(())
+    }
+
+    /// Remove every export called `name`, returning whether there was one. The item itself
+    /// stays in the module.
+    pub fn remove_export(&mut self, name: &str) -> bool {
+        let count = self.exports.len();
+        self.exports.retain(|export| export.name != name);
+        if self.exports.len() == count {
+            return false;
+        }
+
+        self.reencode_section(SectionType::Export, |module, out| {
+            encode_export_section(&module.exports, out)
+        });
+        true
+    }
+
+    /// Like `reencode_section`, but adds the section where it belongs among the others if the
+    /// module has none.
+    fn reencode_or_insert_section(
+        &mut self,
+        section_type: SectionType,
+        encode: impl FnOnce(&Self, &mut Vec<u8>),
+    ) {
+        let exists = self.raw_sections.iter().any(|section| {
+            matches!(section, RawSection::Standard { section_type: existing, .. } if *existing == section_type)
+        });
+        if !exists {
+            let position = self
+                .raw_sections
+                .iter()
+                .position(|section| match section {
+                    RawSection::Standard {
+                        section_type: existing,
+                        ..
+                    } => existing.canonical_order() > section_type.canonical_order(),
+                    RawSection::Custom(_) => false,
+                })
+                .unwrap_or(self.raw_sections.len());
+            self.raw_sections.insert(
+                position,
+                RawSection::Standard {
+                    section_type,
+                    contents: Cow::Borrowed(&[]),
+                },
+            );
+        }
+        self.reencode_section(section_type, encode);
+    }
+
+    /// Replace the kept contents of the section of `section_type` with those written by
+    /// `encode`, after a transform changed it. Does nothing if the module has no such section.
+    fn reencode_section(
+        &mut self,
+        section_type: SectionType,
+        encode: impl FnOnce(&Self, &mut Vec<u8>),
+    ) {
+        let position = self.raw_sections.iter().position(|section| match section {
+            RawSection::Standard {
+                section_type: existing,
+                ..
+            } => *existing == section_type,
+            RawSection::Custom(_) => false,
+        });
+        let Some(index) = position else {
+            return;
+        };
+
+        let mut contents = Vec::new();
+        encode(self, &mut contents);
+        self.raw_sections[index] = RawSection::Standard {
+            section_type,
+            contents: Cow::Owned(contents),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportError, ReferencedFunctions, RemoveFunctionsError};
+    use crate::encode::{func_type, memory_type, ModuleBuilder};
+    use crate::encode_module;
+    use crate::types::{
+        ConstExpr, ElementItems, ElementMode, ElementSegment, ExternalKind, SectionType, TableType,
+        WasmElementType, WasmExportDescriptor, WasmLimits, WasmValueType,
+    };
+    use crate::{decode_bytes, WasmModule};
+
+    /// `local.get 0`, `local.get 0`, `call_indirect (type 2)`, `block (type 3)`, `end`, `end`.
+    const BODY: [u8; 11] = [
+        0x20, 0x00, 0x20, 0x00, 0x11, 0x02, 0x00, 0x02, 0x03, 0x0b, 0x0b,
+    ];
+
+    fn types_of(module: &WasmModule) -> Vec<String> {
+        module.types().iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_dedup_types() {
+        use WasmValueType::I32;
+
+        let bytes = ModuleBuilder::new()
+            .add_type(&[I32], &[I32])
+            .add_type(&[], &[])
+            .add_type(&[I32], &[I32])
+            .add_type(&[I32], &[I32])
+            .add_import("env", "f", func_type(2))
+            .add_function(3, &[], &BODY)
+            .add_table(TableType::new(
+                WasmElementType::Funcref,
+                WasmLimits::new(1, None),
+            ))
+            .build();
+        let mut module = decode_bytes(&bytes).unwrap();
+
+        let remapping = module.dedup_types().unwrap();
+        assert!(!remapping.is_identity());
+        let new_indices: Vec<_> = (0..5).map(|index| remapping.get(index)).collect();
+        assert_eq!(new_indices, [Some(0), Some(1), Some(0), Some(0), None]);
+
+        // The re-encoded module decodes to the rewritten one.
+        let encoded = encode_module(&module);
+        let decoded = decode_bytes(&encoded).unwrap();
+        assert_eq!(types_of(&decoded), ["(i32) -> i32", "() -> ()"]);
+        assert_eq!(decoded.functions().map(u32::from).collect::<Vec<_>>(), [0]);
+        assert_eq!(decoded.imports()[0].descriptor(), &func_type(0));
+        assert_eq!(
+            decoded.code()[0].expression(),
+            [0x20, 0x00, 0x20, 0x00, 0x11, 0x00, 0x00, 0x02, 0x00, 0x0b, 0x0b]
+        );
+        assert_eq!(decoded.code(), module.code());
+    }
+
+    #[test]
+    fn test_dedup_types_without_duplicates() {
+        use WasmValueType::{I32, I64};
+
+        let bytes = ModuleBuilder::new()
+            .add_type(&[I32], &[I32])
+            .add_type(&[I64], &[I32])
+            .add_function(1, &[], &[0x41, 0x00, 0x0b])
+            .build();
+        let mut module = decode_bytes(&bytes).unwrap();
+        let before = module.clone();
+
+        let remapping = module.dedup_types().unwrap();
+        assert!(remapping.is_identity());
+        assert_eq!(module, before);
+        assert_eq!(encode_module(&module), bytes);
+    }
+
+    #[test]
+    fn test_rename_import() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_import("wasi_snapshot_preview1", "fd_write", func_type(0))
+            .add_import("wasi_snapshot_preview1", "proc_exit", func_type(0))
+            .add_import("env", "memory", memory_type(1))
+            .add_function(0, &[], &[0x10, 0x00, 0x0b])
+            .build();
+        let mut module = decode_bytes(&bytes).unwrap();
+
+        assert!(module.rename_import("wasi_snapshot_preview1", "fd_write", "shim", "write"));
+        assert!(!module.rename_import("wasi_snapshot_preview1", "fd_write", "shim", "write"));
+
+        let encoded = encode_module(&module);
+        let decoded = decode_bytes(&encoded).unwrap();
+        let names: Vec<_> = decoded
+            .imports()
+            .iter()
+            .map(|import| (import.module_name(), import.field_name()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("shim", "write"),
+                ("wasi_snapshot_preview1", "proc_exit"),
+                ("env", "memory")
+            ]
+        );
+        assert_eq!(decoded.imports()[0].descriptor(), &func_type(0));
+        assert_eq!(decoded.imports()[2].descriptor(), &memory_type(1));
+        assert_eq!(decoded.code(), module.code());
+    }
+
+    #[test]
+    fn test_add_and_remove_export() {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[WasmValueType::I32])
+            .add_import("env", "f", func_type(0))
+            .add_function(0, &[], &[0x41, 0x2a, 0x0b])
+            .build();
+        let mut module = decode_bytes(&bytes).unwrap();
+
+        // The module has no export section to begin with.
+        module
+            .add_export("internal", WasmExportDescriptor::Function(1.into()))
+            .unwrap();
+        let encoded = encode_module(&module);
+        let decoded = decode_bytes(&encoded).unwrap();
+        assert_eq!(decoded.exports()[0].name(), "internal");
+        assert_eq!(decoded.exports()[0].descriptor().index(), 1);
+        let order: Vec<_> = decoded
+            .sections()
+            .iter()
+            .map(|section| section.section_type)
+            .collect();
+        assert_eq!(
+            order,
+            [
+                SectionType::Type,
+                SectionType::Import,
+                SectionType::Function,
+                SectionType::Export,
+                SectionType::Code
+            ]
+        );
+
+        assert_eq!(
+            module.add_export("internal", WasmExportDescriptor::Function(0.into())),
+            Err(ExportError::DuplicateName {
+                name: "internal".to_string()
+            })
+        );
+        assert_eq!(
+            module.add_export("missing", WasmExportDescriptor::Function(2.into())),
+            Err(ExportError::IndexOutOfBounds {
+                kind: ExternalKind::Function,
+                index: 2,
+                count: 2
+            })
+        );
+        assert_eq!(
+            module.add_export("memory", WasmExportDescriptor::Memory(0.into())),
+            Err(ExportError::IndexOutOfBounds {
+                kind: ExternalKind::Memory,
+                index: 0,
+                count: 0
+            })
+        );
+
+        assert!(module.remove_export("internal"));
+        assert!(!module.remove_export("internal"));
+        let encoded = encode_module(&module);
+        assert!(decode_bytes(&encoded).unwrap().exports().is_empty());
+    }
+
+    /// Five functions after an import: 1 calls 5, 2 calls 4, and 3 calls 1. 5 is exported, 2
+    /// is the start function, and 2 and 4 are in a table.
+    fn five_functions() -> WasmModule<'static> {
+        let bytes = ModuleBuilder::new()
+            .add_type(&[], &[])
+            .add_import("env", "f", func_type(0))
+            .add_function(0, &[], &[0x10, 0x05, 0x0b])
+            .add_function(0, &[], &[0x10, 0x04, 0x0b])
+            .add_function(0, &[], &[0x10, 0x01, 0x0b])
+            .add_function(0, &[], &[0x0b])
+            .add_function(0, &[], &[0x0b])
+            .add_table(TableType::new(
+                WasmElementType::Funcref,
+                WasmLimits::new(2, None),
+            ))
+            .add_export("run", WasmExportDescriptor::Function(5.into()))
+            .set_start(2)
+            .add_element(ElementSegment {
+                mode: ElementMode::Active {
+                    table_index: 0.into(),
+                    offset: ConstExpr::I32Const(0),
+                },
+                element_type: WasmElementType::Funcref,
+                items: ElementItems::Functions(vec![2.into(), 4.into()]),
+            })
+            .build();
+        let mut module = decode_bytes(&bytes).unwrap().into_owned();
+        module.set_custom_section(
+            "name",
+            vec![
+                0x01, 0x13, 0x06, // Function names, 6 entries
+                0x00, 0x01, b'f', 0x01, 0x01, b'a', 0x02, 0x01, b'b', // 0: f, 1: a, 2: b
+                0x03, 0x01, b'c', 0x04, 0x01, b'd', 0x05, 0x01, b'e', // 3: c, 4: d, 5: e
+            ],
+        );
+        module
+    }
+
+    #[test]
+    fn test_remove_functions() {
+        let mut module = five_functions();
+        let remapping = module.remove_functions(&[3]).unwrap();
+        assert_eq!(remapping.removed_functions().collect::<Vec<_>>(), [3]);
+        assert_eq!(remapping.function(2), Some(2));
+        assert_eq!(remapping.function(3), None);
+        assert_eq!(remapping.function(5), Some(4));
+
+        let encoded = encode_module(&module);
+        let decoded = decode_bytes(&encoded).unwrap();
+        assert_eq!(decoded.num_defined_functions(), 4);
+        // The call to the last function now calls 4; the others keep their indices.
+        let bodies: Vec<_> = decoded
+            .code()
+            .iter()
+            .map(|body| body.expression())
+            .collect();
+        assert_eq!(
+            bodies,
+            [
+                &[0x10, 0x04, 0x0b][..],
+                &[0x10, 0x03, 0x0b],
+                &[0x0b],
+                &[0x0b]
+            ]
+        );
+        assert_eq!(decoded.exports()[0].descriptor().index(), 4);
+        assert_eq!(decoded.start_function().unwrap(), Some(2.into()));
+        assert_eq!(
+            decoded.elements()[0].items(),
+            &ElementItems::Functions(vec![2.into(), 3.into()])
+        );
+        let names = decoded.custom_sections().next().unwrap();
+        assert_eq!(
+            names.data(),
+            [
+                0x01, 0x10, 0x05, 0x00, 0x01, b'f', 0x01, 0x01, b'a', 0x02, 0x01, b'b', 0x03, 0x01,
+                b'd', 0x04, 0x01, b'e'
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_referenced_functions() {
+        let mut module = five_functions();
+        let before = module.clone();
+
+        assert!(matches!(
+            module.remove_functions(&[0]),
+            Err(RemoveFunctionsError::NotDefined { index: 0 })
+        ));
+        assert!(matches!(
+            module.remove_functions(&[6]),
+            Err(RemoveFunctionsError::NotDefined { index: 6 })
+        ));
+        // Function 4 is called by 2, and is in the table.
+        let error = module.remove_functions(&[3, 4]).unwrap_err();
+        assert!(matches!(
+            error,
+            RemoveFunctionsError::StillReferenced { index: 4, .. }
+        ));
+        assert_eq!(error.to_string(), "Function 4 (d) is still referenced.");
+        // Function 5 is exported as `run`, but the name section calls it `e`.
+        let error = module.remove_functions(&[5]).unwrap_err();
+        assert_eq!(error.to_string(), "Function 5 (e) is still referenced.");
+        assert_eq!(module, before);
+
+        // Only references from the removed functions themselves do not count.
+        let remapping = module.remove_functions(&[3, 1]).unwrap();
+        assert!(!remapping.is_identity());
+        assert_eq!(module.num_defined_functions(), 3);
+
+        let mut module = before;
+        let remapping = module
+            .remove_functions_with(&[3, 4], ReferencedFunctions::Stub)
+            .unwrap();
+        assert_eq!(remapping.removed_functions().collect::<Vec<_>>(), [3]);
+        let encoded = encode_module(&module);
+        let decoded = decode_bytes(&encoded).unwrap();
+        assert_eq!(decoded.code()[2].expression(), [0x00, 0x0b]);
+        assert_eq!(decoded.code()[0].expression(), [0x10, 0x04, 0x0b]);
+    }
+}
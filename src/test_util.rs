@@ -0,0 +1,28 @@
+//! Helpers shared by the unit tests of several modules.
+
+/// A tiny xorshift generator, for random but reproducible test cases without extra
+/// dependencies.
+pub(crate) struct Xorshift(u32);
+
+impl Xorshift {
+    /// `seed` must not be zero, or every number is zero.
+    pub(crate) fn new(seed: u32) -> Self {
+        Xorshift(seed)
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    pub(crate) fn next_byte(&mut self) -> u8 {
+        self.next_u32() as u8
+    }
+
+    /// A number below `bound`.
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        self.next_u32() as usize % bound
+    }
+}
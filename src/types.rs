@@ -1,6 +1,14 @@
+use crate::decode::{DecodeError, Instructions};
+use crate::encode::encode_varuint;
+use crate::wasm::instructions::Instruction;
+use std::borrow::Cow;
+use std::fmt;
+use thiserror::Error;
+
 /// AKA variable-length integer data (varuint).
 /// Wasm uses LEB128 formatting for integers.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VarUInt(u32);
 
 impl From<u32> for VarUInt {
@@ -15,110 +23,2011 @@ impl From<VarUInt> for u32 {
     }
 }
 
-impl From<VarUInt> for WasmValueType {
-    fn from(value: VarUInt) -> Self {
+impl VarUInt {
+    /// The value as a `usize`, for lengths and counts that size an allocation. Always succeeds
+    /// on 32- and 64-bit targets; on 16-bit ones, where `as usize` would silently drop the
+    /// high bits, values above `u16::MAX` fail with `DecodeError::UsizeOverflow`.
+    pub fn to_usize_checked(self) -> Result<usize, DecodeError> {
+        usize::try_from(self.0).map_err(|_| DecodeError::UsizeOverflow { value: self.0 })
+    }
+
+    /// The value in canonical LEB128, as `encode_module` writes it: as few bytes as possible.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        encode_varuint(self.0, &mut bytes);
+        bytes
+    }
+
+    /// `self + rhs`, or `None` if it does not fit in 32 bits.
+    pub fn checked_add(self, rhs: u32) -> Option<VarUInt> {
+        self.0.checked_add(rhs).map(VarUInt)
+    }
+
+    /// `self - rhs`, or `None` if `rhs` is larger.
+    pub fn checked_sub(self, rhs: u32) -> Option<VarUInt> {
+        self.0.checked_sub(rhs).map(VarUInt)
+    }
+}
+
+impl fmt::Display for VarUInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Declare an index into one index space. Each space has its own type, so passing e.g. a type
+/// index where a function index is expected does not compile.
+macro_rules! index_type {
+    ($(#[$doc:meta])* $index:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $index(u32);
+
+        impl From<u32> for $index {
+            fn from(index: u32) -> Self {
+                $index(index)
+            }
+        }
+
+        impl From<$index> for u32 {
+            fn from(index: $index) -> u32 {
+                index.0
+            }
+        }
+
+        impl From<VarUInt> for $index {
+            fn from(index: VarUInt) -> Self {
+                $index(index.0)
+            }
+        }
+
+        impl From<$index> for VarUInt {
+            fn from(index: $index) -> VarUInt {
+                VarUInt(index.0)
+            }
+        }
+
+        impl fmt::Display for $index {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    };
+}
+
+index_type!(
+    /// An index into the type section.
+    TypeIndex
+);
+index_type!(
+    /// An index into the function index space: the imported functions, then the defined ones.
+    FuncIndex
+);
+index_type!(
+    /// An index into the table index space: the imported tables, then the defined ones.
+    TableIndex
+);
+index_type!(
+    /// An index into the memory index space: the imported memories, then the defined ones.
+    MemoryIndex
+);
+
+/// The specification's name for a `MemoryIndex`, which is spelled out like the other names
+/// of this crate, e.g. `WasmModule::memories`.
+pub type MemIndex = MemoryIndex;
+
+index_type!(
+    /// An index into the global index space: the imported globals, then the defined ones.
+    GlobalIndex
+);
+index_type!(
+    /// An index into the locals of a function, counting its parameters first.
+    LocalIndex
+);
+
+/// Converts a value type encoded as a varuint, handing back the value if it is not a known
+/// value type. Values above `0xff` are rejected rather than truncated to their low byte.
+impl TryFrom<VarUInt> for WasmValueType {
+    type Error = u32;
+
+    fn try_from(value: VarUInt) -> Result<Self, Self::Error> {
         let unsigned: u32 = value.into();
-        WasmValueType::from(unsigned as u8)
+        let byte = u8::try_from(unsigned).map_err(|_| unsigned)?;
+
+        WasmValueType::try_from(byte).map_err(u32::from)
+    }
+}
+
+/// The one-byte id that precedes every section in a WebAssembly binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SectionType {
+    Custom,
+    Type,
+    Import,
+    Function,
+    Table,
+    Memory,
+    Global,
+    Export,
+    Start,
+    Element,
+    Code,
+    Data,
+    DataCount,
+    Tag,
+    /// An id past those known, e.g. of a section from a newer proposal. Its contents are kept
+    /// as they are, like those of a custom section; see `DecoderConfig::reject_unknown_sections`.
+    Unknown(u8),
+}
+
+/// Converts a section id, handing back the id itself if it is not a known section.
+impl TryFrom<u8> for SectionType {
+    type Error = u8;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        use SectionType::*;
+
+        let section_type = match id {
+            0 => Custom,
+            1 => Type,
+            2 => Import,
+            3 => Function,
+            4 => Table,
+            5 => Memory,
+            6 => Global,
+            7 => Export,
+            8 => Start,
+            9 => Element,
+            10 => Code,
+            11 => Data,
+            12 => DataCount,
+            13 => Tag,
+            _ => return Err(id),
+        };
+
+        Ok(section_type)
+    }
+}
+
+/// The four bytes after the magic: a 16-bit version, then a 16-bit layer that tells core
+/// modules (layer 0) from component-model binaries (layer 1).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WasmVersion {
+    pub(crate) version: u16,
+    pub(crate) layer: u16,
+}
+
+impl WasmVersion {
+    pub fn version(self) -> u16 {
+        self.version
+    }
+
+    pub fn layer(self) -> u16 {
+        self.layer
+    }
+
+    pub fn kind(self) -> ModuleKind {
+        match self.layer {
+            0 => ModuleKind::Core,
+            1 => ModuleKind::Component,
+            layer => ModuleKind::Unknown(layer),
+        }
+    }
+}
+
+/// Splits the little-endian version field into its version and layer.
+impl From<u32> for WasmVersion {
+    fn from(value: u32) -> Self {
+        WasmVersion {
+            version: value as u16,
+            layer: (value >> 16) as u16,
+        }
+    }
+}
+
+impl From<WasmVersion> for u32 {
+    fn from(value: WasmVersion) -> u32 {
+        (value.layer as u32) << 16 | value.version as u32
     }
 }
 
-pub(crate) enum WasmSection {
+/// What kind of binary the layer of a `WasmVersion` announces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModuleKind {
+    Core,
+    Component,
+    Unknown(u16),
+}
+
+/// Where a section lives in the original buffer: its contents span
+/// `offset..offset + length`, right after the id byte and size varuint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionInfo {
+    pub(crate) section_type: SectionType,
+    pub(crate) offset: u64,
+    pub(crate) length: u32,
+}
+
+impl SectionInfo {
+    pub fn section_type(&self) -> SectionType {
+        self.section_type
+    }
+
+    /// The offset of the section contents in the original buffer.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The length of the section contents, in bytes.
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+}
+
+impl SectionType {
+    /// The id byte of the section.
+    pub fn id(self) -> u8 {
+        use SectionType::*;
+
+        match self {
+            Custom => 0,
+            Type => 1,
+            Import => 2,
+            Function => 3,
+            Table => 4,
+            Memory => 5,
+            Global => 6,
+            Export => 7,
+            Start => 8,
+            Element => 9,
+            Code => 10,
+            Data => 11,
+            DataCount => 12,
+            Tag => 13,
+            Unknown(id) => id,
+        }
+    }
+
+    /// The name of the section as used in the specification, e.g. "data count".
+    pub fn name(self) -> &'static str {
+        use SectionType::*;
+
+        match self {
+            Custom => "custom",
+            Type => "type",
+            Import => "import",
+            Function => "function",
+            Table => "table",
+            Memory => "memory",
+            Global => "global",
+            Export => "export",
+            Start => "start",
+            Element => "element",
+            Code => "code",
+            Data => "data",
+            DataCount => "data count",
+            Tag => "tag",
+            Unknown(_) => "unknown",
+        }
+    }
+
+    /// The position of a non-custom section in a well-formed module. Sections must appear in
+    /// increasing order, which differs from the id order for the later additions (tag, data count).
+    /// Unknown sections may appear anywhere, like custom ones.
+    pub(crate) fn canonical_order(self) -> u8 {
+        use SectionType::*;
+
+        match self {
+            Custom | Unknown(_) => 0,
+            Type => 1,
+            Import => 2,
+            Function => 3,
+            Table => 4,
+            Memory => 5,
+            Tag => 6,
+            Global => 7,
+            Export => 8,
+            Start => 9,
+            Element => 10,
+            DataCount => 11,
+            Code => 12,
+            Data => 13,
+        }
+    }
+}
+
+/// A decoded section, as passed to the callback of `decode_with`. Sections whose contents are
+/// not decoded yet hold `()`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum WasmSection<'a> {
     Type(TypeSection),
-    Custom(()),
-    Import(ImportSection),
-    Table(()),
-    Memory(()),
-    Global(()),
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Custom(CustomSection<'a>),
+    Import(ImportSection<'a>),
+    Function(FunctionSection),
+    Table(TableSection),
+    Memory(MemorySection),
+    Global(GlobalSection),
+    Export(ExportSection<'a>),
     Start(()),
-    Element(()),
+    Element(ElementSection),
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Code(CodeSection<'a>),
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Data(DataSection<'a>),
+    DataCount(()),
+    Tag(TagSection),
+    /// A section with an id past those known, undecoded.
+    Unknown {
+        id: u8,
+        data: &'a [u8],
+    },
 }
 
+/// `UNSUPPORTED` compares equal to itself, so two unsupported types are indistinguishable; the
+/// decoder never produces it, and rejects unknown value types instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WasmValueType {
     I32,
     I64,
     F32,
     F64,
+    V128,
+    /// A reference to a function, from the reference types proposal.
+    Funcref,
+    /// A reference to a host value, from the reference types proposal.
+    Externref,
+    /// A typed reference from the function references and GC proposals, such as
+    /// `(ref null $t)`. Only decoded with `DecoderConfig::allow_gc`.
+    Ref(RefType),
     UNSUPPORTED,
 }
 
-impl From<u8> for WasmValueType {
-    fn from(x: u8) -> Self {
+/// A reference type written with the `0x63` (`ref null`) or `0x64` (`ref`) prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefType {
+    pub(crate) nullable: bool,
+    pub(crate) heap_type: HeapType,
+}
+
+impl RefType {
+    pub fn new(nullable: bool, heap_type: HeapType) -> Self {
+        RefType {
+            nullable,
+            heap_type,
+        }
+    }
+
+    /// Whether the type includes the null reference, i.e. was written `ref null`.
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    pub fn heap_type(&self) -> HeapType {
+        self.heap_type
+    }
+}
+
+/// Formats as in the text format, e.g. `(ref null 0)` or `(ref func)`.
+impl fmt::Display for RefType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let null = if self.nullable { "null " } else { "" };
+        write!(f, "(ref {null}{})", self.heap_type)
+    }
+}
+
+/// What a typed reference points to: one of the abstract heap types, or the type at an index
+/// of the type section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeapType {
+    Func,
+    Extern,
+    Any,
+    Eq,
+    I31,
+    Struct,
+    Array,
+    None,
+    NoExtern,
+    NoFunc,
+    Exn,
+    NoExn,
+    Concrete(u32),
+}
+
+impl HeapType {
+    /// The abstract heap type encoded as `byte`, if there is one.
+    pub(crate) fn from_abstract_byte(byte: u8) -> Option<Self> {
+        use HeapType::*;
+
+        Some(match byte {
+            0x70 => Func,
+            0x6f => Extern,
+            0x6e => Any,
+            0x6d => Eq,
+            0x6c => I31,
+            0x6b => Struct,
+            0x6a => Array,
+            0x71 => None,
+            0x72 => NoExtern,
+            0x73 => NoFunc,
+            0x69 => Exn,
+            0x74 => NoExn,
+            _ => return Option::None,
+        })
+    }
+}
+
+impl fmt::Display for HeapType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use HeapType::*;
+
+        f.write_str(match self {
+            Func => "func",
+            Extern => "extern",
+            Any => "any",
+            Eq => "eq",
+            I31 => "i31",
+            Struct => "struct",
+            Array => "array",
+            None => "none",
+            NoExtern => "noextern",
+            NoFunc => "nofunc",
+            Exn => "exn",
+            NoExn => "noexn",
+            Concrete(index) => return write!(f, "{index}"),
+        })
+    }
+}
+
+/// Converts a value type byte, handing back the byte itself if it is not a known value type.
+impl TryFrom<u8> for WasmValueType {
+    type Error = u8;
+
+    fn try_from(x: u8) -> Result<Self, Self::Error> {
         use WasmValueType::*;
 
         match x {
-            0x7f => I32,
-            0x7e => I64,
-            0x7d => F32,
-            0x7c => F64,
-            _ => unreachable!("Unknown WashValueType: {:0x}", x),
+            0x7f => Ok(I32),
+            0x7e => Ok(I64),
+            0x7d => Ok(F32),
+            0x7c => Ok(F64),
+            0x7b => Ok(V128),
+            0x70 => Ok(Funcref),
+            0x6f => Ok(Externref),
+            _ => Err(x),
         }
     }
 }
 
+/// Formats as in the text format, e.g. `i32`.
+impl fmt::Display for WasmValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WasmValueType::I32 => "i32",
+            WasmValueType::I64 => "i64",
+            WasmValueType::F32 => "f32",
+            WasmValueType::F64 => "f64",
+            WasmValueType::V128 => "v128",
+            WasmValueType::Funcref => "funcref",
+            WasmValueType::Externref => "externref",
+            WasmValueType::Ref(ref_type) => return write!(f, "{ref_type}"),
+            WasmValueType::UNSUPPORTED => "unsupported",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeSection {
     pub(crate) items: Vec<WasmFunctionType>,
 }
 
-#[derive(Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WasmFunctionType {
     pub(crate) params: Vec<WasmValueType>,
     pub(crate) returns: Vec<WasmValueType>,
 }
 
-pub(crate) enum WasmImportDescriptor {
-    Function(VarUInt), // Index into the function types in the type section
+impl WasmFunctionType {
+    pub fn new(params: Vec<WasmValueType>, returns: Vec<WasmValueType>) -> Self {
+        WasmFunctionType { params, returns }
+    }
+
+    pub fn params(&self) -> &[WasmValueType] {
+        &self.params
+    }
+
+    /// The result types; more than one with the multi-value proposal.
+    pub fn returns(&self) -> &[WasmValueType] {
+        &self.returns
+    }
+
+    /// The parameter types with their positions, which are also their local indices:
+    ///
+    /// ```
+    /// use wasm_thing::types::{WasmFunctionType, WasmValueType};
+    ///
+    /// // (i32 f64) -> ()
+    /// let function_type =
+    ///     WasmFunctionType::new(vec![WasmValueType::I32, WasmValueType::F64], vec![]);
+    ///
+    /// let params: Vec<_> = function_type.params_indexed().collect();
+    /// assert_eq!(params, [(0, &WasmValueType::I32), (1, &WasmValueType::F64)]);
+    /// ```
+    pub fn params_indexed(&self) -> impl Iterator<Item = (usize, &WasmValueType)> {
+        self.params.iter().enumerate()
+    }
+
+    /// The result types with their positions; see `params_indexed`.
+    pub fn returns_indexed(&self) -> impl Iterator<Item = (usize, &WasmValueType)> {
+        self.returns.iter().enumerate()
+    }
+
+    /// Like the `Display` form, but with the results always parenthesized, e.g.
+    /// `(i32, i64) -> (f32)`. `parse` reads both.
+    pub fn to_signature_string(&self) -> String {
+        format!("({}) -> ({})", join(&self.params), join(&self.returns))
+    }
+
+    /// Parse a signature such as `(i32, i64) -> (f32)`, `(f64) -> i32` or `() -> ()`.
+    /// Whitespace between the parts is ignored. Only the number types, `v128`, `funcref` and
+    /// `externref` have names; typed references cannot be parsed.
+    ///
+    /// ```
+    /// use wasm_thing::types::{WasmFunctionType, WasmValueType};
+    ///
+    /// let function_type = WasmFunctionType::parse("(i32,i64) -> (f32, f32)").unwrap();
+    /// assert_eq!(function_type.params(), [WasmValueType::I32, WasmValueType::I64]);
+    /// assert_eq!(function_type.to_signature_string(), "(i32, i64) -> (f32, f32)");
+    ///
+    /// let error = WasmFunctionType::parse("(i32, u8) -> ()").unwrap_err();
+    /// assert_eq!(error.to_string(), "Unknown value type `u8` at position 6.");
+    /// ```
+    pub fn parse(signature: &str) -> Result<Self, SignatureParseError> {
+        let mut parser = SignatureParser {
+            input: signature,
+            position: 0,
+        };
+
+        let params = parser.value_type_list()?;
+        parser.expect("->", "`->`")?;
+        parser.skip_whitespace();
+        let returns = if parser.rest().starts_with('(') {
+            parser.value_type_list()?
+        } else {
+            vec![parser.value_type()?]
+        };
+
+        parser.skip_whitespace();
+        if !parser.rest().is_empty() {
+            return Err(SignatureParseError::Expected {
+                expected: "the end of the signature",
+                position: parser.position,
+            });
+        }
+
+        Ok(WasmFunctionType { params, returns })
+    }
+}
+
+impl std::str::FromStr for WasmFunctionType {
+    type Err = SignatureParseError;
+
+    fn from_str(signature: &str) -> Result<Self, Self::Err> {
+        Self::parse(signature)
+    }
+}
+
+/// Why `WasmFunctionType::parse` rejected a signature. `position` is a byte offset into it.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignatureParseError {
+    #[error("Expected {expected} at position {position}.")]
+    Expected {
+        expected: &'static str,
+        position: usize,
+    },
+
+    #[error("Unknown value type `{name}` at position {position}.")]
+    UnknownValueType { name: String, position: usize },
+}
+
+struct SignatureParser<'s> {
+    input: &'s str,
+    position: usize,
+}
+
+impl SignatureParser<'_> {
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skip whitespace, then `token`, which is called `expected` in the error if it is not there.
+    fn expect(&mut self, token: &str, expected: &'static str) -> Result<(), SignatureParseError> {
+        self.skip_whitespace();
+        if !self.rest().starts_with(token) {
+            return Err(SignatureParseError::Expected {
+                expected,
+                position: self.position,
+            });
+        }
+        self.position += token.len();
+        Ok(())
+    }
+
+    /// A parenthesized, comma-separated list of value types, which may be empty.
+    fn value_type_list(&mut self) -> Result<Vec<WasmValueType>, SignatureParseError> {
+        self.expect("(", "`(`")?;
+        self.skip_whitespace();
+        if self.rest().starts_with(')') {
+            self.position += 1;
+            return Ok(Vec::new());
+        }
+
+        let mut value_types = vec![self.value_type()?];
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with(',') {
+                self.position += 1;
+                value_types.push(self.value_type()?);
+            } else {
+                self.expect(")", "`,` or `)`")?;
+                return Ok(value_types);
+            }
+        }
+    }
+
+    fn value_type(&mut self) -> Result<WasmValueType, SignatureParseError> {
+        self.skip_whitespace();
+        let position = self.position;
+        let rest = self.rest();
+        let name = &rest[..rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len())];
+
+        let value_type = match name {
+            "i32" => WasmValueType::I32,
+            "i64" => WasmValueType::I64,
+            "f32" => WasmValueType::F32,
+            "f64" => WasmValueType::F64,
+            "v128" => WasmValueType::V128,
+            "funcref" => WasmValueType::Funcref,
+            "externref" => WasmValueType::Externref,
+            "" => {
+                return Err(SignatureParseError::Expected {
+                    expected: "a value type",
+                    position,
+                })
+            }
+            name => {
+                return Err(SignatureParseError::UnknownValueType {
+                    name: name.to_string(),
+                    position,
+                })
+            }
+        };
+
+        self.position += name.len();
+        Ok(value_type)
+    }
+}
+
+/// Formats as `(i32, i32) -> i64`. The results are only parenthesized if there is not exactly
+/// one, so `() -> ()` has neither parameters nor results, and `(f32) -> (i32, i32)` has two.
+impl fmt::Display for WasmFunctionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}) -> ", join(&self.params))?;
+        match &self.returns[..] {
+            [result] => write!(f, "{result}"),
+            results => write!(f, "({})", join(results)),
+        }
+    }
+}
+
+fn join(value_types: &[WasmValueType]) -> String {
+    value_types
+        .iter()
+        .map(WasmValueType::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The kind of an import or export, as given by the byte before its type or index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExternalKind {
+    Function = 0,
+    Table = 1,
+    Memory = 2,
+    Global = 3,
+}
+
+/// The kind of an export. Exports use the same kinds, and kind bytes, as imports.
+pub type ExportKind = ExternalKind;
+
+/// Converts a kind byte, handing back the byte itself if it is not a known kind.
+impl TryFrom<u8> for ExternalKind {
+    type Error = u8;
+
+    fn try_from(kind: u8) -> Result<Self, Self::Error> {
+        use ExternalKind::*;
+
+        match kind {
+            0 => Ok(Function),
+            1 => Ok(Table),
+            2 => Ok(Memory),
+            3 => Ok(Global),
+            _ => Err(kind),
+        }
+    }
+}
+
+/// What an import brings in: a function of the signature at an index of the type section, or
+/// a table, memory or global of the given type.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum WasmImportDescriptor {
+    Function(TypeIndex),
     Table(TableType),
     Memory(MemoryType),
     Global(GlobalType),
 }
 
+impl WasmImportDescriptor {
+    pub fn kind(&self) -> ExternalKind {
+        match self {
+            WasmImportDescriptor::Function(_) => ExternalKind::Function,
+            WasmImportDescriptor::Table(_) => ExternalKind::Table,
+            WasmImportDescriptor::Memory(_) => ExternalKind::Memory,
+            WasmImportDescriptor::Global(_) => ExternalKind::Global,
+        }
+    }
+}
+
 /// WebAssembly Table Type
 /// This type is defined by its element type (valtype) and a limits descriptor.
-pub(crate) struct TableType {
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableType {
     pub(crate) element_type: WasmElementType,
     pub(crate) limits: WasmLimits,
+    pub(crate) shared: bool,
+    pub(crate) table64: bool,
+}
+
+impl TableType {
+    /// An unshared table with 32-bit indices.
+    pub fn new(element_type: WasmElementType, limits: WasmLimits) -> Self {
+        TableType {
+            element_type,
+            limits,
+            shared: false,
+            table64: false,
+        }
+    }
+
+    /// Whether the table may be shared between threads; see
+    /// `DecoderConfig::allow_shared_tables`.
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Whether the table is indexed with `i64` rather than `i32`; see
+    /// `DecoderConfig::allow_table64`.
+    pub fn is_table64(&self) -> bool {
+        self.table64
+    }
+
+    pub fn element_type(&self) -> WasmElementType {
+        self.element_type
+    }
+
+    /// The initial and maximum number of elements.
+    pub fn limits(&self) -> &WasmLimits {
+        &self.limits
+    }
 }
 
 /// WebAssembly Memory Type
 /// This type is defined by a limits descriptor.
-pub(crate) struct MemoryType {
+///
+/// ```
+/// // (module (import "env" "memory" (memory 1 16)))
+/// let bytes = [
+///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x02, 0x10, 0x01, 0x03, 0x65, 0x6e,
+///     0x76, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x01, 0x01, 0x10,
+/// ];
+///
+/// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+/// let memory = module.imported_memories().next().unwrap();
+///
+/// assert_eq!(memory.limits().min(), 1);
+/// assert_eq!(memory.limits().max(), Some(16));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryType {
     pub(crate) limits: WasmLimits,
+    pub(crate) shared: bool,
+    pub(crate) memory64: bool,
+}
+
+impl MemoryType {
+    /// An unshared memory with 32-bit addresses.
+    pub fn new(limits: WasmLimits) -> Self {
+        MemoryType {
+            limits,
+            shared: false,
+            memory64: false,
+        }
+    }
+
+    /// Whether the memory may be shared between threads; see
+    /// `DecoderConfig::allow_shared_memories`.
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Whether the memory is addressed with `i64` rather than `i32`; see
+    /// `DecoderConfig::allow_memory64`.
+    pub fn is_memory64(&self) -> bool {
+        self.memory64
+    }
+
+    /// The initial and maximum size in 64 KiB pages.
+    pub fn limits(&self) -> &WasmLimits {
+        &self.limits
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableSection {
+    pub(crate) items: Vec<TableType>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemorySection {
+    pub(crate) items: Vec<MemoryType>,
 }
 
 /// WebAssembly Global Type
 /// This type is defined by its value type (valtype) and a mutability flag.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalType {
     pub(crate) value_type: WasmValueType,
     pub(crate) mutability: Mutability,
 }
 
+impl GlobalType {
+    pub fn new(value_type: WasmValueType, mutability: Mutability) -> Self {
+        GlobalType {
+            value_type,
+            mutability,
+        }
+    }
+
+    pub fn value_type(&self) -> WasmValueType {
+        self.value_type
+    }
+
+    pub fn mutability(&self) -> Mutability {
+        self.mutability
+    }
+
+    /// Whether the global is declared `mut`, i.e. can be changed with `global.set`.
+    pub fn is_mutable(&self) -> bool {
+        self.mutability == Mutability::Mutable
+    }
+}
+
+/// A constant expression, such as the initializer of a global: a single instruction that
+/// produces a value, followed by `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConstExpr {
+    I32Const(i32),
+    I64Const(i64),
+    F32Const(f32),
+    F64Const(f64),
+    /// The value of an imported global.
+    GlobalGet(GlobalIndex),
+    RefNull(WasmElementType),
+    RefFunc(FuncIndex),
+}
+
+/// A value of one of the number or reference types, such as the result of evaluating a
+/// `ConstExpr`; see `WasmModule::global_initial_value`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WasmValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Ref(WasmRef),
+}
+
+/// A reference value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WasmRef {
+    /// The null reference of a reference type.
+    Null(WasmElementType),
+    /// A reference to the function at this index of the function index space.
+    Func(FuncIndex),
+}
+
+/// A global defined in the global section, with the value it starts out with.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Global {
+    pub(crate) global_type: GlobalType,
+    pub(crate) init: ConstExpr,
+}
+
+impl Global {
+    pub fn global_type(&self) -> &GlobalType {
+        &self.global_type
+    }
+
+    pub fn init(&self) -> ConstExpr {
+        self.init
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlobalSection {
+    pub(crate) items: Vec<Global>,
+}
+
+/// Whether, and where, an element segment is copied into a table when the module is
+/// instantiated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementMode {
+    /// Copied into table `table_index`, starting at the index `offset` evaluates to.
+    Active {
+        table_index: TableIndex,
+        offset: ConstExpr,
+    },
+    /// Only copied by `table.init`.
+    Passive,
+    /// Never copied; only declares the functions that `ref.func` may refer to.
+    Declarative,
+}
+
+/// The references an element segment holds.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementItems {
+    /// Indices into the function index space.
+    Functions(Vec<FuncIndex>),
+    /// Constant expressions evaluating to references, such as `ref.func` or `ref.null`.
+    Expressions(Vec<ConstExpr>),
+}
+
+impl ElementItems {
+    pub fn len(&self) -> usize {
+        match self {
+            ElementItems::Functions(indices) => indices.len(),
+            ElementItems::Expressions(exprs) => exprs.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A segment of the element section: references to initialize a table with.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementSegment {
+    pub(crate) mode: ElementMode,
+    pub(crate) element_type: WasmElementType,
+    pub(crate) items: ElementItems,
+}
+
+impl ElementSegment {
+    pub fn mode(&self) -> ElementMode {
+        self.mode
+    }
+
+    pub fn element_type(&self) -> WasmElementType {
+        self.element_type
+    }
+
+    pub fn items(&self) -> &ElementItems {
+        &self.items
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementSection {
+    pub(crate) items: Vec<ElementSegment>,
+}
+
+/// A custom section, which holds a name and data that this crate does not interpret, e.g.
+/// debug information.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomSection<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) name: Cow<'a, str>,
+    #[cfg_attr(feature = "serde", serde(borrow, with = "serde_bytes"))]
+    pub(crate) data: Cow<'a, [u8]>,
+}
+
+impl<'a> CustomSection<'a> {
+    pub fn new(name: impl Into<Cow<'a, str>>, data: impl Into<Cow<'a, [u8]>>) -> Self {
+        CustomSection {
+            name: name.into(),
+            data: data.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name as borrowed from the decoded buffer, so it can outlive the section; `None` if
+    /// the name is owned, e.g. after `into_owned`.
+    pub fn borrowed_name(&self) -> Option<&'a str> {
+        match self.name {
+            Cow::Borrowed(name) => Some(name),
+            Cow::Owned(_) => None,
+        }
+    }
+
+    /// The contents after the name.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_owned(self) -> CustomSection<'static> {
+        CustomSection {
+            name: Cow::Owned(self.name.into_owned()),
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
+}
+
+/// The `linking` custom section of a relocatable object file, such as LLVM emits for `wasm-ld`;
+/// see `WasmModule::linking_section`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkingSection<'a> {
+    pub(crate) version: u32,
+    pub(crate) subsections: Vec<LinkingSubsection<'a>>,
+}
+
+impl LinkingSection<'_> {
+    /// The version of the linking metadata, 2 at the time of writing.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn subsections(&self) -> &[LinkingSubsection<'_>] {
+        &self.subsections
+    }
+
+    /// The entries of the symbol table subsection, or none if there is no such subsection.
+    pub fn symbols(&self) -> &[Symbol<'_>] {
+        self.subsections
+            .iter()
+            .find_map(|subsection| match subsection {
+                LinkingSubsection::SymbolTable(symbols) => Some(&symbols[..]),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A subsection of the `linking` section.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkingSubsection<'a> {
+    /// `WASM_SEGMENT_INFO`: the name, alignment and flags of every data segment.
+    SegmentInfo(Vec<SegmentInfo<'a>>),
+    /// `WASM_SYMBOL_TABLE`.
+    SymbolTable(Vec<Symbol<'a>>),
+    /// Any other subsection, such as `WASM_INIT_FUNCS` or `WASM_COMDAT_INFO`, whose contents
+    /// are not decoded.
+    Other { id: u8, data: &'a [u8] },
+}
+
+/// The metadata of a data segment, from the segment info subsection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentInfo<'a> {
+    pub(crate) name: Cow<'a, str>,
+    pub(crate) alignment: u32,
+    pub(crate) flags: u32,
+}
+
+impl SegmentInfo<'_> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The alignment of the segment, as a power of two.
+    pub fn alignment(&self) -> u32 {
+        self.alignment
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+}
+
+/// An entry of the symbol table subsection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol<'a> {
+    pub(crate) kind: SymbolKind,
+    pub(crate) flags: u32,
+    pub(crate) name: Option<Cow<'a, str>>,
+}
+
+impl Symbol<'_> {
+    /// `WASM_SYM_UNDEFINED`: the symbol is imported, rather than defined by the object file.
+    pub const UNDEFINED: u32 = 0x10;
+    /// `WASM_SYM_EXPLICIT_NAME`: an undefined symbol has a name of its own, rather than that of
+    /// its import.
+    pub const EXPLICIT_NAME: u32 = 0x40;
+
+    pub fn kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    /// The `WASM_SYM_*` flags, such as `UNDEFINED`.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        self.flags & Self::UNDEFINED != 0
+    }
+
+    /// `None` for section symbols, and undefined symbols named after their import.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// What a symbol refers to, by index into the index space of its kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function(u32),
+    /// `None` for an undefined data symbol, which has no segment.
+    Data(Option<DataSymbol>),
+    Global(u32),
+    /// A custom section, by its index among all sections.
+    Section(u32),
+    Tag(u32),
+    Table(u32),
+}
+
+/// Where a defined data symbol is: `size` bytes at `offset` into data segment `segment`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataSymbol {
+    pub(crate) segment: u32,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+}
+
+impl DataSymbol {
+    /// The index of the data segment the symbol is in.
+    pub fn segment(&self) -> u32 {
+        self.segment
+    }
+
+    /// The offset of the symbol into its segment.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Whether, and where, a data segment is copied into memory when the module is instantiated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataMode {
+    /// Copied into memory `memory_index`, starting at the address `offset` evaluates to.
+    Active {
+        memory_index: MemoryIndex,
+        offset: ConstExpr,
+    },
+    /// Only copied by `memory.init`.
+    Passive,
+}
+
+/// A segment of the data section: bytes to initialize memory with.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataSegment<'a> {
+    pub(crate) mode: DataMode,
+    #[cfg_attr(feature = "serde", serde(borrow, with = "serde_bytes"))]
+    pub(crate) data: Cow<'a, [u8]>,
+}
+
+impl DataSegment<'_> {
+    pub fn mode(&self) -> DataMode {
+        self.mode
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub(crate) fn into_owned(self) -> DataSegment<'static> {
+        DataSegment {
+            mode: self.mode,
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataSection<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) items: Vec<DataSegment<'a>>,
+}
+
+/// Formats as in the text format: `i32` for an immutable global, `(mut i32)` for a mutable one.
+impl fmt::Display for GlobalType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mutability {
+            Mutability::Immutable => write!(f, "{}", self.value_type),
+            Mutability::Mutable => write!(f, "(mut {})", self.value_type),
+        }
+    }
+}
+
 /// WebAssembly Element Type
 /// This is essentially the value type, restricted to funcref and externref.
-pub(crate) enum WasmElementType {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum WasmElementType {
     Funcref,
     Externref,
-    // Additional types may be added in future WebAssembly extensions
 }
 
 /// WebAssembly Limits
 /// This is defined by a minimum and an optional maximum.
-pub(crate) struct WasmLimits {
-    pub(crate) min: u32,
-    pub(crate) max: Option<u32>,
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WasmLimits {
+    pub(crate) min: u64,
+    pub(crate) max: Option<u64>,
+}
+
+impl WasmLimits {
+    pub fn new(min: u64, max: Option<u64>) -> Self {
+        WasmLimits { min, max }
+    }
+
+    /// The minimum; only the limits of a 64-bit table can exceed `u32::MAX`.
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    /// The maximum, if the limits declare one.
+    pub fn max(&self) -> Option<u64> {
+        self.max
+    }
+
+    /// Whether an item with these limits can be used where `other` limits are expected, as
+    /// when it satisfies an import: it is at least as large to begin with, and if `other` caps
+    /// the size, so do these, no higher. Limits without a maximum are only a subtype of others
+    /// without one.
+    pub fn is_subtype_of(&self, other: &WasmLimits) -> bool {
+        self.min >= other.min
+            && match (self.max, other.max) {
+                (_, None) => true,
+                (None, Some(_)) => false,
+                (Some(max), Some(other_max)) => max <= other_max,
+            }
+    }
+
+    /// The limits that both these and `other` allow: the larger minimum and the smaller
+    /// maximum. `None` if no size is within both.
+    pub fn intersect(&self, other: &WasmLimits) -> Option<WasmLimits> {
+        let min = self.min.max(other.min);
+        let max = match (self.max, other.max) {
+            (Some(max), Some(other_max)) => Some(max.min(other_max)),
+            (max, None) | (None, max) => max,
+        };
+        match max {
+            Some(max) if max < min => None,
+            max => Some(WasmLimits { min, max }),
+        }
+    }
+
+    /// Whether `size` is within the limits.
+    pub fn contains(&self, size: u64) -> bool {
+        size >= self.min && self.max.is_none_or(|max| size <= max)
+    }
+}
+
+/// Formats as `min=1, max=16`, or `min=1` without a maximum.
+impl fmt::Display for WasmLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "min={}", self.min)?;
+        if let Some(max) = self.max {
+            write!(f, ", max={max}")?;
+        }
+        Ok(())
+    }
 }
 
-pub(crate) struct WasmImportEntry {
-    pub(crate) module_name: String,
-    pub(crate) field_name: String,
+/// Names borrow from the decoded buffer where possible; see `WasmModule::into_owned`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WasmImportEntry<'a> {
+    pub(crate) module_name: Cow<'a, str>,
+    pub(crate) field_name: Cow<'a, str>,
     pub(crate) descriptor: WasmImportDescriptor,
 }
 
-pub(crate) struct ImportSection {
-    pub(crate) items: Vec<WasmImportEntry>,
+impl<'a> WasmImportEntry<'a> {
+    pub fn new(
+        module_name: impl Into<Cow<'a, str>>,
+        field_name: impl Into<Cow<'a, str>>,
+        descriptor: WasmImportDescriptor,
+    ) -> Self {
+        WasmImportEntry {
+            module_name: module_name.into(),
+            field_name: field_name.into(),
+            descriptor,
+        }
+    }
+
+    pub fn module_name(&self) -> &str {
+        &self.module_name
+    }
+
+    pub fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    /// What kind of item is imported, and its type.
+    pub fn descriptor(&self) -> &WasmImportDescriptor {
+        &self.descriptor
+    }
+
+    pub(crate) fn into_owned(self) -> WasmImportEntry<'static> {
+        WasmImportEntry {
+            module_name: Cow::Owned(self.module_name.into_owned()),
+            field_name: Cow::Owned(self.field_name.into_owned()),
+            descriptor: self.descriptor,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportSection<'a> {
+    pub(crate) items: Vec<WasmImportEntry<'a>>,
+}
+
+/// The function section declares the signature of every function defined in the module.
+/// Each item is an index into the type section; the bodies live in the code section.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionSection {
+    pub(crate) items: Vec<TypeIndex>,
+}
+
+/// What an export exposes: an index into the function, table, memory or global index space,
+/// which counts imports first.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum WasmExportDescriptor {
+    Function(FuncIndex),
+    Table(TableIndex),
+    Memory(MemoryIndex),
+    Global(GlobalIndex),
+}
+
+macro_rules! export_descriptor_from {
+    ($index:ident, $variant:ident) => {
+        impl From<$index> for WasmExportDescriptor {
+            fn from(index: $index) -> Self {
+                WasmExportDescriptor::$variant(index)
+            }
+        }
+    };
+}
+
+export_descriptor_from!(FuncIndex, Function);
+export_descriptor_from!(TableIndex, Table);
+export_descriptor_from!(MemoryIndex, Memory);
+export_descriptor_from!(GlobalIndex, Global);
+
+impl WasmExportDescriptor {
+    pub fn kind(&self) -> ExternalKind {
+        match self {
+            WasmExportDescriptor::Function(_) => ExternalKind::Function,
+            WasmExportDescriptor::Table(_) => ExternalKind::Table,
+            WasmExportDescriptor::Memory(_) => ExternalKind::Memory,
+            WasmExportDescriptor::Global(_) => ExternalKind::Global,
+        }
+    }
+
+    /// The index of the exported item in the index space of its kind.
+    pub fn index(&self) -> u32 {
+        match self {
+            WasmExportDescriptor::Function(index) => index.0,
+            WasmExportDescriptor::Table(index) => index.0,
+            WasmExportDescriptor::Memory(index) => index.0,
+            WasmExportDescriptor::Global(index) => index.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WasmExportEntry<'a> {
+    pub(crate) name: Cow<'a, str>,
+    pub(crate) descriptor: WasmExportDescriptor,
+}
+
+impl<'a> WasmExportEntry<'a> {
+    pub fn new(name: impl Into<Cow<'a, str>>, descriptor: WasmExportDescriptor) -> Self {
+        WasmExportEntry {
+            name: name.into(),
+            descriptor,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What kind of item is exported, and its index.
+    pub fn descriptor(&self) -> &WasmExportDescriptor {
+        &self.descriptor
+    }
+
+    pub(crate) fn into_owned(self) -> WasmExportEntry<'static> {
+        WasmExportEntry {
+            name: Cow::Owned(self.name.into_owned()),
+            descriptor: self.descriptor,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExportSection<'a> {
+    pub(crate) items: Vec<WasmExportEntry<'a>>,
+}
+
+impl<'a> ExportSection<'a> {
+    /// The exports of functions, in order.
+    pub fn functions(&self) -> impl Iterator<Item = &WasmExportEntry<'a>> {
+        self.of_kind(ExternalKind::Function)
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &WasmExportEntry<'a>> {
+        self.of_kind(ExternalKind::Table)
+    }
+
+    pub fn memories(&self) -> impl Iterator<Item = &WasmExportEntry<'a>> {
+        self.of_kind(ExternalKind::Memory)
+    }
+
+    pub fn globals(&self) -> impl Iterator<Item = &WasmExportEntry<'a>> {
+        self.of_kind(ExternalKind::Global)
+    }
+
+    /// The kind of the first export called `name`, or `None` if nothing is exported under it.
+    pub fn kind_of(&self, name: &str) -> Option<ExportKind> {
+        self.items
+            .iter()
+            .find(|export| export.name == name)
+            .map(|export| export.descriptor.kind())
+    }
+
+    fn of_kind(&self, kind: ExternalKind) -> impl Iterator<Item = &WasmExportEntry<'a>> {
+        self.items
+            .iter()
+            .filter(move |export| export.descriptor.kind() == kind)
+    }
+}
+
+/// A run of locals of the same type, as declared at the start of a function body.
+/// `(100, i32)` declares 100 locals, so the runs are kept rather than expanded.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WasmLocals {
+    pub(crate) count: u32,
+    pub(crate) value_type: WasmValueType,
+}
+
+/// The body of a defined function: its local declarations, and the expression kept as raw bytes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionBody<'a> {
+    pub(crate) locals: Vec<WasmLocals>,
+    #[cfg_attr(feature = "serde", serde(borrow, with = "serde_bytes"))]
+    pub(crate) expression: Cow<'a, [u8]>,
+}
+
+impl<'a> FunctionBody<'a> {
+    /// A body from its local declarations and encoded instructions, which must include the
+    /// final `end`.
+    pub fn new(locals: Vec<WasmLocals>, expression: impl Into<Cow<'a, [u8]>>) -> Self {
+        FunctionBody {
+            locals,
+            expression: expression.into(),
+        }
+    }
+
+    /// The local declarations, as runs of locals of the same type.
+    pub fn locals(&self) -> &[WasmLocals] {
+        &self.locals
+    }
+
+    /// The encoded instructions of the body, including the final `end`.
+    pub fn expression(&self) -> &[u8] {
+        &self.expression
+    }
+
+    /// Decode the instructions of the body one at a time, as they are needed. Iteration stops
+    /// after the first error; bodies that were decoded as part of a module never fail.
+    ///
+    /// ```
+    /// use wasm_thing::wasm::instructions::Instruction;
+    ///
+    /// let bytes = std::fs::read("tests/fixtures/wasi_hello.wasm").unwrap();
+    /// let module = wasm_thing::decode_bytes(&bytes).unwrap();
+    ///
+    /// let calls = module.code()[0]
+    ///     .instructions()
+    ///     .filter(|instruction| matches!(instruction, Ok(Instruction::Call(_))))
+    ///     .count();
+    /// assert_eq!(calls, 2);
+    /// ```
+    pub fn instructions(&self) -> impl Iterator<Item = Result<Instruction, DecodeError>> + '_ {
+        Instructions::new(&self.expression)
+    }
+
+    /// The number of locals declared by the body, not counting parameters.
+    pub fn total_locals(&self) -> u64 {
+        self.locals.iter().map(|locals| locals.count as u64).sum()
+    }
+
+    pub(crate) fn into_owned(self) -> FunctionBody<'static> {
+        FunctionBody {
+            locals: self.locals,
+            expression: Cow::Owned(self.expression.into_owned()),
+        }
+    }
+}
+
+impl WasmLocals {
+    pub fn new(count: u32, value_type: WasmValueType) -> Self {
+        WasmLocals { count, value_type }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn value_type(&self) -> WasmValueType {
+        self.value_type
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeSection<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) items: Vec<FunctionBody<'a>>,
+}
+
+/// An exception tag from the exception-handling proposal.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tag {
+    /// Always `0x00` (exception) in the current proposal.
+    pub(crate) attribute: u8,
+    /// Index into the type section describing the tag's parameters.
+    pub(crate) type_index: TypeIndex,
+}
+
+impl Tag {
+    pub fn attribute(&self) -> u8 {
+        self.attribute
+    }
+
+    pub fn type_index(&self) -> TypeIndex {
+        self.type_index
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagSection {
+    pub(crate) items: Vec<Tag>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mutability {
     Immutable,
     Mutable,
 }
+
+/// Formats as `const` or `mut`.
+impl fmt::Display for Mutability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mutability::Immutable => "const",
+            Mutability::Mutable => "mut",
+        })
+    }
+}
+
+/// Implement `items` for a section that is a vector of entries.
+macro_rules! section_items {
+    ($section:ident $(<$lifetime:lifetime>)?, $item:ty) => {
+        impl$(<$lifetime>)? $section$(<$lifetime>)? {
+            /// The entries of the section, in order.
+            pub fn items(&self) -> &[$item] {
+                &self.items
+            }
+        }
+    };
+}
+
+section_items!(TypeSection, WasmFunctionType);
+section_items!(ImportSection<'a>, WasmImportEntry<'a>);
+section_items!(FunctionSection, TypeIndex);
+section_items!(TableSection, TableType);
+section_items!(MemorySection, MemoryType);
+section_items!(ElementSection, ElementSegment);
+section_items!(GlobalSection, Global);
+section_items!(ExportSection<'a>, WasmExportEntry<'a>);
+section_items!(CodeSection<'a>, FunctionBody<'a>);
+section_items!(DataSection<'a>, DataSegment<'a>);
+section_items!(TagSection, Tag);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Xorshift;
+
+    fn function_type(params: &[WasmValueType], returns: &[WasmValueType]) -> String {
+        WasmFunctionType {
+            params: params.to_vec(),
+            returns: returns.to_vec(),
+        }
+        .to_string()
+    }
+
+    #[test]
+    fn test_limits_subtyping() {
+        let limits = WasmLimits::new;
+
+        // Every combination of the minimum being smaller, equal or larger, and of either
+        // maximum being absent, smaller, equal or larger.
+        let cases = [
+            (limits(1, None), limits(2, None), false),
+            (limits(2, None), limits(2, None), true),
+            (limits(3, None), limits(2, None), true),
+            (limits(1, None), limits(2, Some(4)), false),
+            (limits(2, None), limits(2, Some(4)), false),
+            (limits(3, None), limits(2, Some(4)), false),
+            (limits(1, Some(4)), limits(2, None), false),
+            (limits(2, Some(4)), limits(2, None), true),
+            (limits(3, Some(4)), limits(2, None), true),
+            (limits(1, Some(3)), limits(2, Some(4)), false),
+            (limits(2, Some(3)), limits(2, Some(4)), true),
+            (limits(3, Some(3)), limits(2, Some(4)), true),
+            (limits(1, Some(4)), limits(2, Some(4)), false),
+            (limits(2, Some(4)), limits(2, Some(4)), true),
+            (limits(3, Some(4)), limits(2, Some(4)), true),
+            (limits(1, Some(5)), limits(2, Some(4)), false),
+            (limits(2, Some(5)), limits(2, Some(4)), false),
+            (limits(3, Some(5)), limits(2, Some(4)), false),
+        ];
+        for (sub, sup, expected) in cases {
+            assert_eq!(sub.is_subtype_of(&sup), expected, "{sub} <: {sup}");
+            // Being a subtype is being contained, so intersecting changes nothing.
+            assert_eq!(
+                sub.intersect(&sup) == Some(sub.clone()),
+                expected,
+                "{sub} & {sup}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_limits_intersect() {
+        let limits = WasmLimits::new;
+
+        assert_eq!(
+            limits(1, None).intersect(&limits(2, None)),
+            Some(limits(2, None))
+        );
+        assert_eq!(
+            limits(1, Some(8)).intersect(&limits(2, None)),
+            Some(limits(2, Some(8)))
+        );
+        assert_eq!(
+            limits(1, None).intersect(&limits(2, Some(8))),
+            Some(limits(2, Some(8)))
+        );
+        assert_eq!(
+            limits(1, Some(8)).intersect(&limits(4, Some(16))),
+            Some(limits(4, Some(8)))
+        );
+        assert_eq!(
+            limits(1, Some(4)).intersect(&limits(4, Some(16))),
+            Some(limits(4, Some(4)))
+        );
+        assert_eq!(limits(1, Some(3)).intersect(&limits(4, Some(16))), None);
+        assert_eq!(limits(5, None).intersect(&limits(1, Some(4))), None);
+    }
+
+    #[test]
+    fn test_limits_contains() {
+        let bounded = WasmLimits::new(2, Some(4));
+        let contained: Vec<_> = (0..6).filter(|&size| bounded.contains(size)).collect();
+        assert_eq!(contained, [2, 3, 4]);
+
+        let unbounded = WasmLimits::new(2, None);
+        assert!(!unbounded.contains(1));
+        assert!(unbounded.contains(2));
+        assert!(unbounded.contains(u64::MAX));
+    }
+
+    #[test]
+    fn test_function_types_as_set_keys() {
+        let signature =
+            || WasmFunctionType::new(vec![WasmValueType::I32], vec![WasmValueType::F64]);
+        let set: std::collections::HashSet<_> = [signature(), signature()].into();
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(&signature()));
+    }
+
+    #[test]
+    fn test_var_uint_arithmetic() {
+        let one = VarUInt::from(1);
+        let max = VarUInt::from(u32::MAX);
+
+        assert!(one < max);
+        assert_eq!(one.max(max), max);
+        assert_eq!(one.to_string(), "1");
+        assert_eq!(one.checked_add(1), Some(VarUInt::from(2)));
+        assert_eq!(max.checked_add(1), None);
+        assert_eq!(one.checked_sub(1), Some(VarUInt::from(0)));
+        assert_eq!(one.checked_sub(2), None);
+
+        let counts: std::collections::BTreeMap<_, _> = [(max, "max"), (one, "one")].into();
+        assert_eq!(counts.keys().copied().collect::<Vec<_>>(), [one, max]);
+    }
+
+    #[test]
+    fn test_index_types() {
+        let index = FuncIndex::from(VarUInt::from(3));
+        assert_eq!(u32::from(index), 3);
+        assert_eq!(VarUInt::from(index), VarUInt::from(3));
+        assert_eq!(index.to_string(), "3");
+        assert!(FuncIndex::from(2) < index);
+
+        assert_eq!(
+            WasmExportDescriptor::from(GlobalIndex::from(1)),
+            WasmExportDescriptor::Global(1.into())
+        );
+        assert_eq!(WasmExportDescriptor::from(TableIndex::from(4)).index(), 4);
+    }
+
+    #[test]
+    fn test_var_uint_to_bytes() {
+        assert_eq!(VarUInt::from(0).to_bytes(), [0x00]);
+        assert_eq!(VarUInt::from(127).to_bytes(), [0x7f]);
+        assert_eq!(VarUInt::from(129).to_bytes(), [0x81, 0x01]);
+        assert_eq!(
+            VarUInt::from(u32::MAX).to_bytes(),
+            [0xff, 0xff, 0xff, 0xff, 0x0f]
+        );
+        // 624485, the example of the LEB128 article on Wikipedia.
+        assert_eq!(VarUInt::from(624485).to_bytes(), [0xe5, 0x8e, 0x26]);
+    }
+
+    #[test]
+    fn test_var_uint_to_usize_checked() {
+        assert_eq!(VarUInt::from(0).to_usize_checked().unwrap(), 0);
+        assert_eq!(VarUInt::from(0xffff).to_usize_checked().unwrap(), 0xffff);
+
+        let large = VarUInt::from(u32::MAX).to_usize_checked();
+        if cfg!(target_pointer_width = "16") {
+            assert!(matches!(
+                large,
+                Err(DecodeError::UsizeOverflow { value: u32::MAX })
+            ));
+        } else {
+            assert_eq!(large.unwrap() as u64, u64::from(u32::MAX));
+        }
+    }
+
+    #[test]
+    fn test_external_kind_from_byte() {
+        use ExternalKind::*;
+
+        for (byte, kind) in [(0, Function), (1, Table), (2, Memory), (3, Global)] {
+            assert_eq!(ExternalKind::try_from(byte), Ok(kind));
+            assert_eq!(kind as u8, byte);
+        }
+        assert_eq!(ExternalKind::try_from(4), Err(4));
+    }
+
+    #[test]
+    fn test_display_function_types() {
+        use WasmValueType::*;
+
+        assert_eq!(function_type(&[], &[]), "() -> ()");
+        assert_eq!(function_type(&[I32, I32], &[I64]), "(i32, i32) -> i64");
+        assert_eq!(function_type(&[F32], &[I32, I32]), "(f32) -> (i32, i32)");
+        assert_eq!(
+            function_type(&[Funcref, V128], &[]),
+            "(funcref, v128) -> ()"
+        );
+    }
+
+    #[test]
+    fn test_parse_signatures() {
+        use WasmValueType::*;
+
+        let parse =
+            |signature| WasmFunctionType::parse(signature).map(|ty| (ty.params, ty.returns));
+        assert_eq!(parse("() -> ()"), Ok((vec![], vec![])));
+        assert_eq!(parse("(i32,i64)->f32"), Ok((vec![I32, I64], vec![F32])));
+        assert_eq!(
+            parse(" ( externref ,\tv128 )\n->  ( f64 , funcref ) "),
+            Ok((vec![Externref, V128], vec![F64, Funcref]))
+        );
+
+        let expected =
+            |expected, position| Err(SignatureParseError::Expected { expected, position });
+        assert_eq!(parse(""), expected("`(`", 0));
+        assert_eq!(parse("(i32 i64) -> ()"), expected("`,` or `)`", 5));
+        assert_eq!(parse("(i32,) -> ()"), expected("a value type", 5));
+        assert_eq!(parse("(i32) ()"), expected("`->`", 6));
+        assert_eq!(parse("(i32) ->"), expected("a value type", 8));
+        assert_eq!(
+            parse("() -> i32 i32"),
+            expected("the end of the signature", 10)
+        );
+        assert_eq!(
+            parse("(i32) -> (I64)"),
+            Err(SignatureParseError::UnknownValueType {
+                name: "I64".into(),
+                position: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_signature_round_trip() {
+        const VALUE_TYPES: [WasmValueType; 7] = [
+            WasmValueType::I32,
+            WasmValueType::I64,
+            WasmValueType::F32,
+            WasmValueType::F64,
+            WasmValueType::V128,
+            WasmValueType::Funcref,
+            WasmValueType::Externref,
+        ];
+        const WHITESPACE: [&str; 4] = ["", " ", "\t", "\n  "];
+
+        let mut rng = Xorshift::new(0x9e37_79b9);
+        let value_types = |rng: &mut Xorshift| {
+            let len = rng.below(5);
+            (0..len)
+                .map(|_| VALUE_TYPES[rng.below(VALUE_TYPES.len())])
+                .collect()
+        };
+
+        for _ in 0..1000 {
+            let function_type = WasmFunctionType {
+                params: value_types(&mut rng),
+                returns: value_types(&mut rng),
+            };
+
+            let signature = function_type.to_signature_string();
+            assert_eq!(
+                WasmFunctionType::parse(&signature),
+                Ok(function_type.clone())
+            );
+            assert_eq!(function_type.to_string().parse(), Ok(function_type.clone()));
+
+            // Any whitespace may go between the parts.
+            let mut spaced = String::new();
+            for part in signature.split_inclusive(['(', ')', ',', ' ']) {
+                spaced.push_str(part.trim_end());
+                spaced.push_str(WHITESPACE[rng.below(WHITESPACE.len())]);
+            }
+            assert_eq!(
+                WasmFunctionType::parse(&spaced),
+                Ok(function_type),
+                "{spaced:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_function_body_instructions() {
+        use crate::wasm::opcodes::Opcode;
+
+        // `call 0`, `call 3`, `drop`, `local.get 0`, `end`.
+        let body = FunctionBody::new(
+            Vec::new(),
+            &[0x10, 0x00, 0x10, 0x03, 0x1a, 0x20, 0x00, 0x0b][..],
+        );
+        let calls = body
+            .instructions()
+            .filter(|instruction| matches!(instruction, Ok(Instruction::Call(_))))
+            .count();
+        assert_eq!(calls, 2);
+
+        let instructions: Vec<_> = body.instructions().map(Result::unwrap).collect();
+        assert_eq!(instructions[1], Instruction::Call(3.into()));
+        assert_eq!(instructions[4], Instruction::Plain(Opcode::End));
+
+        // A truncated `call` is the last item.
+        let body = FunctionBody::new(Vec::new(), &[0x10, 0x00, 0x10][..]);
+        let instructions: Vec<_> = body.instructions().collect();
+        assert_eq!(instructions.len(), 2);
+        assert!(instructions[1].is_err());
+    }
+
+    #[test]
+    fn test_display_limits_and_globals() {
+        let limits = WasmLimits {
+            min: 1,
+            max: Some(16),
+        };
+        assert_eq!(limits.to_string(), "min=1, max=16");
+        assert_eq!(WasmLimits { min: 0, max: None }.to_string(), "min=0");
+
+        let mut global_type = GlobalType {
+            value_type: WasmValueType::F64,
+            mutability: Mutability::Immutable,
+        };
+        assert_eq!(global_type.to_string(), "f64");
+
+        global_type.mutability = Mutability::Mutable;
+        assert_eq!(global_type.to_string(), "(mut f64)");
+        assert_eq!(Mutability::Mutable.to_string(), "mut");
+    }
+}
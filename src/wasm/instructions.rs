@@ -0,0 +1,127 @@
+use crate::types::{
+    FuncIndex, GlobalIndex, LocalIndex, MemoryIndex, TableIndex, TypeIndex, WasmElementType,
+    WasmValueType,
+};
+use crate::wasm::opcodes::Opcode;
+
+/// The result type annotation of a `block`, `loop` or `if`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockType {
+    /// `0x40`, the block produces no values.
+    Empty,
+    /// The block produces a single value of this type.
+    Value(WasmValueType),
+    /// The block's signature is the function type at this index of the type section.
+    TypeIndex(TypeIndex),
+}
+
+/// The alignment hint and static offset attached to every load and store.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemArg {
+    pub align: u32,
+    pub offset: u32,
+}
+
+/// A single decoded instruction together with its immediates.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction {
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    Br(u32),
+    BrIf(u32),
+    BrTable {
+        targets: Vec<u32>,
+        default: u32,
+    },
+    Call(FuncIndex),
+    CallIndirect {
+        type_index: TypeIndex,
+        table_index: TableIndex,
+    },
+    /// `select` with explicit operand types, from the reference types proposal.
+    SelectTyped(Vec<WasmValueType>),
+    GetLocal(LocalIndex),
+    SetLocal(LocalIndex),
+    TeeLocal(LocalIndex),
+    GetGlobal(GlobalIndex),
+    SetGlobal(GlobalIndex),
+    TableGet(TableIndex),
+    TableSet(TableIndex),
+    /// Any load or store, distinguished by its opcode.
+    Memory(Opcode, MemArg),
+    CurrentMemory(MemoryIndex),
+    GrowMemory(MemoryIndex),
+    I32Const(i32),
+    I64Const(i64),
+    F32Const(f32),
+    F64Const(f64),
+    RefNull(WasmElementType),
+    RefFunc(FuncIndex),
+    /// An `0xfc` prefixed instruction.
+    Extended(ExtendedInstruction),
+    /// An `0xfd` prefixed instruction.
+    Simd(SimdInstruction),
+    /// Any instruction without immediates, such as `end`, `drop` or `i32.add`.
+    Plain(Opcode),
+}
+
+/// Instructions behind the `0xfc` prefix: saturating truncation and bulk memory/table operations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtendedInstruction {
+    I32TruncSatF32S,
+    I32TruncSatF32U,
+    I32TruncSatF64S,
+    I32TruncSatF64U,
+    I64TruncSatF32S,
+    I64TruncSatF32U,
+    I64TruncSatF64S,
+    I64TruncSatF64U,
+    MemoryInit {
+        data_index: u32,
+        memory: MemoryIndex,
+    },
+    DataDrop(u32),
+    MemoryCopy {
+        dst_memory: MemoryIndex,
+        src_memory: MemoryIndex,
+    },
+    MemoryFill(MemoryIndex),
+    TableInit {
+        elem_index: u32,
+        table: TableIndex,
+    },
+    ElemDrop(u32),
+    TableCopy {
+        dst_table: TableIndex,
+        src_table: TableIndex,
+    },
+    TableGrow(TableIndex),
+    TableSize(TableIndex),
+    TableFill(TableIndex),
+}
+
+/// Instructions behind the `0xfd` prefix, grouped by the immediates that follow the sub-opcode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SimdInstruction {
+    /// `v128.load*` and `v128.store`.
+    Memory {
+        opcode: u32,
+        memarg: MemArg,
+    },
+    /// `v128.load*_lane` and `v128.store*_lane`.
+    MemoryLane {
+        opcode: u32,
+        memarg: MemArg,
+        lane: u8,
+    },
+    V128Const([u8; 16]),
+    I8x16Shuffle([u8; 16]),
+    /// `*.extract_lane*` and `*.replace_lane`.
+    Lane {
+        opcode: u32,
+        lane: u8,
+    },
+    /// Any other SIMD instruction, none of which take immediates.
+    Plain(u32),
+}
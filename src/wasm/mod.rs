@@ -1 +1,2 @@
-mod opcodes;
+pub mod instructions;
+pub mod opcodes;
@@ -0,0 +1,260 @@
+use wasm_thing::decode::DecoderConfig;
+use wasm_thing::types::{
+    ConstExpr, DataMode, MemoryType, TableType, WasmElementType, WasmExportDescriptor,
+    WasmExportEntry, WasmFunctionType, WasmImportDescriptor, WasmImportEntry, WasmLimits,
+    WasmValue, WasmValueType,
+};
+use wasm_thing::{decode_bytes, decode_bytes_with_config, encode_module, WasmModuleBuilder};
+
+/// `add.wasm` is the unmodified output of `wat2wasm tests/fixtures/add.wat`.
+const ADD_WASM: &[u8] = include_bytes!("fixtures/add.wasm");
+
+/// `imports.wasm` is `tests/fixtures/imports.wat`, with a function and a memory import.
+const IMPORTS_WASM: &[u8] = include_bytes!("fixtures/imports.wasm");
+
+/// `wasi_hello.wasm` is `wasm-tools parse tests/fixtures/wasi_hello.wat`, a WASI hello world.
+const WASI_HELLO_WASM: &[u8] = include_bytes!("fixtures/wasi_hello.wasm");
+
+/// `features.wasm` is `wasm-tools parse tests/fixtures/features.wat`, which fills every section
+/// the decoder keeps.
+const FEATURES_WASM: &[u8] = include_bytes!("fixtures/features.wasm");
+
+/// `linked.wasm` is `tests/fixtures/linked.rs` compiled for wasm32-unknown-unknown, as laid out
+/// by the linker.
+const LINKED_WASM: &[u8] = include_bytes!("fixtures/linked.wasm");
+
+/// `vtable.wasm` is `tests/fixtures/vtable.rs` compiled like `linked.wasm`; the vtables of its
+/// trait objects end up in the table. The linker pads the relocated integers in its function
+/// bodies, so it only decodes without `strict_leb128`.
+const VTABLE_WASM: &[u8] = include_bytes!("fixtures/vtable.wasm");
+
+#[test]
+fn decodes_wat2wasm_output() {
+    assert!(decode_bytes(ADD_WASM).is_ok());
+}
+
+#[test]
+fn decodes_imports_fixture() {
+    let module = decode_bytes(IMPORTS_WASM).unwrap();
+
+    let imports: Vec<_> = module
+        .imports()
+        .iter()
+        .map(|import| (import.module_name(), import.field_name()))
+        .collect();
+    assert_eq!(imports, [("env", "log"), ("env", "memory")]);
+    assert_eq!(module.memory_requirements().unwrap().max(), Some(16));
+}
+
+#[test]
+fn wasi_hello_imports_only_from_wasi() {
+    let module = decode_bytes(WASI_HELLO_WASM).unwrap();
+
+    let wasi_imports: Vec<_> = module
+        .imports_from("wasi_snapshot_preview1")
+        .map(|import| import.field_name())
+        .collect();
+    assert_eq!(wasi_imports, ["fd_write", "proc_exit"]);
+    assert_eq!(wasi_imports.len(), module.imports().len());
+    assert_eq!(module.imports_from("env").count(), 0);
+
+    let type_indices: Vec<u32> = module.imported_functions().map(u32::from).collect();
+    assert_eq!(type_indices, [0, 1]);
+    assert_eq!(module.imported_memories().count(), 0);
+    assert_eq!(module.imported_globals().count(), 0);
+    let spaces = module.index_spaces();
+    let imported = [
+        spaces.functions(),
+        spaces.tables(),
+        spaces.memories(),
+        spaces.globals(),
+    ];
+    assert_eq!(imported.map(|space| space.imported()), [2, 0, 0, 0]);
+
+    let [segment] = module.data() else {
+        panic!("expected a single data segment");
+    };
+    assert_eq!(
+        segment.mode(),
+        DataMode::Active {
+            memory_index: 0.into(),
+            offset: ConstExpr::I32Const(8)
+        }
+    );
+    assert_eq!(segment.data(), b"hello, world\n");
+
+    let imports = decode_bytes(IMPORTS_WASM).unwrap();
+    let spaces = imports.index_spaces();
+    let imported = [
+        spaces.functions(),
+        spaces.tables(),
+        spaces.memories(),
+        spaces.globals(),
+    ];
+    assert_eq!(imported.map(|space| space.imported()), [1, 0, 1, 0]);
+}
+
+#[test]
+fn decodes_features_fixture() {
+    let module = decode_bytes(FEATURES_WASM).unwrap();
+
+    assert_eq!(module.types().len(), 3);
+    assert_eq!(module.imports().len(), 3);
+    assert_eq!(module.memories().len(), 1);
+    assert_eq!(module.tags().len(), 1);
+    assert_eq!(module.globals().len(), 3);
+    assert_eq!(module.exports().len(), 4);
+    assert_eq!(module.code().len(), 2);
+    assert_eq!(module.data().len(), 2);
+}
+
+#[test]
+fn rejects_wrong_magic() {
+    let mut bytes = ADD_WASM.to_vec();
+    bytes[3] = 0x64;
+
+    let error = decode_bytes(&bytes).err().unwrap();
+    assert!(error
+        .to_string()
+        .contains("expected 00 61 73 6d, found 00 61 73 64"));
+}
+
+#[test]
+fn reports_offsets_of_corrupted_bytes() {
+    // (position, replacement byte, expected error offset)
+    let corruptions = [
+        (11, 0x5f, 11), // function type marker
+        (14, 0x40, 14), // second parameter type
+        (18, 0x03, 19), // function section size, reported at the start of its contents
+        (21, 0x01, 21), // export section id, now that of a second type section
+        (39, 0x06, 39), // i32.add
+        (40, 0x01, 41), // the final end, reported at the end of the body
+    ];
+
+    for (position, byte, offset) in corruptions {
+        let mut bytes = ADD_WASM.to_vec();
+        bytes[position] = byte;
+
+        let error = decode_bytes(&bytes).err().unwrap();
+        assert_eq!(
+            error.offset(),
+            Some(offset),
+            "corrupting byte {position}: {error}"
+        );
+    }
+}
+
+#[test]
+fn evaluates_linker_globals() {
+    let module = decode_bytes(LINKED_WASM).unwrap();
+
+    let global = |name| {
+        let export = module
+            .exports()
+            .iter()
+            .find(|export| export.name() == name)
+            .unwrap();
+        assert!(matches!(
+            export.descriptor(),
+            WasmExportDescriptor::Global(_)
+        ));
+        module.global_initial_value(export.descriptor().index())
+    };
+
+    // The stack grows down from 1 MiB, the static data follows it, then the heap.
+    assert_eq!(module.global_initial_value(0), Ok(WasmValue::I32(1 << 20)));
+    assert_eq!(global("__data_end"), Ok(WasmValue::I32((1 << 20) + 6)));
+    assert_eq!(global("__heap_base"), Ok(WasmValue::I32((1 << 20) + 16)));
+}
+
+#[test]
+fn reconstructs_vtable_table() {
+    let config = DecoderConfig::new().strict_leb128(false);
+    let module = decode_bytes_with_config(VTABLE_WASM, &config).unwrap();
+    let table = module.table_image(0).unwrap();
+
+    // The linker leaves index 0 null, so calling through a null function pointer traps.
+    let indices: Vec<_> = table.iter().map(|slot| slot.map(u32::from)).collect();
+    assert_eq!(indices, [None, Some(2), Some(3), Some(0), Some(1)]);
+
+    // Every element is a method of a `Shape` implementation: `fn(&self) -> u32`.
+    for func_index in table.into_iter().flatten() {
+        let signature = module.function_signature(func_index.into()).unwrap();
+        assert_eq!(signature.to_string(), "(i32) -> i32");
+    }
+}
+
+#[test]
+fn strips_a_custom_section() {
+    let mut module = decode_bytes(LINKED_WASM).unwrap();
+    assert_eq!(encode_module(&module), LINKED_WASM);
+
+    // The producers section starts where the section before it ends.
+    let sections = module.sections();
+    let end = |index: usize| (sections[index].offset() + sections[index].length() as u64) as usize;
+    let index = sections
+        .iter()
+        .position(|section| LINKED_WASM[section.offset() as usize..].starts_with(b"\x09producers"))
+        .unwrap();
+    let (start, end) = (end(index - 1), end(index));
+    let expected = [&LINKED_WASM[..start], &LINKED_WASM[end..]].concat();
+
+    assert!(module.remove_custom_section("producers"));
+    let stripped = encode_module(&module);
+    assert_eq!(stripped, expected);
+
+    let names: Vec<_> = decode_bytes(&stripped)
+        .unwrap()
+        .custom_sections()
+        .map(|section| section.name().to_string())
+        .collect();
+    assert_eq!(names, ["name", "target_features"]);
+}
+
+#[test]
+fn builds_imports_and_exports_from_public_types() {
+    let mut builder = WasmModuleBuilder::new();
+    let log = builder.add_type(WasmFunctionType::new(vec![WasmValueType::I32], vec![]));
+    builder.add_import("env", "log", WasmImportDescriptor::Function(log));
+    builder.add_import(
+        "env",
+        "table",
+        WasmImportDescriptor::Table(TableType::new(
+            WasmElementType::Funcref,
+            WasmLimits::new(1, None),
+        )),
+    );
+    builder.add_import(
+        "env",
+        "memory",
+        WasmImportDescriptor::Memory(MemoryType::new(WasmLimits::new(1, Some(2)))),
+    );
+    builder.add_export("log", WasmExportDescriptor::Function(0.into()));
+
+    let module = builder.build().unwrap();
+
+    let expected = [
+        WasmImportEntry::new("env", "log", WasmImportDescriptor::Function(0.into())),
+        WasmImportEntry::new(
+            "env",
+            "table",
+            WasmImportDescriptor::Table(TableType::new(
+                WasmElementType::Funcref,
+                WasmLimits::new(1, None),
+            )),
+        ),
+        WasmImportEntry::new(
+            "env",
+            "memory",
+            WasmImportDescriptor::Memory(MemoryType::new(WasmLimits::new(1, Some(2)))),
+        ),
+    ];
+    assert_eq!(module.imports(), expected);
+    assert_eq!(
+        module.exports(),
+        [WasmExportEntry::new(
+            "log",
+            WasmExportDescriptor::Function(0.into())
+        )]
+    );
+}
@@ -0,0 +1,22 @@
+(module
+  (type $binary (func (param i32 i32) (result i32)))
+  (import "env" "log" (func $log (param i32)))
+  (import "env" "table" (table 1 funcref))
+  (import "env" "base" (global $base i32))
+  (tag $oops (param i32))
+  (memory (export "memory") 1 2)
+  (global $counter (mut i64) (i64.const -1))
+  (global $callback funcref (ref.func $add))
+  (global $scale f64 (f64.const 0.5))
+  (func $add (export "add") (type $binary)
+    (local $tmp i32)
+    (local.set $tmp (i32.add (local.get 0) (local.get 1)))
+    (call $log (local.get $tmp))
+    (local.get $tmp))
+  (func (export "start") (result i32 i32)
+    (block (result i32 i32)
+      (i32.const 1)
+      (global.get $base)))
+  (export "counter" (global $counter))
+  (data (global.get $base) "active")
+  (data "passive\00"))
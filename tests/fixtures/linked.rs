@@ -0,0 +1,16 @@
+// Built with:
+//   rustc --target wasm32-unknown-unknown --crate-type cdylib -C opt-level=s -C panic=abort \
+//       -C strip=debuginfo linked.rs -o linked.wasm
+#![no_std]
+
+static GREETING: [u8; 6] = *b"hello\0";
+
+#[no_mangle]
+pub extern "C" fn greeting() -> *const u8 {
+    GREETING.as_ptr()
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
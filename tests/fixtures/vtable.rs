@@ -0,0 +1,46 @@
+// Built with:
+//   rustc --target wasm32-unknown-unknown --crate-type cdylib -C opt-level=s -C panic=abort \
+//       -C strip=debuginfo vtable.rs -o vtable.wasm
+#![no_std]
+
+pub trait Shape {
+    fn area(&self) -> u32;
+    fn sides(&self) -> u32;
+}
+
+struct Square(u32);
+struct Rect(u32, u32);
+
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+
+    fn sides(&self) -> u32 {
+        4
+    }
+}
+
+impl Shape for Rect {
+    fn area(&self) -> u32 {
+        self.0 * self.1
+    }
+
+    fn sides(&self) -> u32 {
+        4 + self.0 % 2
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn area(kind: u32, a: u32, b: u32) -> u32 {
+    let square = Square(a);
+    let rect = Rect(a, b);
+    let shape: &dyn Shape = if kind == 0 { &square } else { &rect };
+    let shape = core::hint::black_box(shape);
+    shape.area() + shape.sides()
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
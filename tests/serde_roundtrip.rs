@@ -0,0 +1,35 @@
+#![cfg(feature = "serde")]
+
+use wasm_thing::module::ModuleInterface;
+use wasm_thing::{decode_bytes, WasmModule};
+
+const FIXTURES: [&[u8]; 4] = [
+    include_bytes!("fixtures/add.wasm"),
+    include_bytes!("fixtures/imports.wasm"),
+    include_bytes!("fixtures/wasi_hello.wasm"),
+    include_bytes!("fixtures/features.wasm"),
+];
+
+#[test]
+fn modules_round_trip_through_json() {
+    for bytes in FIXTURES {
+        let module = decode_bytes(bytes).unwrap();
+
+        let json = serde_json::to_string(&module).unwrap();
+        let decoded: WasmModule = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, module);
+    }
+}
+
+#[test]
+fn interfaces_round_trip_through_json() {
+    for bytes in FIXTURES {
+        let interface = decode_bytes(bytes).unwrap().interface();
+
+        let json = serde_json::to_string(&interface).unwrap();
+        let decoded: ModuleInterface = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, interface);
+    }
+}